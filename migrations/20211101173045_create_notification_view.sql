@@ -0,0 +1,11 @@
+-- Add migration script here
+-- merged inbox of comment and post notifications, with enough context to display each one
+CREATE OR REPLACE VIEW detailed_notification_view AS
+SELECT comment_notifications.notification_id, 'comment'::TEXT AS notification_source, comment_notifications.notification_type, comment_notifications.user_id, comment_notifications.is_read, comment_notifications.created_at, comments.post_id, posts.title AS post_title, posts.guild_tag, comments.comment_id, comments.body AS comment_body, users.username AS actor_username
+FROM (((comment_notifications INNER JOIN comments ON comment_notifications.comment_id = comments.comment_id) INNER JOIN posts ON comments.post_id = posts.post_id) INNER JOIN users ON comments.user_id = users.user_id)
+UNION ALL
+SELECT post_notifications.notification_id, 'post'::TEXT AS notification_source, post_notifications.notification_type, post_notifications.user_id, post_notifications.is_read, post_notifications.created_at, posts.post_id, posts.title AS post_title, posts.guild_tag, NULL::INTEGER AS comment_id, NULL::TEXT AS comment_body, users.username AS actor_username
+FROM ((post_notifications INNER JOIN posts ON post_notifications.post_id = posts.post_id) INNER JOIN users ON posts.user_id = users.user_id);
+
+CREATE INDEX IF NOT EXISTS comment_notifications_user_id_idx ON comment_notifications (user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS post_notifications_user_id_idx ON post_notifications (user_id, created_at DESC);
@@ -0,0 +1,8 @@
+-- Add migration script here
+-- expression indexes for full text search. queries in search/model.rs need to use these exact expressions to hit the index
+CREATE INDEX IF NOT EXISTS posts_search_idx ON posts USING GIN (to_tsvector('english', title || ' ' || coalesce(body, '')));
+CREATE INDEX IF NOT EXISTS comments_search_idx ON comments USING GIN (to_tsvector('english', body));
+CREATE INDEX IF NOT EXISTS guilds_search_idx ON guilds USING GIN (to_tsvector('english', guild_name || ' ' || coalesce(guild_description, '')));
+
+-- prefix matching on usernames
+CREATE INDEX IF NOT EXISTS users_username_prefix_idx ON users (username text_pattern_ops);
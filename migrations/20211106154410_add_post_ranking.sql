@@ -0,0 +1,47 @@
+-- Add migration script here
+-- ranking columns for sorted feeds. hot_rank is reddit's formula, the age part is baked in at post creation
+-- so newer posts always get a head start and nothing has to be recalculated on a schedule
+ALTER TABLE post_aggregates ADD COLUMN IF NOT EXISTS score INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE post_aggregates ADD COLUMN IF NOT EXISTS hot_rank DOUBLE PRECISION NOT NULL DEFAULT 0;
+ALTER TABLE post_aggregates ADD COLUMN IF NOT EXISTS controversy_rank DOUBLE PRECISION NOT NULL DEFAULT 0;
+
+CREATE OR REPLACE FUNCTION hot_rank(score INTEGER, created_at TIMESTAMP)
+RETURNS DOUBLE PRECISION AS $$
+SELECT sign(score) * log(greatest(abs(score), 1)) + (EXTRACT(EPOCH FROM created_at) - 1134028003) / 45000;
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE OR REPLACE FUNCTION controversy_rank(upvotes INTEGER, downvotes INTEGER)
+RETURNS DOUBLE PRECISION AS $$
+SELECT CASE
+    WHEN upvotes <= 0 OR downvotes <= 0 THEN 0
+    ELSE power(upvotes + downvotes, CASE WHEN downvotes > upvotes THEN upvotes::DOUBLE PRECISION / downvotes ELSE downvotes::DOUBLE PRECISION / upvotes END)
+END;
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- recalculate ranks whenever the vote triggers touch the aggregates
+CREATE OR REPLACE FUNCTION post_agg_update_ranks()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+new.score = new.upvotes - new.downvotes;
+new.hot_rank = hot_rank(new.score, (SELECT created_at FROM posts WHERE posts.post_id = new.post_id));
+new.controversy_rank = controversy_rank(new.upvotes, new.downvotes);
+RETURN NEW;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+CREATE TRIGGER post_agg_ranks BEFORE INSERT OR UPDATE OF upvotes, downvotes ON post_aggregates FOR EACH ROW EXECUTE PROCEDURE post_agg_update_ranks();
+
+-- backfill existing posts
+UPDATE post_aggregates SET upvotes = upvotes;
+
+CREATE UNIQUE INDEX IF NOT EXISTS post_aggregates_post_id_idx ON post_aggregates (post_id);
+CREATE INDEX IF NOT EXISTS post_aggregates_hot_idx ON post_aggregates (hot_rank DESC, post_id DESC);
+CREATE INDEX IF NOT EXISTS post_aggregates_score_idx ON post_aggregates (score DESC, post_id DESC);
+CREATE INDEX IF NOT EXISTS post_aggregates_controversy_idx ON post_aggregates (controversy_rank DESC, post_id DESC);
+CREATE INDEX IF NOT EXISTS posts_created_at_idx ON posts (created_at DESC, post_id DESC);
+CREATE INDEX IF NOT EXISTS posts_guild_tag_created_at_idx ON posts (guild_tag, created_at DESC, post_id DESC);
+
+-- expose the ranks on the view, and drop the built in ordering so queries can pick their own (and use the indexes above)
+CREATE OR REPLACE VIEW detailed_post_view AS
+SELECT posts.post_id, posts.guild_tag, posts.image_url, posts.link_url, posts.title, posts.body, posts.is_locked, posts.is_edited, posts.created_at, users.username, users.avatar_url, users.is_admin, users.is_verified, post_aggregates.upvotes, post_aggregates.downvotes, post_aggregates.replies, post_aggregates.score, post_aggregates.hot_rank, post_aggregates.controversy_rank
+FROM ((posts INNER JOIN users ON posts.user_id = users.user_id) INNER JOIN post_aggregates ON posts.post_id = post_aggregates.post_id);
@@ -0,0 +1,18 @@
+-- Add migration script here
+-- indexes matching the (sort key, id) order of each list query, so cursor pages are an index range scan instead of an OFFSET
+CREATE INDEX IF NOT EXISTS posts_user_id_created_at_idx ON posts (user_id, created_at DESC, post_id DESC);
+CREATE INDEX IF NOT EXISTS comments_post_id_created_at_idx ON comments (post_id, created_at, comment_id);
+CREATE INDEX IF NOT EXISTS comments_user_id_created_at_idx ON comments (user_id, created_at DESC, comment_id DESC);
+CREATE INDEX IF NOT EXISTS bookmarks_user_id_created_at_idx ON bookmarks (user_id, created_at DESC, bookmark_id DESC);
+CREATE INDEX IF NOT EXISTS reports_created_at_idx ON reports (created_at DESC, report_id DESC);
+CREATE INDEX IF NOT EXISTS guild_aggregates_members_idx ON guild_aggregates (members DESC, guild_tag);
+
+-- the old ORDER BY members ran on every query against the view, the list query orders it now
+CREATE OR REPLACE VIEW short_guild_view AS
+SELECT guilds.guild_tag, guilds.guild_name, guilds.avatar_url, guild_aggregates.members, guild_aggregates.number_of_posts
+FROM (guilds INNER JOIN guild_aggregates ON guild_aggregates.guild_tag = guilds.guild_tag);
+
+DROP INDEX IF EXISTS comment_notifications_user_id_idx;
+DROP INDEX IF EXISTS post_notifications_user_id_idx;
+CREATE INDEX IF NOT EXISTS comment_notifications_user_id_idx ON comment_notifications (user_id, created_at DESC, notification_id DESC);
+CREATE INDEX IF NOT EXISTS post_notifications_user_id_idx ON post_notifications (user_id, created_at DESC, notification_id DESC);
@@ -0,0 +1,13 @@
+-- Add migration script here
+-- lower bound of the wilson score interval at 95% confidence, the "best" sort for comment siblings.
+-- a comment with 10 up and 1 down beats one with 1 up and 0 down, unlike a plain up/(up + down) ratio
+CREATE OR REPLACE FUNCTION comment_best_rank(upvotes INTEGER, downvotes INTEGER)
+RETURNS DOUBLE PRECISION AS $$
+SELECT CASE WHEN upvotes + downvotes = 0 THEN 0
+ELSE ((upvotes + 1.9208) / (upvotes + downvotes) - 1.96 * sqrt((upvotes * downvotes)::DOUBLE PRECISION / (upvotes + downvotes) + 0.9604) / (upvotes + downvotes)) / (1 + 3.8416 / (upvotes + downvotes))
+END;
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- comment trees are walked parent to children
+CREATE INDEX IF NOT EXISTS comments_parent_comment_id_idx ON comments (parent_comment_id);
+CREATE INDEX IF NOT EXISTS comment_aggregates_comment_id_idx ON comment_aggregates (comment_id);
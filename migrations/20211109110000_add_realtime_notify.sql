@@ -0,0 +1,79 @@
+-- Add migration script here
+-- every backend instance LISTENs on realtime_events and forwards these to its connected clients.
+-- payloads only carry ids (NOTIFY is capped at 8000 bytes), clients fetch the rest from the /view routes.
+-- topics: user:<user_id> for notifications, post:<post_id> for comments and votes, guild:<guild_tag> for new posts
+CREATE OR REPLACE FUNCTION notify_realtime(topic TEXT, event TEXT, data JSON)
+RETURNS VOID AS $$
+SELECT pg_notify('realtime_events', json_build_object('topic', topic, 'event', event, 'data', data)::TEXT);
+$$ LANGUAGE SQL;
+
+-- create comment, now also tells the post's subscribers
+CREATE OR REPLACE FUNCTION create_comment_aggregates()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+INSERT INTO comment_aggregates (comment_id)
+VALUES (new.comment_id);
+UPDATE post_aggregates
+SET replies = (replies + 1)
+WHERE post_id = new.post_id;
+UPDATE user_aggregates
+SET number_of_comments = (number_of_comments + 1)
+WHERE user_id = new.user_id;
+PERFORM notify_realtime('post:' || new.post_id, 'comment', json_build_object('post_id', new.post_id, 'comment_id', new.comment_id, 'parent_comment_id', new.parent_comment_id));
+RETURN NEW;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+-- create post, now also tells the guild's subscribers
+CREATE OR REPLACE FUNCTION create_post_aggregates()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+INSERT INTO post_aggregates (post_id)
+VALUES (new.post_id);
+UPDATE guild_aggregates
+SET number_of_posts = (number_of_posts + 1)
+WHERE guild_tag = new.guild_tag;
+UPDATE user_aggregates
+SET number_of_posts = (number_of_posts + 1)
+WHERE user_id = new.user_id;
+PERFORM notify_realtime('guild:' || new.guild_tag, 'post', json_build_object('guild_tag', new.guild_tag, 'post_id', new.post_id));
+RETURN NEW;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+-- vote changes go out with the recalculated score
+CREATE OR REPLACE FUNCTION post_agg_update_ranks()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+new.score = new.upvotes - new.downvotes;
+new.hot_rank = hot_rank(new.score, (SELECT created_at FROM posts WHERE posts.post_id = new.post_id));
+new.controversy_rank = controversy_rank(new.upvotes, new.downvotes);
+IF TG_OP = 'UPDATE' THEN
+    PERFORM notify_realtime('post:' || new.post_id, 'votes', json_build_object('post_id', new.post_id, 'upvotes', new.upvotes, 'downvotes', new.downvotes, 'score', new.score));
+END IF;
+RETURN NEW;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+-- notifications, however they were created
+CREATE OR REPLACE FUNCTION notify_comment_notification()
+RETURNS TRIGGER AS $noti_notify$
+BEGIN
+PERFORM notify_realtime('user:' || new.user_id, 'notification', json_build_object('notification_source', 'comment', 'notification_id', new.notification_id, 'notification_type', new.notification_type, 'comment_id', new.comment_id));
+RETURN NEW;
+END;
+$noti_notify$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS comment_noti_notify ON comment_notifications;
+CREATE TRIGGER comment_noti_notify AFTER INSERT ON comment_notifications FOR EACH ROW EXECUTE PROCEDURE notify_comment_notification();
+
+CREATE OR REPLACE FUNCTION notify_post_notification()
+RETURNS TRIGGER AS $noti_notify$
+BEGIN
+PERFORM notify_realtime('user:' || new.user_id, 'notification', json_build_object('notification_source', 'post', 'notification_id', new.notification_id, 'notification_type', new.notification_type, 'post_id', new.post_id));
+RETURN NEW;
+END;
+$noti_notify$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS post_noti_notify ON post_notifications;
+CREATE TRIGGER post_noti_notify AFTER INSERT ON post_notifications FOR EACH ROW EXECUTE PROCEDURE notify_post_notification();
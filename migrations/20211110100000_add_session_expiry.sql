@@ -0,0 +1,8 @@
+-- Add migration script here
+-- sessions expire after SESSION_IDLE_TIMEOUT_MINUTES without a request, or SESSION_ABSOLUTE_TIMEOUT_HOURS after login,
+-- whichever comes first. expired rows are deleted by the purge task in main
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS user_sessions_user_id_idx ON user_sessions (user_id);
+CREATE INDEX IF NOT EXISTS user_sessions_last_seen_at_idx ON user_sessions (last_seen_at);
+CREATE INDEX IF NOT EXISTS user_sessions_created_at_idx ON user_sessions (created_at);
@@ -0,0 +1,8 @@
+-- Add migration script here
+-- what a user sees when managing their logged in devices. device_id is what the client refers to,
+-- session_id stays secret inside the encrypted cookie
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS device_id SERIAL;
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS user_agent VARCHAR(512);
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS ip_address VARCHAR(64);
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_sessions_device_id_idx ON user_sessions (device_id);
@@ -0,0 +1,152 @@
+-- Add migration script here
+-- guilds get a surrogate guild_id so guild_tag can change. memberships, posts and aggregates point at the id,
+-- and guild_tag stays unique on guilds as the name used in urls
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS guild_id SERIAL NOT NULL;
+ALTER TABLE guilds ADD CONSTRAINT guilds_guild_id_key UNIQUE (guild_id);
+
+ALTER TABLE guild_memberships ADD COLUMN IF NOT EXISTS guild_id INTEGER;
+ALTER TABLE posts ADD COLUMN IF NOT EXISTS guild_id INTEGER;
+ALTER TABLE guild_aggregates ADD COLUMN IF NOT EXISTS guild_id INTEGER;
+UPDATE guild_memberships SET guild_id = guilds.guild_id FROM guilds WHERE guilds.guild_tag = guild_memberships.guild_tag;
+UPDATE posts SET guild_id = guilds.guild_id FROM guilds WHERE guilds.guild_tag = posts.guild_tag;
+UPDATE guild_aggregates SET guild_id = guilds.guild_id FROM guilds WHERE guilds.guild_tag = guild_aggregates.guild_tag;
+ALTER TABLE guild_memberships ALTER COLUMN guild_id SET NOT NULL;
+ALTER TABLE posts ALTER COLUMN guild_id SET NOT NULL;
+ALTER TABLE guild_aggregates ALTER COLUMN guild_id SET NOT NULL;
+
+-- these select the old guild_tag columns, they're recreated below
+DROP VIEW IF EXISTS detailed_post_view;
+DROP VIEW IF EXISTS detailed_guild_view;
+DROP VIEW IF EXISTS short_guild_view;
+DROP VIEW IF EXISTS detailed_notification_view;
+
+ALTER TABLE guild_memberships DROP CONSTRAINT IF EXISTS guild_memberships_guild_tag_fkey;
+ALTER TABLE posts DROP CONSTRAINT IF EXISTS posts_guild_tag_fkey;
+ALTER TABLE guild_aggregates DROP CONSTRAINT IF EXISTS guild_aggregates_guild_tag_fkey;
+DROP INDEX IF EXISTS posts_guild_tag_created_at_idx;
+DROP INDEX IF EXISTS guild_aggregates_members_idx;
+ALTER TABLE guild_memberships DROP COLUMN guild_tag;
+ALTER TABLE posts DROP COLUMN guild_tag;
+ALTER TABLE guild_aggregates DROP COLUMN guild_tag;
+
+ALTER TABLE guilds DROP CONSTRAINT guilds_pkey;
+ALTER TABLE guilds DROP CONSTRAINT guilds_guild_id_key;
+ALTER TABLE guilds ADD PRIMARY KEY (guild_id);
+ALTER TABLE guilds ADD CONSTRAINT guilds_guild_tag_key UNIQUE (guild_tag);
+
+ALTER TABLE guild_memberships ADD FOREIGN KEY (guild_id) REFERENCES guilds(guild_id) ON DELETE CASCADE;
+ALTER TABLE posts ADD FOREIGN KEY (guild_id) REFERENCES guilds(guild_id) ON DELETE SET NULL;
+ALTER TABLE guild_aggregates ADD FOREIGN KEY (guild_id) REFERENCES guilds(guild_id) ON DELETE CASCADE;
+CREATE UNIQUE INDEX IF NOT EXISTS guild_aggregates_guild_id_idx ON guild_aggregates (guild_id);
+CREATE UNIQUE INDEX IF NOT EXISTS guild_memberships_user_id_guild_id_idx ON guild_memberships (user_id, guild_id);
+CREATE INDEX IF NOT EXISTS guild_memberships_guild_id_idx ON guild_memberships (guild_id, membership_id);
+CREATE INDEX IF NOT EXISTS posts_guild_id_created_at_idx ON posts (guild_id, created_at DESC, post_id DESC);
+CREATE INDEX IF NOT EXISTS guild_aggregates_members_idx ON guild_aggregates (members DESC, guild_id);
+
+-- tags a guild used to have. they keep resolving to the guild so old links work, and can't be taken by another guild
+CREATE TABLE IF NOT EXISTS guild_tag_aliases (
+    guild_tag VARCHAR(20) NOT NULL PRIMARY KEY,
+    guild_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(guild_id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS guild_tag_aliases_guild_id_idx ON guild_tag_aliases (guild_id);
+
+-- current tag first, then old ones
+CREATE OR REPLACE FUNCTION resolve_guild_tag(tag VARCHAR)
+RETURNS INTEGER AS $$
+SELECT coalesce(
+    (SELECT guild_id FROM guilds WHERE guild_tag = lower(tag)),
+    (SELECT guild_id FROM guild_tag_aliases WHERE guild_tag = lower(tag))
+);
+$$ LANGUAGE SQL STABLE;
+
+-- aggregate and realtime triggers, keyed on guild_id now
+CREATE OR REPLACE FUNCTION create_guild_aggregates()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+INSERT INTO guild_aggregates (guild_id)
+VALUES (new.guild_id);
+RETURN NEW;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION create_post_aggregates()
+RETURNS TRIGGER AS $agg_create$
+DECLARE
+    post_guild_tag VARCHAR(20);
+BEGIN
+INSERT INTO post_aggregates (post_id)
+VALUES (new.post_id);
+UPDATE guild_aggregates
+SET number_of_posts = (number_of_posts + 1)
+WHERE guild_id = new.guild_id;
+UPDATE user_aggregates
+SET number_of_posts = (number_of_posts + 1)
+WHERE user_id = new.user_id;
+SELECT guild_tag INTO post_guild_tag FROM guilds WHERE guild_id = new.guild_id;
+PERFORM notify_realtime('guild:' || post_guild_tag, 'post', json_build_object('guild_id', new.guild_id, 'guild_tag', post_guild_tag, 'post_id', new.post_id));
+RETURN NEW;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION post_delete_aggregates()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+IF old.guild_id IS NOT NULL THEN
+    UPDATE guild_aggregates
+    SET number_of_posts = (number_of_posts - 1)
+    WHERE guild_id = old.guild_id;
+END IF;
+UPDATE user_aggregates
+SET number_of_posts = (number_of_posts - 1)
+WHERE user_id = old.user_id;
+RETURN OLD;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION guild_agg_new_member()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+UPDATE guild_aggregates
+SET members = (members + 1)
+WHERE guild_id = new.guild_id;
+UPDATE user_aggregates
+SET number_of_memberships = (number_of_memberships + 1)
+WHERE user_id = new.user_id;
+RETURN NEW;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION guild_agg_member_leaves()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+UPDATE guild_aggregates
+SET members = (members - 1)
+WHERE guild_id = old.guild_id;
+UPDATE user_aggregates
+SET number_of_memberships = (number_of_memberships - 1)
+WHERE user_id = old.user_id;
+RETURN OLD;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+-- views pick up the current tag from guilds, so a rename shows up everywhere at once
+CREATE OR REPLACE VIEW detailed_post_view AS
+SELECT posts.post_id, posts.guild_id, guilds.guild_tag, posts.image_url, posts.link_url, posts.title, posts.body, posts.is_locked, posts.is_edited, posts.created_at, users.username, users.avatar_url, users.is_admin, users.is_verified, post_aggregates.upvotes, post_aggregates.downvotes, post_aggregates.replies, post_aggregates.score, post_aggregates.hot_rank, post_aggregates.controversy_rank
+FROM (((posts INNER JOIN users ON posts.user_id = users.user_id) INNER JOIN post_aggregates ON posts.post_id = post_aggregates.post_id) INNER JOIN guilds ON posts.guild_id = guilds.guild_id);
+
+CREATE OR REPLACE VIEW detailed_guild_view AS
+SELECT guilds.guild_id, guilds.guild_tag, guilds.guild_name, guilds.guild_description, guilds.avatar_url, guilds.banner_url, guilds.is_banned, guilds.created_at, guild_aggregates.members, guild_aggregates.number_of_posts
+FROM (guilds INNER JOIN guild_aggregates ON guild_aggregates.guild_id = guilds.guild_id);
+
+CREATE OR REPLACE VIEW short_guild_view AS
+SELECT guilds.guild_id, guilds.guild_tag, guilds.guild_name, guilds.avatar_url, guild_aggregates.members, guild_aggregates.number_of_posts
+FROM (guilds INNER JOIN guild_aggregates ON guild_aggregates.guild_id = guilds.guild_id);
+
+CREATE OR REPLACE VIEW detailed_notification_view AS
+SELECT comment_notifications.notification_id, 'comment'::TEXT AS notification_source, comment_notifications.notification_type, comment_notifications.user_id, comment_notifications.is_read, comment_notifications.created_at, comments.post_id, posts.title AS post_title, guilds.guild_tag, comments.comment_id, comments.body AS comment_body, users.username AS actor_username
+FROM ((((comment_notifications INNER JOIN comments ON comment_notifications.comment_id = comments.comment_id) INNER JOIN posts ON comments.post_id = posts.post_id) INNER JOIN guilds ON posts.guild_id = guilds.guild_id) INNER JOIN users ON comments.user_id = users.user_id)
+UNION ALL
+SELECT post_notifications.notification_id, 'post'::TEXT AS notification_source, post_notifications.notification_type, post_notifications.user_id, post_notifications.is_read, post_notifications.created_at, posts.post_id, posts.title AS post_title, guilds.guild_tag, NULL::INTEGER AS comment_id, NULL::TEXT AS comment_body, users.username AS actor_username
+FROM (((post_notifications INNER JOIN posts ON post_notifications.post_id = posts.post_id) INNER JOIN guilds ON posts.guild_id = guilds.guild_id) INNER JOIN users ON posts.user_id = users.user_id);
@@ -0,0 +1,29 @@
+-- Add migration script here
+-- one row per guild moderation or site admin action, written in the same transaction as the action itself.
+-- guild_tag and target_username are copied at the time of the action, so entries still read right after the
+-- guild or user is deleted. target post and comment ids aren't foreign keys because removing them is the action
+CREATE TABLE IF NOT EXISTS moderation_actions (
+    action_id SERIAL NOT NULL PRIMARY KEY,
+    actor_id INTEGER,
+    action VARCHAR(30) NOT NULL,
+    guild_id INTEGER,
+    guild_tag VARCHAR(20),
+    target_user_id INTEGER,
+    target_username VARCHAR(20),
+    target_post_id INTEGER,
+    target_comment_id INTEGER,
+    reason TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (actor_id) REFERENCES users(user_id) ON DELETE SET NULL,
+    FOREIGN KEY (guild_id) REFERENCES guilds(guild_id) ON DELETE SET NULL,
+    FOREIGN KEY (target_user_id) REFERENCES users(user_id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS moderation_actions_created_at_idx ON moderation_actions (created_at DESC, action_id DESC);
+CREATE INDEX IF NOT EXISTS moderation_actions_guild_id_created_at_idx ON moderation_actions (guild_id, created_at DESC, action_id DESC);
+CREATE INDEX IF NOT EXISTS moderation_actions_actor_id_idx ON moderation_actions (actor_id);
+
+-- current names where the guild and users still exist
+CREATE OR REPLACE VIEW detailed_moderation_action_view AS
+SELECT moderation_actions.action_id, moderation_actions.action, moderation_actions.guild_id, coalesce(guilds.guild_tag, moderation_actions.guild_tag) AS guild_tag, moderation_actions.actor_id, actors.username AS actor_username, moderation_actions.target_user_id, coalesce(targets.username, moderation_actions.target_username) AS target_username, moderation_actions.target_post_id, moderation_actions.target_comment_id, moderation_actions.reason, moderation_actions.created_at
+FROM (((moderation_actions LEFT JOIN users actors ON moderation_actions.actor_id = actors.user_id) LEFT JOIN users targets ON moderation_actions.target_user_id = targets.user_id) LEFT JOIN guilds ON moderation_actions.guild_id = guilds.guild_id);
@@ -0,0 +1,30 @@
+-- Add migration script here
+-- reports belong to the guild the reported content is in, and remember who made them and how they were resolved
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS guild_id INTEGER REFERENCES guilds(guild_id) ON DELETE CASCADE;
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS reporter_id INTEGER REFERENCES users(user_id) ON DELETE SET NULL;
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS resolution VARCHAR(20);
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS resolved_by INTEGER REFERENCES users(user_id) ON DELETE SET NULL;
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS resolved_at TIMESTAMP;
+
+UPDATE reports SET guild_id = posts.guild_id FROM posts WHERE posts.post_id = reports.post_id;
+UPDATE reports SET guild_id = posts.guild_id FROM comments INNER JOIN posts ON posts.post_id = comments.post_id WHERE comments.comment_id = reports.comment_id;
+
+-- one report per user per post or comment
+CREATE UNIQUE INDEX IF NOT EXISTS reports_reporter_id_post_id_idx ON reports (reporter_id, post_id) WHERE post_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS reports_reporter_id_comment_id_idx ON reports (reporter_id, comment_id) WHERE comment_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS reports_open_guild_id_idx ON reports (guild_id, report_id) WHERE NOT addressed;
+
+-- open reports grouped by what they're about, oldest first. queue_id is the group's first report,
+-- resolving it resolves the whole group
+CREATE OR REPLACE VIEW report_queue_view AS
+SELECT queue.queue_id, queue.guild_id, guilds.guild_tag, queue.post_id, queue.comment_id, posts.title AS post_title, comments.body AS comment_body, users.username AS author_username, queue.report_count, queue.reasons, queue.first_reported_at, queue.last_reported_at
+FROM (
+    SELECT min(report_id) AS queue_id, guild_id, post_id, comment_id, count(*) AS report_count, array_agg(reason ORDER BY created_at) AS reasons, min(created_at) AS first_reported_at, max(created_at) AS last_reported_at
+    FROM reports
+    WHERE NOT addressed
+    GROUP BY guild_id, post_id, comment_id
+) queue
+LEFT JOIN guilds ON queue.guild_id = guilds.guild_id
+LEFT JOIN comments ON queue.comment_id = comments.comment_id
+LEFT JOIN posts ON posts.post_id = coalesce(queue.post_id, comments.post_id)
+LEFT JOIN users ON users.user_id = coalesce(comments.user_id, posts.user_id);
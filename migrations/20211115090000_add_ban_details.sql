@@ -0,0 +1,19 @@
+-- Add migration script here
+-- bans keep is_banned as the flag everything checks, plus why, who, when and until when.
+-- the banned user is shown ban_reason, ban_message and ban_expires_at. ban_reason also goes in the moderation log.
+-- a NULL ban_expires_at is a permanent ban
+ALTER TABLE users ADD COLUMN IF NOT EXISTS ban_reason TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS ban_message TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS banned_by INTEGER REFERENCES users(user_id) ON DELETE SET NULL;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS banned_at TIMESTAMP;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS ban_expires_at TIMESTAMP;
+
+ALTER TABLE guild_memberships ADD COLUMN IF NOT EXISTS ban_reason TEXT;
+ALTER TABLE guild_memberships ADD COLUMN IF NOT EXISTS ban_message TEXT;
+ALTER TABLE guild_memberships ADD COLUMN IF NOT EXISTS banned_by INTEGER REFERENCES users(user_id) ON DELETE SET NULL;
+ALTER TABLE guild_memberships ADD COLUMN IF NOT EXISTS banned_at TIMESTAMP;
+ALTER TABLE guild_memberships ADD COLUMN IF NOT EXISTS ban_expires_at TIMESTAMP;
+
+-- for the task that lifts expired bans
+CREATE INDEX IF NOT EXISTS users_ban_expires_at_idx ON users (ban_expires_at) WHERE is_banned AND ban_expires_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS guild_memberships_ban_expires_at_idx ON guild_memberships (ban_expires_at) WHERE is_banned AND ban_expires_at IS NOT NULL;
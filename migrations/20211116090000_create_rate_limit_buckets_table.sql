@@ -0,0 +1,38 @@
+-- Add migration script here
+-- token buckets for the rate limiter when RATE_LIMIT_STORE=postgres, so every backend instance shares them.
+-- full_at is when the bucket will have refilled, after that the row carries no information and can be purged
+CREATE TABLE IF NOT EXISTS rate_limit_buckets (
+    bucket_key VARCHAR(100) NOT NULL PRIMARY KEY,
+    tokens DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    full_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS rate_limit_buckets_full_at_idx ON rate_limit_buckets (full_at);
+
+-- refills the bucket for the time since it was last touched and takes a token if there's one.
+-- returns 0 when a token was taken, otherwise the seconds until one will be available
+CREATE OR REPLACE FUNCTION take_rate_limit_token(key VARCHAR, capacity DOUBLE PRECISION, refill_per_second DOUBLE PRECISION)
+RETURNS DOUBLE PRECISION AS $$
+DECLARE
+    available DOUBLE PRECISION;
+    remaining DOUBLE PRECISION;
+    wait DOUBLE PRECISION := 0;
+BEGIN
+INSERT INTO rate_limit_buckets (bucket_key, tokens, updated_at, full_at)
+VALUES (key, capacity, clock_timestamp(), clock_timestamp())
+ON CONFLICT (bucket_key) DO NOTHING;
+SELECT least(capacity, tokens + extract(epoch FROM clock_timestamp() - updated_at) * refill_per_second) INTO available
+FROM rate_limit_buckets WHERE bucket_key = key FOR UPDATE;
+IF available >= 1 THEN
+    remaining := available - 1;
+ELSE
+    remaining := available;
+    wait := (1 - available) / refill_per_second;
+END IF;
+UPDATE rate_limit_buckets
+SET tokens = remaining, updated_at = clock_timestamp(), full_at = clock_timestamp() + (capacity - remaining) / refill_per_second * INTERVAL '1 second'
+WHERE bucket_key = key;
+RETURN wait;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,12 @@
+-- Add migration script here
+-- failed logins counted per account and per client ip. accounts are keyed by the username that was tried, whether
+-- or not it exists, so lockouts don't give away which usernames are taken.
+-- failure_key is 'account:<username>' or 'ip:<address>', locked_until is when the next attempt is allowed
+CREATE TABLE IF NOT EXISTS login_failures (
+    failure_key VARCHAR(100) NOT NULL PRIMARY KEY,
+    failure_count INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_until TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS login_failures_last_failed_at_idx ON login_failures (last_failed_at);
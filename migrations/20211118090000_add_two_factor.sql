@@ -0,0 +1,30 @@
+-- Add migration script here
+-- totp two factor. totp_secret is base32 and set on enrollment, totp_enabled only once a code from it has been verified.
+-- totp_last_step is the last 30 second step a code was accepted for, so a code can't be used twice
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_secret VARCHAR(64);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_step BIGINT;
+
+-- single use codes for when the authenticator is lost, stored as sha256 hex
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    code_id SERIAL NOT NULL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS recovery_codes_user_id_idx ON recovery_codes (user_id);
+
+-- a login that got the password right and still needs a code. the cookie holds pending_id until the code promotes
+-- it to a user_sessions row, they're only good for a few minutes
+CREATE TABLE IF NOT EXISTS pending_logins (
+    pending_id VARCHAR(128) NOT NULL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT LOCALTIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE
+);
+
+-- site admins can make two factor mandatory for every site admin account
+ALTER TABLE site_info ADD COLUMN IF NOT EXISTS require_admin_two_factor BOOLEAN NOT NULL DEFAULT FALSE;
+INSERT INTO site_info (description) SELECT 'Placeholder Description.' WHERE NOT EXISTS (SELECT 1 FROM site_info);
@@ -0,0 +1,15 @@
+-- Add migration script here
+-- personal access tokens for bots and scripts, sent as Authorization: Bearer. only the sha256 hex of the token is kept.
+-- scopes are read, post and moderate:<guild_tag>
+CREATE TABLE IF NOT EXISTS api_tokens (
+    token_id SERIAL NOT NULL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    token_name VARCHAR(100) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    last_used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS api_tokens_user_id_idx ON api_tokens (user_id);
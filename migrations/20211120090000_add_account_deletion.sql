@@ -0,0 +1,11 @@
+-- Add migration script here
+-- users can schedule their own deletion. the account is deleted once deletion_scheduled_for passes, unless they cancel
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deletion_scheduled_for TIMESTAMP;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deletion_anonymise BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE INDEX IF NOT EXISTS users_deletion_scheduled_for_idx ON users (deletion_scheduled_for) WHERE deletion_scheduled_for IS NOT NULL;
+
+-- deleted users' anonymised posts and comments, and the ones other users replied to, are handed to this account
+-- so threads survive. registration only allows alphanumeric usernames, and '!' never matches a bcrypt hash
+INSERT INTO users (email, username, password_hash, is_banned, ban_reason)
+VALUES ('deleted@invalid', '[deleted]', '!', TRUE, 'Stands in for deleted accounts.')
+ON CONFLICT DO NOTHING;
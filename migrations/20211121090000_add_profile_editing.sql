@@ -0,0 +1,38 @@
+-- Add migration script here
+-- profile fields users can edit themselves. username_changed_at is for the cooldown between username changes
+ALTER TABLE users ADD COLUMN IF NOT EXISTS display_name VARCHAR(50);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS bio TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS username_changed_at TIMESTAMP;
+
+-- usernames a user used to have. nobody else can take them for a while after the change
+CREATE TABLE IF NOT EXISTS username_history (
+    history_id SERIAL NOT NULL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    username VARCHAR(20) NOT NULL,
+    changed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS username_history_username_idx ON username_history (username, changed_at DESC);
+CREATE INDEX IF NOT EXISTS username_history_user_id_idx ON username_history (user_id);
+
+-- blocks point at the username, so they have to follow it when it changes
+ALTER TABLE blocks DROP CONSTRAINT IF EXISTS blocks_blocked_user_username_fkey;
+ALTER TABLE blocks ADD FOREIGN KEY (blocked_user_username) REFERENCES users(username) ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- a new email address waiting on its confirmation link, one per user. only the sha256 hex of the link's token is kept
+CREATE TABLE IF NOT EXISTS email_changes (
+    user_id INTEGER NOT NULL PRIMARY KEY,
+    new_email VARCHAR(50) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE
+);
+
+-- new columns go on the end, CREATE OR REPLACE can't move the others
+CREATE OR REPLACE VIEW detailed_user_view AS
+SELECT users.username, users.avatar_url, users.is_admin, users.is_verified, users.is_banned, users.created_at, user_aggregates.upvotes, user_aggregates.downvotes, user_aggregates.number_of_posts, user_aggregates.number_of_comments, user_aggregates.number_of_memberships, users.display_name, users.bio
+FROM (users INNER JOIN user_aggregates ON users.user_id = user_aggregates.user_id);
+
+CREATE OR REPLACE VIEW user_personal_view AS
+SELECT users.username, users.email, users.avatar_url, users.display_name, users.bio
+FROM users;
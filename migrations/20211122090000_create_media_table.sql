@@ -0,0 +1,21 @@
+-- Add migration script here
+-- uploaded images. the files live in the media store (utils/media_store.rs), only their keys are kept here.
+-- posts, users and guilds point at media by id, and keep copying its url into image_url, avatar_url and banner_url
+-- so the views don't need to know about media
+CREATE TABLE IF NOT EXISTS media (
+    media_id SERIAL NOT NULL PRIMARY KEY,
+    user_id INTEGER,
+    storage_key VARCHAR(100) NOT NULL UNIQUE,
+    thumbnail_key VARCHAR(100) NOT NULL UNIQUE,
+    content_type VARCHAR(50) NOT NULL,
+    byte_size INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS media_user_id_idx ON media (user_id);
+ALTER TABLE posts ADD COLUMN IF NOT EXISTS image_media_id INTEGER REFERENCES media(media_id) ON DELETE SET NULL;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar_media_id INTEGER REFERENCES media(media_id) ON DELETE SET NULL;
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS avatar_media_id INTEGER REFERENCES media(media_id) ON DELETE SET NULL;
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS banner_media_id INTEGER REFERENCES media(media_id) ON DELETE SET NULL;
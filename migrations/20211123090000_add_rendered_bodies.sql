@@ -0,0 +1,14 @@
+-- Add migration script here
+-- sanitised html rendered from the markdown body whenever a post or comment is written, see markdown::render.
+-- rows from before this are NULL until the background task at startup renders them
+ALTER TABLE posts ADD COLUMN IF NOT EXISTS body_html TEXT;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS body_html TEXT;
+
+CREATE OR REPLACE VIEW detailed_post_view AS
+SELECT posts.post_id, posts.guild_id, guilds.guild_tag, posts.image_url, posts.link_url, posts.title, posts.body, posts.is_locked, posts.is_edited, posts.created_at, users.username, users.avatar_url, users.is_admin, users.is_verified, post_aggregates.upvotes, post_aggregates.downvotes, post_aggregates.replies, post_aggregates.score, post_aggregates.hot_rank, post_aggregates.controversy_rank, posts.body_html
+FROM (((posts INNER JOIN users ON posts.user_id = users.user_id) INNER JOIN post_aggregates ON posts.post_id = post_aggregates.post_id) INNER JOIN guilds ON posts.guild_id = guilds.guild_id);
+
+CREATE OR REPLACE VIEW detailed_comment_view AS
+SELECT comments.comment_id, comments.post_id, comments.parent_comment_id, comments.body, comments.created_at, comments.is_edited, users.username, users.avatar_url, users.is_admin, users.is_verified, comment_aggregates.upvotes, comment_aggregates.downvotes, comments.body_html
+FROM ((comments INNER JOIN users ON comments.user_id = users.user_id) INNER JOIN comment_aggregates ON comment_aggregates.comment_id = comments.comment_id)
+ORDER BY created_at DESC;
@@ -0,0 +1,35 @@
+-- Add migration script here
+-- what a member hears about new posts in the guild: off, all (every new post) or top (posts that reach
+-- POST_NOTIFICATION_TOP_SCORE within a day)
+ALTER TABLE guild_memberships ADD COLUMN IF NOT EXISTS notification_preference VARCHAR(10) NOT NULL DEFAULT 'off';
+ALTER TABLE guild_memberships ADD CONSTRAINT guild_memberships_notification_preference_check CHECK (notification_preference IN ('off', 'all', 'top'));
+CREATE INDEX IF NOT EXISTS guild_memberships_notification_preference_idx ON guild_memberships (guild_id, notification_preference, membership_id) WHERE notification_preference <> 'off';
+
+-- notifying a big guild takes a while, so creating a post only queues a job and a background task works through
+-- the members in batches. last_membership_id is how far it got, audience is which preference the job is for
+CREATE TABLE IF NOT EXISTS post_notification_jobs (
+    job_id SERIAL NOT NULL PRIMARY KEY,
+    post_id INTEGER NOT NULL,
+    audience VARCHAR(10) NOT NULL,
+    last_membership_id INTEGER NOT NULL DEFAULT 0,
+    is_done BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (post_id, audience),
+    FOREIGN KEY (post_id) REFERENCES posts(post_id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS post_notification_jobs_pending_idx ON post_notification_jobs (job_id) WHERE NOT is_done;
+
+-- post notifications, only queued when someone in the guild wants every post
+CREATE OR REPLACE FUNCTION create_post_notification()
+RETURNS TRIGGER AS $noti_create$
+BEGIN
+IF EXISTS (SELECT 1 FROM guild_memberships WHERE guild_id = new.guild_id AND notification_preference = 'all') THEN
+    INSERT INTO post_notification_jobs (post_id, audience)
+    VALUES (new.post_id, 'all');
+END IF;
+RETURN NEW;
+END;
+$noti_create$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS post_trig ON posts;
+CREATE TRIGGER post_trig AFTER INSERT ON posts FOR EACH ROW EXECUTE PROCEDURE create_post_notification();
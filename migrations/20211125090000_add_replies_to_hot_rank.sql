@@ -0,0 +1,29 @@
+-- Add migration script here
+-- hot_rank counts replies as well as votes, so a post people are discussing climbs like one they're voting on.
+-- replies change through the comment triggers, so the rank trigger has to watch them too
+CREATE OR REPLACE FUNCTION hot_rank(score INTEGER, replies INTEGER, created_at TIMESTAMP)
+RETURNS DOUBLE PRECISION AS $$
+SELECT sign(score + replies) * log(greatest(abs(score + replies), 1)) + (EXTRACT(EPOCH FROM created_at) - 1134028003) / 45000;
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- same as the realtime version, except a reply count change doesn't send a votes event
+CREATE OR REPLACE FUNCTION post_agg_update_ranks()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+new.score = new.upvotes - new.downvotes;
+new.hot_rank = hot_rank(new.score, new.replies, (SELECT created_at FROM posts WHERE posts.post_id = new.post_id));
+new.controversy_rank = controversy_rank(new.upvotes, new.downvotes);
+IF TG_OP = 'UPDATE' AND (new.upvotes, new.downvotes) IS DISTINCT FROM (old.upvotes, old.downvotes) THEN
+    PERFORM notify_realtime('post:' || new.post_id, 'votes', json_build_object('post_id', new.post_id, 'upvotes', new.upvotes, 'downvotes', new.downvotes, 'score', new.score));
+END IF;
+RETURN NEW;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+DROP FUNCTION IF EXISTS hot_rank(INTEGER, TIMESTAMP);
+
+DROP TRIGGER IF EXISTS post_agg_ranks ON post_aggregates;
+CREATE TRIGGER post_agg_ranks BEFORE INSERT OR UPDATE OF upvotes, downvotes, replies ON post_aggregates FOR EACH ROW EXECUTE PROCEDURE post_agg_update_ranks();
+
+-- backfill existing posts
+UPDATE post_aggregates SET replies = replies;
@@ -0,0 +1,64 @@
+-- Add migration script here
+-- deleting a guild takes its posts with it. posts.guild_id is NOT NULL, so the old SET NULL could never work
+ALTER TABLE posts DROP CONSTRAINT IF EXISTS posts_guild_id_fkey;
+ALTER TABLE posts ADD FOREIGN KEY (guild_id) REFERENCES guilds(guild_id) ON DELETE CASCADE;
+
+-- the cascade deletes memberships and posts after the guild row is gone, and its guild_aggregates row with it.
+-- there's nothing left to count then, so the triggers skip the guild side
+CREATE OR REPLACE FUNCTION guild_agg_member_leaves()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+IF EXISTS (SELECT 1 FROM guilds WHERE guild_id = old.guild_id) THEN
+    UPDATE guild_aggregates
+    SET members = (members - 1)
+    WHERE guild_id = old.guild_id;
+END IF;
+UPDATE user_aggregates
+SET number_of_memberships = (number_of_memberships - 1)
+WHERE user_id = old.user_id;
+RETURN OLD;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION post_delete_aggregates()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+IF EXISTS (SELECT 1 FROM guilds WHERE guild_id = old.guild_id) THEN
+    UPDATE guild_aggregates
+    SET number_of_posts = (number_of_posts - 1)
+    WHERE guild_id = old.guild_id;
+END IF;
+UPDATE user_aggregates
+SET number_of_posts = (number_of_posts - 1)
+WHERE user_id = old.user_id;
+RETURN OLD;
+END;
+$agg_create$ LANGUAGE plpgsql;
+
+-- likewise a post's votes are deleted after the post, when its aggregates can't be reranked any more
+CREATE OR REPLACE FUNCTION post_agg_deleted_vote()
+RETURNS TRIGGER AS $agg_create$
+BEGIN
+IF NOT EXISTS (SELECT 1 FROM posts WHERE posts.post_id = old.post_id) THEN
+    RETURN OLD;
+END IF;
+IF old.up = TRUE THEN
+    UPDATE post_aggregates
+    SET upvotes = (upvotes - 1)
+    WHERE post_id = old.post_id;
+
+    UPDATE user_aggregates
+    SET upvotes = (upvotes - 1)
+    WHERE user_id = (SELECT user_id FROM posts where posts.post_id = old.post_id);
+ELSE
+    UPDATE post_aggregates
+    SET downvotes = (downvotes - 1)
+    WHERE post_id = old.post_id;
+
+    UPDATE user_aggregates
+    SET downvotes = (downvotes - 1)
+    WHERE user_id = (SELECT user_id FROM posts where posts.post_id = old.post_id);
+END IF;
+RETURN OLD;
+END;
+$agg_create$ LANGUAGE plpgsql;
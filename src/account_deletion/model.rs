@@ -0,0 +1,237 @@
+use crate::markdown;
+use crate::user::User;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::time::Duration;
+
+//the account that anonymised posts and comments are handed to, see the account deletion migration
+pub const DELETED_USERNAME: &str = "[deleted]";
+const DEFAULT_GRACE_DAYS: i32 = 14;
+
+//eg {"password": "...", "anonymise": true}. anonymise keeps posts and comments up under [deleted] instead of
+//deleting them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeleteAccountForm {
+    pub password: String,
+    pub anonymise: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountDeletionView {
+    pub scheduled_for: String, //convert time to string
+    pub anonymise: bool,
+}
+
+pub struct AccountDeletion;
+
+//ACCOUNT_DELETION_GRACE_DAYS, how long users have to change their mind
+pub fn grace_days() -> i32 {
+    dotenv::var("ACCOUNT_DELETION_GRACE_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i32>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_GRACE_DAYS)
+}
+
+impl AccountDeletion {
+    pub async fn schedule(
+        user_id: &i32,
+        anonymise: bool,
+        grace_days: i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<AccountDeletionView> {
+        let deletion = sqlx::query!(
+            r#"
+            UPDATE users
+            SET deletion_scheduled_for = LOCALTIMESTAMP + make_interval(days => $2), deletion_anonymise = $3
+            WHERE user_id = $1
+            RETURNING deletion_scheduled_for AS "deletion_scheduled_for!", deletion_anonymise
+            "#,
+            user_id,
+            grace_days,
+            anonymise
+        )
+        .fetch_one(tx)
+        .await?;
+        Ok(AccountDeletionView {
+            scheduled_for: deletion.deletion_scheduled_for.to_string(),
+            anonymise: deletion.deletion_anonymise,
+        })
+    }
+    pub async fn find_by_user_id(
+        user_id: &i32,
+        pool: &PgPool,
+    ) -> Result<Option<AccountDeletionView>> {
+        let deletion = sqlx::query!(
+            r#"
+            SELECT deletion_scheduled_for AS "deletion_scheduled_for!", deletion_anonymise FROM users
+            WHERE user_id = $1 AND deletion_scheduled_for IS NOT NULL
+            "#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(deletion.map(|deletion| AccountDeletionView {
+            scheduled_for: deletion.deletion_scheduled_for.to_string(),
+            anonymise: deletion.deletion_anonymise,
+        }))
+    }
+    //false if no deletion was scheduled
+    pub async fn cancel(user_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<bool> {
+        let cancelled = sqlx::query!(
+            r#"
+            UPDATE users
+            SET deletion_scheduled_for = NULL, deletion_anonymise = FALSE
+            WHERE user_id = $1 AND deletion_scheduled_for IS NOT NULL
+            "#,
+            user_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(cancelled.rows_affected() > 0)
+    }
+    //deletes the user without taking other people's replies with them. anonymised posts and comments go to
+    //[deleted] as they are. otherwise they're deleted, except where someone else replied further down: those
+    //comments are blanked and those posts lose their title, body and links, and both go to [deleted]
+    pub async fn delete_user(
+        user_id: &i32,
+        anonymise: bool,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        if anonymise {
+            sqlx::query!(
+                r#"
+                UPDATE comments
+                SET user_id = (SELECT user_id FROM users WHERE username = $2)
+                WHERE user_id = $1
+                "#,
+                user_id,
+                DELETED_USERNAME
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                UPDATE posts
+                SET user_id = (SELECT user_id FROM users WHERE username = $2)
+                WHERE user_id = $1
+                "#,
+                user_id,
+                DELETED_USERNAME
+            )
+            .execute(&mut *tx)
+            .await?;
+        } else {
+            sqlx::query!(
+                r#"
+                WITH RECURSIVE subtree AS (
+                    SELECT comment_id AS root_id, comment_id, user_id FROM comments
+                    WHERE user_id = $1
+                    UNION ALL
+                    SELECT subtree.root_id, replies.comment_id, replies.user_id FROM comments replies
+                    INNER JOIN subtree ON replies.parent_comment_id = subtree.comment_id
+                )
+                UPDATE comments
+                SET user_id = (SELECT user_id FROM users WHERE username = $2), body = '[deleted]', body_html = $3
+                WHERE comment_id IN (SELECT root_id FROM subtree WHERE user_id <> $1)
+                "#,
+                user_id,
+                DELETED_USERNAME,
+                markdown::render("[deleted]", &markdown::References::default())
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM comments
+                WHERE user_id = $1
+                "#,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                UPDATE posts
+                SET user_id = (SELECT user_id FROM users WHERE username = $2), title = '[deleted]', body = NULL, body_html = NULL, link_url = NULL, image_url = NULL
+                WHERE user_id = $1
+                AND EXISTS (SELECT 1 FROM comments WHERE comments.post_id = posts.post_id)
+                "#,
+                user_id,
+                DELETED_USERNAME
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM posts
+                WHERE user_id = $1
+                "#,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        //leaving a guild updates the user's aggregates, which fails once the cascade from users has started
+        sqlx::query!(
+            r#"
+            DELETE FROM guild_memberships
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        User::delete(user_id, tx).await?;
+        Ok(())
+    }
+    //returns how many accounts were deleted. each gets its own transaction so one failure doesn't hold up the rest
+    pub async fn delete_due(pool: &PgPool) -> Result<u64> {
+        let due = sqlx::query!(
+            r#"
+            SELECT user_id, deletion_anonymise,
+            EXISTS (SELECT 1 FROM guild_memberships WHERE guild_memberships.user_id = users.user_id AND is_admin) AS "is_guild_admin!"
+            FROM users
+            WHERE deletion_scheduled_for <= LOCALTIMESTAMP
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+        let mut deleted = 0;
+        for user in due {
+            //scheduling checks this too, but they could have been made a guild admin during the grace period.
+            //guilds need their admin, so the deletion waits until they hand it over or cancel
+            if user.is_guild_admin {
+                warn!(
+                    "Not deleting user {}, they became a guild admin after scheduling their deletion",
+                    user.user_id
+                );
+                continue;
+            }
+            let mut tx = pool.begin().await?;
+            match AccountDeletion::delete_user(&user.user_id, user.deletion_anonymise, &mut tx)
+                .await
+            {
+                Ok(()) => {
+                    tx.commit().await?;
+                    deleted += 1;
+                }
+                Err(err) => error!("Error deleting user {}: {}", user.user_id, err),
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+//runs for the life of the server, deleting accounts whose grace period is over
+pub async fn delete_scheduled_accounts(pool: PgPool) {
+    loop {
+        match AccountDeletion::delete_due(&pool).await {
+            Ok(0) => (),
+            Ok(count) => info!("Deleted {} accounts scheduled for deletion", count),
+            Err(err) => error!("Error deleting scheduled accounts: {}", err),
+        }
+        async_std::task::sleep(Duration::from_secs(60 * 60)).await;
+    }
+}
@@ -24,7 +24,7 @@ pub struct CommentAggregates {
 }
 
 pub struct GuildAggregates {
-    pub guild_tag: String,
+    pub guild_id: i32,
     pub members: i32,
     pub number_of_posts: i32,
 }
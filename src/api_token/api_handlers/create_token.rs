@@ -0,0 +1,47 @@
+use crate::api_token::*;
+use crate::guild::Guild;
+use crate::guild_membership::GuildMembership;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//the token is only in this response, after that only its hash is kept
+#[post("/create")]
+pub async fn handler(
+    token_form: web::Json<ApiTokenForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let token_form = token_form.validate()?;
+    //moderate scopes only for guilds the user can already moderate
+    for guild_tag in token_form.moderated_guild_tags() {
+        let guild = Guild::find_by_guild_tag(&guild_tag, db_pool.get_ref())
+            .await?
+            .ok_or_else(|| {
+                ApiError::invalid_field("scopes", format!("Guild {} does not exist.", guild_tag))
+            })?;
+        let membership = GuildMembership::find_by_user_and_guild_id(
+            &user.user_id,
+            &guild.guild_id,
+            db_pool.get_ref(),
+        )
+        .await?;
+        let moderates = match membership {
+            Some(membership) => membership.is_admin || membership.is_moderator,
+            None => false,
+        };
+        if !moderates && !user.is_admin {
+            return Err(ApiError::invalid_field(
+                "scopes",
+                format!("You are not a moderator of {}.", guild_tag),
+            ));
+        }
+    }
+    let mut tx = db_pool.begin().await?;
+    let created = ApiToken::create(&user.user_id, &token_form, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(created))
+}
@@ -0,0 +1,16 @@
+use crate::api_token::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+#[get("")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let api_tokens = ApiToken::find_all_by_user_id(&user.user_id, db_pool.get_ref()).await?;
+    Ok(HttpResponse::Ok().json(api_tokens))
+}
@@ -0,0 +1,4 @@
+pub mod create_token;
+pub mod get_tokens;
+pub mod revoke_all_tokens;
+pub mod revoke_token;
@@ -0,0 +1,18 @@
+use crate::api_token::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/revokeall")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let mut tx = db_pool.begin().await?;
+    ApiToken::revoke_all_by_user_id(&user.user_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("All tokens revoked."))
+}
@@ -0,0 +1,21 @@
+use crate::api_token::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/{token_id}/revoke")]
+pub async fn handler(
+    token_id: web::Path<i32>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let mut tx = db_pool.begin().await?;
+    if !ApiToken::revoke(&token_id, &user.user_id, &mut tx).await? {
+        return Err(ApiError::not_found("Token not found."));
+    }
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Token revoked."))
+}
@@ -0,0 +1,263 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::token;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+
+//makes leaked tokens easy to spot, eg by secret scanners
+const TOKEN_PREFIX: &str = "lat_";
+const DEFAULT_EXPIRY_DAYS: i32 = 90;
+
+pub const SCOPE_READ: &str = "read";
+pub const SCOPE_POST: &str = "post";
+pub const SCOPE_MODERATE_PREFIX: &str = "moderate:";
+
+//never includes the token itself, that's only shown once when it's created
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiToken {
+    pub token_id: i32,
+    pub token_name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: String,           //convert time to string
+    pub last_used_at: Option<String>, //convert time to string
+    pub created_at: String,           //convert time to string
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiTokenCreatedView {
+    pub token: String,
+    #[serde(flatten)]
+    pub api_token: ApiToken,
+}
+
+//eg {"token_name": "modbot", "scopes": ["read", "moderate:rust"], "expires_in_days": 30}.
+//read only lets the token make GET requests, post lets it do anything the user can besides moderation and
+//site admin routes, and moderate:<guild_tag> lets it use that guild's mod and admin routes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiTokenForm {
+    pub token_name: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i32>,
+}
+
+impl ApiTokenForm {
+    //trims the name and lowercases and dedupes scopes. expiry defaults to 90 days, and is capped at a year
+    pub fn validate(&self) -> Result<ApiTokenForm, ApiError> {
+        let token_name = self.token_name.trim();
+        if token_name.is_empty() || token_name.len() > 100 {
+            return Err(ApiError::invalid_field(
+                "token_name",
+                "Token names must be between 1 and 100 characters.",
+            ));
+        }
+        let mut scopes: Vec<String> = vec![];
+        for scope in &self.scopes {
+            let scope = scope.trim().to_lowercase();
+            let known = scope == SCOPE_READ
+                || scope == SCOPE_POST
+                || scope
+                    .strip_prefix(SCOPE_MODERATE_PREFIX)
+                    .is_some_and(|guild_tag| !guild_tag.is_empty());
+            if !known {
+                return Err(ApiError::invalid_field(
+                    "scopes",
+                    "Scopes must be read, post or moderate:<guild_tag>.",
+                ));
+            }
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+        if scopes.is_empty() {
+            return Err(ApiError::invalid_field(
+                "scopes",
+                "Tokens need at least one scope.",
+            ));
+        }
+        let expires_in_days = self.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS);
+        if !(1..=365).contains(&expires_in_days) {
+            return Err(ApiError::invalid_field(
+                "expires_in_days",
+                "Tokens must expire in between 1 and 365 days.",
+            ));
+        }
+        Ok(ApiTokenForm {
+            token_name: token_name.to_string(),
+            scopes,
+            expires_in_days: Some(expires_in_days),
+        })
+    }
+    pub fn moderated_guild_tags(&self) -> Vec<String> {
+        moderated_guild_tags(&self.scopes)
+    }
+}
+
+fn moderated_guild_tags(scopes: &[String]) -> Vec<String> {
+    scopes
+        .iter()
+        .filter_map(|scope| scope.strip_prefix(SCOPE_MODERATE_PREFIX))
+        .map(str::to_string)
+        .collect()
+}
+
+//what a valid bearer token lets the current request do, see utils::api_token_auth.
+//writes is whether the request is anything other than a GET, HEAD or OPTIONS
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiTokenGrant {
+    pub token_id: i32,
+    pub user_id: i32,
+    pub scopes: Vec<String>,
+    pub writes: bool,
+}
+
+impl ApiTokenGrant {
+    pub fn can_post(&self) -> bool {
+        !self.writes || self.scopes.iter().any(|scope| scope == SCOPE_POST)
+    }
+    //guild tags from moderate:<guild_tag> scopes, they're resolved like any other tag so renamed guilds keep working
+    pub fn moderated_guild_tags(&self) -> Vec<String> {
+        moderated_guild_tags(&self.scopes)
+    }
+}
+
+impl ApiToken {
+    //returns the token, which can't be recovered later
+    pub async fn create(
+        user_id: &i32,
+        form: &ApiTokenForm,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<ApiTokenCreatedView> {
+        let token = format!("{}{}", TOKEN_PREFIX, token::random_token(40));
+        let api_token = sqlx::query!(
+            r#"
+            INSERT INTO api_tokens (user_id, token_name, token_hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, LOCALTIMESTAMP + make_interval(days => $5))
+            RETURNING token_id, token_name, scopes, expires_at, last_used_at, created_at
+            "#,
+            user_id,
+            form.token_name,
+            token::hash_token(&token),
+            &form.scopes,
+            form.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS)
+        )
+        .fetch_one(tx)
+        .await?;
+        Ok(ApiTokenCreatedView {
+            token,
+            api_token: ApiToken {
+                token_id: api_token.token_id,
+                token_name: api_token.token_name,
+                scopes: api_token.scopes,
+                expires_at: api_token.expires_at.to_string(),
+                last_used_at: api_token.last_used_at.map(|l| l.to_string()),
+                created_at: api_token.created_at.to_string(),
+            },
+        })
+    }
+    //expired tokens are listed too until they're purged
+    pub async fn find_all_by_user_id(user_id: &i32, pool: &PgPool) -> Result<Vec<ApiToken>> {
+        let api_tokens = sqlx::query!(
+            r#"
+            SELECT token_id, token_name, scopes, expires_at, last_used_at, created_at FROM api_tokens
+            WHERE user_id = $1
+            ORDER BY created_at DESC, token_id DESC
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(api_tokens
+            .into_iter()
+            .map(|api_token| ApiToken {
+                token_id: api_token.token_id,
+                token_name: api_token.token_name,
+                scopes: api_token.scopes,
+                expires_at: api_token.expires_at.to_string(),
+                last_used_at: api_token.last_used_at.map(|l| l.to_string()),
+                created_at: api_token.created_at.to_string(),
+            })
+            .collect())
+    }
+    //None if the token doesn't exist or has expired
+    pub async fn find_grant(
+        token: &str,
+        writes: bool,
+        pool: &PgPool,
+    ) -> Result<Option<ApiTokenGrant>> {
+        let api_token = sqlx::query!(
+            r#"
+            SELECT token_id, user_id, scopes FROM api_tokens
+            WHERE token_hash = $1 AND expires_at > LOCALTIMESTAMP
+            "#,
+            token::hash_token(token)
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(api_token.map(|api_token| ApiTokenGrant {
+            token_id: api_token.token_id,
+            user_id: api_token.user_id,
+            scopes: api_token.scopes,
+            writes,
+        }))
+    }
+    //only writes once a minute, bots can make a lot of requests
+    pub async fn record_use(token_id: &i32, pool: &PgPool) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE api_tokens
+            SET last_used_at = LOCALTIMESTAMP
+            WHERE token_id = $1
+            AND (last_used_at IS NULL OR last_used_at < LOCALTIMESTAMP - INTERVAL '1 minute')
+            "#,
+            token_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+    //false if the user has no such token
+    pub async fn revoke(
+        token_id: &i32,
+        user_id: &i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<bool> {
+        let revoked = sqlx::query!(
+            r#"
+            DELETE FROM api_tokens
+            WHERE token_id = $1 AND user_id = $2
+            "#,
+            token_id,
+            user_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(revoked.rows_affected() > 0)
+    }
+    pub async fn revoke_all_by_user_id(
+        user_id: &i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<u64> {
+        let revoked = sqlx::query!(
+            r#"
+            DELETE FROM api_tokens
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(revoked.rows_affected())
+    }
+    //expired tokens stay listed for a month so their owners can see what stopped working
+    pub async fn purge_expired(pool: &PgPool) -> Result<u64> {
+        let purged = sqlx::query!(
+            r#"
+            DELETE FROM api_tokens
+            WHERE expires_at <= LOCALTIMESTAMP - INTERVAL '30 days'
+            "#
+        )
+        .execute(pool)
+        .await?;
+        Ok(purged.rows_affected())
+    }
+}
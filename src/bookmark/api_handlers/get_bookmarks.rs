@@ -0,0 +1,24 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{PageForm, PageRequest};
+use crate::utils::session_validation;
+use crate::view::DetailedPostView;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+#[get("/posts/{page_number}")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    page_number: web::Path<i64>,
+    page_form: web::Query<PageForm>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let page = PageRequest::new(&page_form, &page_number, &20)
+        .map_err(|_| ApiError::validation("Invalid page number or cursor."))?;
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let mut posts =
+        DetailedPostView::get_bookmarked_posts_by_user_id(&user.user_id, db_pool.get_ref(), &page)
+            .await?;
+    DetailedPostView::annotate_for_user(&mut posts.items, &user.user_id, db_pool.get_ref()).await?;
+    Ok(HttpResponse::Ok().json(posts))
+}
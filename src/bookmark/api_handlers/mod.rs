@@ -0,0 +1,3 @@
+pub mod get_bookmarks;
+pub mod save_post;
+pub mod unsave_post;
@@ -0,0 +1,35 @@
+use crate::bookmark::*;
+use crate::post::Post;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/save/{post_id}")]
+pub async fn handler(
+    post_id: web::Path<i32>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    //make sure post exists
+    let post = Post::find_by_post_id(&post_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("The post you are trying to save does not exist."))?;
+    //make sure post isn't already saved
+    let existing_bookmark =
+        Bookmark::find_by_user_and_post_id(&user.user_id, &post.post_id, db_pool.get_ref()).await?;
+    if existing_bookmark.is_some() {
+        return Err(ApiError::conflict("You have already saved this post."));
+    }
+    //create bookmark
+    let bookmark_form = BookmarkForm {
+        post_id: post.post_id,
+        user_id: user.user_id,
+    };
+    let mut tx = db_pool.begin().await?;
+    Bookmark::create(&bookmark_form, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Post saved."))
+}
@@ -0,0 +1,24 @@
+use crate::bookmark::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/unsave/{post_id}")]
+pub async fn handler(
+    post_id: web::Path<i32>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    //make sure post is saved
+    let bookmark = Bookmark::find_by_user_and_post_id(&user.user_id, &post_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("You have not saved this post."))?;
+    //delete bookmark
+    let mut tx = db_pool.begin().await?;
+    Bookmark::delete(bookmark, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Post unsaved."))
+}
@@ -1,5 +1,4 @@
+pub mod api_handlers;
 mod model;
-mod routes;
 
 pub use model::*;
-pub use routes::init;
@@ -1,23 +1,82 @@
-use serde::{Deserialize, Serialize};
-use actix_web::{Error, HttpRequest, HttpResponse, Responder};
-use sqlx::postgres::PgRow;
-use sqlx::{FromRow, PgPool, Row};
+use crate::utils::api_error::ApiError;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Bookmark {
-    pub bookmark_id: i32
+    pub bookmark_id: i32,
     pub post_id: i32,
     pub user_id: i32,
+    pub created_at: String, //convert time to string
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BookmarkForm {
-    post_id: i32,
-    user_id: i32,
+    pub post_id: i32,
+    pub user_id: i32,
 }
 
 impl Bookmark {
-    pub async fn create(bookmark_form: BookmarkForm, pool: &PgPool) -> Result<()> {}
-    pub async fn find_by_bookmark_id(bookmark_id: i32, pool: &PgPool) -> Result<Option<Bookmark>> {}
-    pub async fn find_bookmarks_by_user_id(user_id: i32, pool: &PgPool) -> Result<Option<Vec<Bookmark>>> {}
-    pub async fn delete(bookmark: Bookmark, pool: &PgPool) -> Result<()> {}
-}
\ No newline at end of file
+    pub async fn create(
+        bookmark_form: &BookmarkForm,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO bookmarks (post_id, user_id)
+            VALUES ($1, $2)
+            "#,
+            bookmark_form.post_id,
+            bookmark_form.user_id
+        )
+        .execute(tx)
+        .await
+        .map_err(duplicate_bookmark)?;
+        Ok(())
+    }
+    pub async fn find_by_user_and_post_id(
+        user_id: &i32,
+        post_id: &i32,
+        pool: &PgPool,
+    ) -> Result<Option<Bookmark>> {
+        let bookmark = sqlx::query!(
+            r#"
+            SELECT * FROM bookmarks
+            WHERE user_id = $1 AND post_id = $2
+            "#,
+            user_id,
+            post_id
+        )
+        .fetch_optional(&*pool)
+        .await?;
+        Ok(bookmark.map(|bookmark| Bookmark {
+            bookmark_id: bookmark.bookmark_id,
+            post_id: bookmark.post_id,
+            user_id: bookmark.user_id,
+            created_at: bookmark.created_at.to_string(),
+        }))
+    }
+    pub async fn delete(bookmark: Bookmark, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            DELETE FROM bookmarks
+            WHERE bookmark_id = $1
+            "#,
+            bookmark.bookmark_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+}
+
+//two saves of the same post can both get past find_by_user_and_post_id, the second then hits UNIQUE (user_id, post_id)
+fn duplicate_bookmark(err: sqlx::Error) -> anyhow::Error {
+    match &err {
+        sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("23505") => {
+            ApiError::conflict("You have already saved this post.").into()
+        }
+        _ => err.into(),
+    }
+}
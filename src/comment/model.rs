@@ -1,3 +1,5 @@
+use crate::markdown::{self, References};
+use crate::notification::CommentNotification;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Postgres, Transaction};
@@ -22,43 +24,52 @@ pub struct CommentForm {
 }
 
 impl Comment {
+    //returns the new comment's id. users mentioned in the body are notified
     pub async fn create(
         comment_form: &CommentForm,
         tx: &mut Transaction<'_, Postgres>,
-    ) -> Result<()> {
-        match comment_form.parent_comment_id {
+    ) -> Result<i32> {
+        let references = References::resolve(&comment_form.body, &mut *tx).await?;
+        let body_html = markdown::render(&comment_form.body, &references);
+        let comment_id = match comment_form.parent_comment_id {
             Some(parent_comment_id) => {
                 sqlx::query!(
                     r#"
-                    INSERT INTO comments (post_id, parent_comment_id, user_id, body)
-                    VALUES ($1, $2, $3, $4)
+                    INSERT INTO comments (post_id, parent_comment_id, user_id, body, body_html)
+                    VALUES ($1, $2, $3, $4, $5)
+                    RETURNING comment_id
                     "#,
                     comment_form.post_id,
                     parent_comment_id,
                     comment_form.user_id,
-                    comment_form.body
+                    comment_form.body,
+                    body_html
                 )
-                .execute(tx)
-                .await?;
-                Ok(())
+                .fetch_one(&mut *tx)
+                .await?
+                .comment_id
             }
             None => {
                 sqlx::query!(
                     r#"
-                    INSERT INTO comments (post_id, user_id, body)
-                    VALUES ($1, $2, $3)
+                    INSERT INTO comments (post_id, user_id, body, body_html)
+                    VALUES ($1, $2, $3, $4)
+                    RETURNING comment_id
                     "#,
                     comment_form.post_id,
                     comment_form.user_id,
-                    comment_form.body
+                    comment_form.body,
+                    body_html
                 )
-                .execute(tx)
-                .await?;
-                Ok(())
+                .fetch_one(&mut *tx)
+                .await?
+                .comment_id
             }
-        }
+        };
+        CommentNotification::create_mentions(&comment_id, &references.mentioned_user_ids(), tx)
+            .await?;
+        Ok(comment_id)
     }
-
     pub async fn find_by_comment_id(comment_id: &i32, pool: &PgPool) -> Result<Option<Comment>> {
         let comment = sqlx::query!(
             r#"
@@ -147,23 +158,28 @@ impl Comment {
 
         Ok(comments)
     }
+    //only users newly mentioned by the edit are notified
     pub async fn update(
         comment_id: &i32,
         new_body: &String,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
+        let references = References::resolve(new_body, &mut *tx).await?;
         sqlx::query!(
             r#"
             UPDATE comments
-            SET body = $2, is_edited = $3
+            SET body = $2, is_edited = $3, body_html = $4
             WHERE comment_id = $1
             "#,
             comment_id,
             new_body,
-            true
+            true,
+            markdown::render(new_body, &references)
         )
-        .execute(tx)
+        .execute(&mut *tx)
         .await?;
+        CommentNotification::create_mentions(comment_id, &references.mentioned_user_ids(), tx)
+            .await?;
         Ok(())
     }
     pub async fn delete(comment_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
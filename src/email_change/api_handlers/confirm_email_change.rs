@@ -0,0 +1,28 @@
+use crate::email_change::*;
+use crate::user::User;
+use crate::utils::api_error::ApiError;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+//opened from the email, so no login needed. the token is proof enough
+#[get("/confirm/{confirmation_token}")]
+pub async fn handler(
+    confirmation_token: web::Path<String>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let email_change = EmailChange::find_active_by_token(&confirmation_token, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Error confirming email. Double check link in email"))?;
+    //someone could have taken the address since the link was sent
+    if User::find_by_email(&email_change.new_email, db_pool.get_ref())
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::conflict("User with that email already exists"));
+    }
+    let mut tx = db_pool.begin().await?;
+    User::update_email(email_change.new_email, &email_change.user_id, &mut tx).await?;
+    EmailChange::delete(&email_change.user_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Your email has been changed."))
+}
@@ -0,0 +1,2 @@
+pub mod confirm_email_change;
+pub mod request_email_change;
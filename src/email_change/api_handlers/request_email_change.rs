@@ -0,0 +1,57 @@
+use crate::email_change::*;
+use crate::user::User;
+use crate::user_registration::UserRegistration;
+use crate::utils::api_error::ApiError;
+use crate::utils::mailer::{MailTemplate, Mailer};
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+//users.email only changes once the link sent to the new address is opened. checks the password, so it's rate limited
+//like login
+pub async fn handler(
+    change_form: web::Json<EmailChangeForm>,
+    db_pool: web::Data<PgPool>,
+    mailer: web::Data<dyn Mailer>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    if !User::verify_password(&user, &change_form.password).await? {
+        return Err(ApiError::invalid_field("password", "Incorrect password."));
+    }
+    let new_email = change_form.formatted_email()?;
+    if new_email == user.email {
+        return Err(ApiError::invalid_field(
+            "new_email",
+            "That is already your email.",
+        ));
+    }
+    //same checks as registration
+    if User::find_by_email(&new_email, db_pool.get_ref())
+        .await?
+        .is_some()
+        || UserRegistration::find_by_email(&new_email, db_pool.get_ref())
+            .await?
+            .is_some()
+    {
+        return Err(ApiError::conflicting_field(
+            "new_email",
+            "User with that email already exists",
+        ));
+    }
+    let mut tx = db_pool.begin().await?;
+    let confirmation_token = EmailChange::create(&user.user_id, &new_email, &mut tx).await?;
+    mailer
+        .send_template(
+            MailTemplate::EmailChange,
+            &new_email,
+            &[
+                ("username", &user.username),
+                ("confirmation_token", &confirmation_token),
+            ],
+        )
+        .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("A link to confirm your new email has been sent to it."))
+}
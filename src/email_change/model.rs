@@ -0,0 +1,103 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::token;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmailChange {
+    pub user_id: i32,
+    pub new_email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmailChangeForm {
+    pub new_email: String,
+    pub password: String,
+}
+
+impl EmailChangeForm {
+    //lowercased like at registration. the link we send is the real check that the address works
+    pub fn formatted_email(&self) -> Result<String, ApiError> {
+        let new_email = self.new_email.trim().to_lowercase();
+        let looks_valid = new_email.len() <= 50
+            && !new_email.chars().any(char::is_whitespace)
+            && matches!(new_email.split_once('@'), Some((local, domain)) if !local.is_empty() && domain.contains('.'));
+        if looks_valid {
+            Ok(new_email)
+        } else {
+            Err(ApiError::invalid_field(
+                "new_email",
+                "Enter a valid email address, no longer than 50 characters.",
+            ))
+        }
+    }
+}
+
+impl EmailChange {
+    //replaces any change the user already had waiting. returns the token for the confirmation link
+    pub async fn create(
+        user_id: &i32,
+        new_email: &str,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<String> {
+        let confirmation_token = token::random_token(32);
+        sqlx::query!(
+            r#"
+            INSERT INTO email_changes (user_id, new_email, token_hash)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE
+            SET new_email = $2, token_hash = $3, created_at = LOCALTIMESTAMP
+            "#,
+            user_id,
+            new_email,
+            token::hash_token(&confirmation_token)
+        )
+        .execute(tx)
+        .await?;
+        Ok(confirmation_token)
+    }
+    //links are good for a day
+    pub async fn find_active_by_token(
+        confirmation_token: &str,
+        pool: &PgPool,
+    ) -> Result<Option<EmailChange>> {
+        let email_change = sqlx::query!(
+            r#"
+            SELECT user_id, new_email FROM email_changes
+            WHERE token_hash = $1
+            AND created_at > LOCALTIMESTAMP - INTERVAL '1 day'
+            "#,
+            token::hash_token(confirmation_token)
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(email_change.map(|email_change| EmailChange {
+            user_id: email_change.user_id,
+            new_email: email_change.new_email,
+        }))
+    }
+    pub async fn delete(user_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            DELETE FROM email_changes
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+    pub async fn purge_expired(pool: &PgPool) -> Result<u64> {
+        let purged = sqlx::query!(
+            r#"
+            DELETE FROM email_changes
+            WHERE created_at <= LOCALTIMESTAMP - INTERVAL '1 day'
+            "#
+        )
+        .execute(pool)
+        .await?;
+        Ok(purged.rows_affected())
+    }
+}
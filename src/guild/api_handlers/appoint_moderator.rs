@@ -1,8 +1,11 @@
+use crate::guild::*;
 use crate::guild_membership::*;
+use crate::moderation_action::*;
 use crate::user::User;
+use crate::utils::api_error::ApiError;
 use crate::utils::session_validation;
 use actix_session::Session;
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{post, web, HttpResponse};
 use serde::Deserialize;
 use sqlx::PgPool;
 
@@ -17,105 +20,53 @@ pub async fn handler(
     appoint_moderator_form: web::Path<AppointModForm>,
     db_pool: web::Data<PgPool>,
     session: Session,
-) -> impl Responder {
+    reason_form: Option<web::Json<ModerationReasonForm>>,
+) -> Result<HttpResponse, ApiError> {
+    let reason = format_reason(&reason_form.and_then(|form| form.into_inner().reason))?;
     let formatted_form = AppointModForm {
         guild_tag: appoint_moderator_form.guild_tag.to_lowercase(),
         username: appoint_moderator_form.username.to_lowercase(),
     };
-    let valid_session = session_validation::policy_guild_admin(
-        &session,
-        &formatted_form.guild_tag,
+    let guild = Guild::find_by_guild_tag(&formatted_form.guild_tag, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    let actor =
+        session_validation::policy_guild_admin(&session, &guild.guild_id, db_pool.get_ref())
+            .await?;
+    //make sure user exists and is in guild
+    let user = User::find_by_username_sensitive(&formatted_form.username, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("The user you are trying to appoint does not exist"))?;
+    let membership = GuildMembership::find_by_user_and_guild_id(
+        &user.user_id,
+        &guild.guild_id,
         db_pool.get_ref(),
     )
-    .await;
-    match valid_session {
-        Ok((None, Some(user))) => {
-            //make sure user exists and is in guild
-            let user_exists =
-                User::find_by_username_sensitive(&formatted_form.username, db_pool.get_ref()).await;
-            match user_exists {
-                Ok(Some(user)) => {
-                    //make sure user is in guild
-                    let user_is_member = GuildMembership::find_by_user_and_guild_tag(
-                        &user.user_id,
-                        &formatted_form.guild_tag,
-                        db_pool.get_ref(),
-                    )
-                    .await;
-
-                    match user_is_member {
-                        Ok(Some(membership)) => {
-                            //make sure not already mod
-                            if membership.is_moderator || membership.is_admin {
-                                return HttpResponse::BadRequest().body(
-                                    "The user you are trying to appoint as mod is already a mod or admin.",
-                                );
-                            }
-                            //update membership
-                            let mut tx = db_pool.begin().await.unwrap();
-                            let updated_member = GuildMembership::update_membership_mod_status(
-                                true, membership, &mut tx,
-                            )
-                            .await;
-                            match updated_member {
-                                Ok(()) => {
-                                    let succesful_commit = tx.commit().await;
-                                    match succesful_commit {
-                                        Ok(()) => (),
-                                        Err(err) => {
-                                            error!("Error committing transaction: {}", err);
-                                            return HttpResponse::InternalServerError()
-                                                .body("Unknown Error.");
-                                        }
-                                    }
-                                    return HttpResponse::Ok().body("Mod appointed successfully.");
-                                }
-                                Err(err) => {
-                                    let succesful_rollback = tx.rollback().await;
-                                    match succesful_rollback {
-                                        Ok(()) => (),
-                                        Err(err) => {
-                                            error!("Error rolling back transaction: {}", err);
-                                            return HttpResponse::InternalServerError()
-                                                .body("Unknown Error.");
-                                        }
-                                    }
-                                    error!("Error appointing user as mod: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Error appointing user as mod.");
-                                }
-                            }
-                        }
-                        Ok(None) => {
-                            return HttpResponse::BadRequest()
-                            .body("The user you are trying to appoint as mod is not a member of the guild.");
-                        }
-                        Err(err) => {
-                            error!("Error fetching user guild membership: {}", err);
-                            return HttpResponse::InternalServerError()
-                                .body("Error fetching user.");
-                        }
-                    }
-                }
-                Ok(None) => {
-                    return HttpResponse::NotFound()
-                        .body("The user you are trying to appoint does not exist");
-                }
-                Err(err) => {
-                    error!("Error fetching user: {}", err);
-                    return HttpResponse::InternalServerError().body("Error fetching user.");
-                }
-            }
-        }
-        Ok((Some(response), None)) => {
-            return response;
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
+    .await?
+    .ok_or_else(|| {
+        ApiError::validation(
+            "The user you are trying to appoint as mod is not a member of the guild.",
+        )
+    })?;
+    //make sure not already mod
+    if membership.is_moderator || membership.is_admin {
+        return Err(ApiError::conflict(
+            "The user you are trying to appoint as mod is already a mod or admin.",
+        ));
     }
+    //update membership
+    let mut tx = db_pool.begin().await?;
+    GuildMembership::update_membership_mod_status(true, membership, &mut tx).await?;
+    ModerationAction::create(
+        &ModerationActionForm {
+            guild_id: Some(guild.guild_id),
+            target_user_id: Some(user.user_id),
+            reason,
+            ..ModerationActionForm::new(actor.user_id, ModerationActionType::AppointModerator)
+        },
+        &mut tx,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Mod appointed successfully."))
 }
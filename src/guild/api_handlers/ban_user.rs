@@ -1,7 +1,11 @@
+use crate::guild::*;
 use crate::guild_membership::*;
+use crate::moderation_action::*;
+use crate::user::{BanForm, User};
+use crate::utils::api_error::ApiError;
 use crate::utils::session_validation;
 use actix_session::Session;
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{post, web, HttpResponse};
 use serde::Deserialize;
 use sqlx::PgPool;
 
@@ -13,94 +17,77 @@ pub struct BanUserForm {
 
 #[post("/{guild_tag}/ban/{username}")]
 pub async fn handler(
-    appoint_moderator_form: web::Path<BanUserForm>,
+    ban_form: web::Path<BanUserForm>,
     db_pool: web::Data<PgPool>,
     session: Session,
-) -> impl Responder {
+    body: web::Bytes,
+) -> Result<HttpResponse, ApiError> {
+    ban_member(&ban_form, &body, false, db_pool.get_ref(), &session).await
+}
+
+//shared with admin_ban_user. moderators can ban members, only guild admins can ban moderators too
+pub async fn ban_member(
+    ban_form: &BanUserForm,
+    body: &[u8],
+    as_admin: bool,
+    pool: &PgPool,
+    session: &Session,
+) -> Result<HttpResponse, ApiError> {
+    let ban = BanForm::from_body(body)?;
     let formatted_form = BanUserForm {
-        guild_tag: appoint_moderator_form.guild_tag.to_lowercase(),
-        username: appoint_moderator_form.username.to_lowercase(),
+        guild_tag: ban_form.guild_tag.to_lowercase(),
+        username: ban_form.username.to_lowercase(),
     };
-    let valid_session = session_validation::policy_guild_moderator_or_admin(
-        &session,
-        &formatted_form.guild_tag,
-        db_pool.get_ref(),
-    )
-    .await;
-    match valid_session {
-        Ok((None, Some(user))) => {
-            //make sure user is in guild
-            let user_is_member = GuildMembership::find_by_user_and_guild_tag(
-                &user.user_id,
-                &formatted_form.guild_tag,
-                db_pool.get_ref(),
-            )
-            .await;
-
-            match user_is_member {
-                Ok(Some(membership)) => {
-                    //update membership with ban
-                    //make sure not already mod
-                    if membership.is_moderator || membership.is_admin {
-                        return HttpResponse::BadRequest()
-                            .body("The user you are trying to ban is already a mod or admin.");
-                    }
-                    if membership.is_banned {
-                        return HttpResponse::BadRequest()
-                            .body("The user you are trying to ban is already banned.");
-                    }
-                    //update membership
-                    let mut tx = db_pool.begin().await.unwrap();
-                    let updated_member =
-                        GuildMembership::update_membership_ban_status(true, membership, &mut tx)
-                            .await;
-                    match updated_member {
-                        Ok(()) => {
-                            let succesful_commit = tx.commit().await;
-                            match succesful_commit {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error committing transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            return HttpResponse::Ok().body("User has been banned from the guild.");
-                        }
-                        Err(err) => {
-                            let succesful_rollback = tx.rollback().await;
-                            match succesful_rollback {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error rolling back transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            error!("Error banning user: {}", err);
-                            return HttpResponse::InternalServerError().body("Error banning user");
-                        }
-                    }
-                }
-                Ok(None) => {
-                    return HttpResponse::BadRequest()
-                        .body("The user you are trying to ban is not in the guild.");
-                }
-                Err(err) => {
-                    error!("Error fetching user guild membership: {}", err);
-                    return HttpResponse::InternalServerError().body("Error fetching user.");
-                }
-            }
-        }
-        Ok((Some(response), None)) => {
-            return response;
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
+    let guild = Guild::find_by_guild_tag(&formatted_form.guild_tag, pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    let actor = if as_admin {
+        session_validation::policy_guild_admin(session, &guild.guild_id, pool).await?
+    } else {
+        session_validation::policy_guild_moderator_or_admin(session, &guild.guild_id, pool).await?
+    };
+    //make sure user exists and is in guild
+    let user = User::find_by_username_sensitive(&formatted_form.username, pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("The user you are trying to ban does not exist."))?;
+    let membership =
+        GuildMembership::find_by_user_and_guild_id(&user.user_id, &guild.guild_id, pool)
+            .await?
+            .ok_or_else(|| {
+                ApiError::validation("The user you are trying to ban is not in the guild.")
+            })?;
+    if membership.is_admin {
+        return Err(ApiError::forbidden(
+            "The user you are trying to ban is already an admin.",
+        ));
+    }
+    if membership.is_moderator && !as_admin {
+        return Err(ApiError::forbidden(
+            "The user you are trying to ban is already a mod or admin.",
+        ));
     }
+    if membership.is_banned
+        && GuildMembership::find_active_ban(&membership.membership_id, pool)
+            .await?
+            .is_some()
+    {
+        return Err(ApiError::conflict(
+            "The user you are trying to ban is already banned.",
+        ));
+    }
+    //update membership
+    let mut tx = pool.begin().await?;
+    GuildMembership::ban(membership, &ban, &actor.user_id, &mut tx).await?;
+    ModerationAction::create(
+        &ModerationActionForm {
+            guild_id: Some(guild.guild_id),
+            target_user_id: Some(user.user_id),
+            reason: ban.reason.clone(),
+            ..ModerationActionForm::new(actor.user_id, ModerationActionType::BanUser)
+        },
+        &mut tx,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("User has been banned from the guild."))
 }
@@ -13,3 +13,5 @@ pub mod update_guild_avatar;
 pub mod update_guild_banner;
 pub mod update_guild_description;
 pub mod update_guild_name;
+pub mod update_guild_tag;
+pub mod update_notification_preference;
@@ -1,7 +1,11 @@
 use crate::comment::*;
+use crate::guild::*;
+use crate::moderation_action::*;
+use crate::post::*;
+use crate::utils::api_error::ApiError;
 use crate::utils::session_validation;
 use actix_session::Session;
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
@@ -9,81 +13,56 @@ use sqlx::PgPool;
 pub struct RemoveCommentForm {
     guild_tag: String,
     comment_id: i32,
+    reason: Option<String>,
 }
 
 pub async fn handler(
     remove_comment_form: web::Json<RemoveCommentForm>,
     db_pool: web::Data<PgPool>,
     session: Session,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let formatted_form = RemoveCommentForm {
         guild_tag: remove_comment_form.guild_tag.to_lowercase(),
         comment_id: remove_comment_form.comment_id,
+        reason: format_reason(&remove_comment_form.reason)?,
     };
-    let valid_session = session_validation::policy_guild_moderator_or_admin(
+    let guild = Guild::find_by_guild_tag(&formatted_form.guild_tag, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    let actor = session_validation::policy_guild_moderator_or_admin(
         &session,
-        &formatted_form.guild_tag,
+        &guild.guild_id,
         db_pool.get_ref(),
     )
-    .await;
-    match valid_session {
-        Ok((None, Some(user))) => {
-            //make sure comment exists
-            let comment_exists =
-                Comment::find_by_comment_id(&formatted_form.comment_id, db_pool.get_ref()).await;
-            match comment_exists {
-                Ok(Some(comment)) => {
-                    //delete comment
-                    let mut tx = db_pool.begin().await.unwrap();
-                    let deleted_comment = Comment::delete(&comment.comment_id, &mut tx).await;
-                    match deleted_comment {
-                        Ok(()) => {
-                            let succesful_commit = tx.commit().await;
-                            match succesful_commit {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error committing transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            return HttpResponse::Ok().body("Comment deleted.");
-                        }
-                        Err(err) => {
-                            let succesful_rollback = tx.rollback().await;
-                            match succesful_rollback {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error rolling back transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            error!("Error deleting comment: {}", err);
-                            return HttpResponse::InternalServerError()
-                                .body("Error deleting comment.");
-                        }
-                    }
-                }
-                Ok(None) => {
-                    return HttpResponse::BadRequest()
-                        .body("The comment you are trying to remove does not exist.");
-                }
-                Err(err) => {
-                    error!("Error fetching comment: {}", err);
-                    return HttpResponse::InternalServerError().body("Error fetching comment.");
-                }
-            }
-        }
-        Ok((Some(response), None)) => {
-            return response;
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
+    .await?;
+    //make sure comment exists
+    let comment = Comment::find_by_comment_id(&formatted_form.comment_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| {
+            ApiError::not_found("The comment you are trying to remove does not exist.")
+        })?;
+    //mods can only remove comments in their own guild
+    let post = Post::find_by_post_id(&comment.post_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("The comment's post does not exist."))?;
+    if post.guild_id != guild.guild_id {
+        return Err(ApiError::forbidden("That comment is not in this guild."));
     }
+    //delete comment
+    let mut tx = db_pool.begin().await?;
+    Comment::delete(&comment.comment_id, &mut tx).await?;
+    ModerationAction::create(
+        &ModerationActionForm {
+            guild_id: Some(guild.guild_id),
+            target_user_id: Some(comment.user_id),
+            target_post_id: Some(comment.post_id),
+            target_comment_id: Some(comment.comment_id),
+            reason: formatted_form.reason,
+            ..ModerationActionForm::new(actor.user_id, ModerationActionType::RemoveComment)
+        },
+        &mut tx,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Comment deleted."))
 }
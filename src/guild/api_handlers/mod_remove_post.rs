@@ -1,7 +1,10 @@
+use crate::guild::*;
+use crate::moderation_action::*;
 use crate::post::*;
+use crate::utils::api_error::ApiError;
 use crate::utils::session_validation;
 use actix_session::Session;
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
@@ -9,81 +12,50 @@ use sqlx::PgPool;
 pub struct RemovePostForm {
     guild_tag: String,
     post_id: i32,
+    reason: Option<String>,
 }
 
 pub async fn handler(
     remove_post_form: web::Json<RemovePostForm>,
     db_pool: web::Data<PgPool>,
     session: Session,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let formatted_form = RemovePostForm {
         guild_tag: remove_post_form.guild_tag.to_lowercase(),
         post_id: remove_post_form.post_id,
+        reason: format_reason(&remove_post_form.reason)?,
     };
-    let valid_session = session_validation::policy_guild_moderator_or_admin(
+    let guild = Guild::find_by_guild_tag(&formatted_form.guild_tag, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    let actor = session_validation::policy_guild_moderator_or_admin(
         &session,
-        &formatted_form.guild_tag,
+        &guild.guild_id,
         db_pool.get_ref(),
     )
-    .await;
-    match valid_session {
-        Ok((None, Some(user))) => {
-            //make sure post exists
-            let post_exists =
-                Post::find_by_post_id(&formatted_form.post_id, db_pool.get_ref()).await;
-            match post_exists {
-                Ok(Some(post)) => {
-                    //delete post
-                    let mut tx = db_pool.begin().await.unwrap();
-                    let deleted_post = Post::delete(&post.post_id, &mut tx).await;
-                    match deleted_post {
-                        Ok(()) => {
-                            let succesful_commit = tx.commit().await;
-                            match succesful_commit {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error committing transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            return HttpResponse::Ok().body("Post deleted.");
-                        }
-                        Err(err) => {
-                            let succesful_rollback = tx.rollback().await;
-                            match succesful_rollback {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error rolling back transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            error!("Error deleting post: {}", err);
-                            return HttpResponse::InternalServerError()
-                                .body("Error deleting post.");
-                        }
-                    }
-                }
-                Ok(None) => {
-                    return HttpResponse::BadRequest()
-                        .body("The post you are trying to remove does not exist.");
-                }
-                Err(err) => {
-                    error!("Error fetching post: {}", err);
-                    return HttpResponse::InternalServerError().body("Error fetching post.");
-                }
-            }
-        }
-        Ok((Some(response), None)) => {
-            return response;
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
+    .await?;
+    //make sure post exists
+    let post = Post::find_by_post_id(&formatted_form.post_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("The post you are trying to remove does not exist."))?;
+    //mods can only remove posts in their own guild
+    if post.guild_id != guild.guild_id {
+        return Err(ApiError::forbidden("That post is not in this guild."));
     }
+    //delete post
+    let mut tx = db_pool.begin().await?;
+    Post::delete(&post.post_id, &mut tx).await?;
+    ModerationAction::create(
+        &ModerationActionForm {
+            guild_id: Some(guild.guild_id),
+            target_user_id: Some(post.user_id),
+            target_post_id: Some(post.post_id),
+            reason: formatted_form.reason,
+            ..ModerationActionForm::new(actor.user_id, ModerationActionType::RemovePost)
+        },
+        &mut tx,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Post deleted."))
 }
@@ -1,13 +1,19 @@
 use crate::guild::*;
+use crate::media::ImageReference;
+use crate::utils::api_error::ApiError;
+use crate::utils::media_store::MediaStore;
 use crate::utils::session_validation;
 use actix_session::Session;
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{post, web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
+//either a url or the media_id of an image the moderator uploaded, an empty url with no media_id clears it
 #[derive(Serialize, Deserialize, Clone)]
 pub struct UpdateGuildAvatarForm {
+    #[serde(default)]
     avatar_url: String,
+    avatar_media_id: Option<i32>,
 }
 
 #[post("/{guild_tag}/mod/updateavatar")]
@@ -15,8 +21,9 @@ pub async fn handler(
     guild_tag: web::Path<String>,
     update_form: web::Json<UpdateGuildAvatarForm>,
     db_pool: web::Data<PgPool>,
+    media_store: web::Data<dyn MediaStore>,
     session: Session,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let formatted_tag = guild_tag.to_string().to_lowercase();
     let formatted_avatar_url: Option<String>;
     if update_form.avatar_url == "" {
@@ -24,55 +31,26 @@ pub async fn handler(
     } else {
         formatted_avatar_url = Some(update_form.avatar_url.clone());
     }
-    let valid_session = session_validation::policy_guild_moderator_or_admin(
+    let guild = Guild::find_by_guild_tag(&formatted_tag, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    let user = session_validation::policy_guild_moderator_or_admin(
         &session,
-        &formatted_tag,
+        &guild.guild_id,
         db_pool.get_ref(),
     )
-    .await;
-    match valid_session {
-        Ok((None, Some(user))) => {
-            //update avatar
-            let mut tx = db_pool.begin().await.unwrap();
-            let updated_avatar =
-                Guild::update_guild_description(&formatted_avatar_url, &formatted_tag, &mut tx)
-                    .await;
-            match updated_avatar {
-                Ok(()) => {
-                    let succesful_commit = tx.commit().await;
-                    match succesful_commit {
-                        Ok(()) => (),
-                        Err(err) => {
-                            error!("Error committing transaction: {}", err);
-                            return HttpResponse::InternalServerError().body("Unknown Error.");
-                        }
-                    }
-                    return HttpResponse::Ok().body("Guild avatar updated successfully.");
-                }
-                Err(err) => {
-                    let succesful_rollback = tx.rollback().await;
-                    match succesful_rollback {
-                        Ok(()) => (),
-                        Err(err) => {
-                            error!("Error rolling back transaction: {}", err);
-                            return HttpResponse::InternalServerError().body("Unknown Error.");
-                        }
-                    }
-                    error!("Error updating guild avatar: {}", err);
-                    return HttpResponse::InternalServerError()
-                        .body("Error updating guild avatar.");
-                }
-            }
-        }
-        Ok((Some(response), None)) => {
-            return response;
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
-    }
+    .await?;
+    let new_avatar = ImageReference::resolve(
+        &update_form.avatar_media_id,
+        formatted_avatar_url,
+        &user.user_id,
+        &**media_store,
+        db_pool.get_ref(),
+    )
+    .await?;
+    //update avatar
+    let mut tx = db_pool.begin().await?;
+    Guild::update_guild_avatar(&new_avatar, &guild.guild_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Guild avatar updated successfully."))
 }
@@ -1,13 +1,19 @@
 use crate::guild::*;
+use crate::media::ImageReference;
+use crate::utils::api_error::ApiError;
+use crate::utils::media_store::MediaStore;
 use crate::utils::session_validation;
 use actix_session::Session;
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{post, web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
+//either a url or the media_id of an image the moderator uploaded, an empty url with no media_id clears it
 #[derive(Serialize, Deserialize, Clone)]
 pub struct UpdateGuildBannerForm {
+    #[serde(default)]
     banner_url: String,
+    banner_media_id: Option<i32>,
 }
 
 #[post("/{guild_tag}/mod/updatebanner")]
@@ -15,8 +21,9 @@ pub async fn handler(
     guild_tag: web::Path<String>,
     update_form: web::Json<UpdateGuildBannerForm>,
     db_pool: web::Data<PgPool>,
+    media_store: web::Data<dyn MediaStore>,
     session: Session,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let formatted_tag = guild_tag.to_string().to_lowercase();
     let formatted_banner_url: Option<String>;
     if update_form.banner_url == "" {
@@ -24,55 +31,26 @@ pub async fn handler(
     } else {
         formatted_banner_url = Some(update_form.banner_url.clone());
     }
-    let valid_session = session_validation::policy_guild_moderator_or_admin(
+    let guild = Guild::find_by_guild_tag(&formatted_tag, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    let user = session_validation::policy_guild_moderator_or_admin(
         &session,
-        &formatted_tag,
+        &guild.guild_id,
         db_pool.get_ref(),
     )
-    .await;
-    match valid_session {
-        Ok((None, Some(user))) => {
-            //update banner
-            let mut tx = db_pool.begin().await.unwrap();
-            let updated_banner =
-                Guild::update_guild_description(&formatted_banner_url, &formatted_tag, &mut tx)
-                    .await;
-            match updated_banner {
-                Ok(()) => {
-                    let succesful_commit = tx.commit().await;
-                    match succesful_commit {
-                        Ok(()) => (),
-                        Err(err) => {
-                            error!("Error committing transaction: {}", err);
-                            return HttpResponse::InternalServerError().body("Unknown Error.");
-                        }
-                    }
-                    return HttpResponse::Ok().body("Guild banner updated successfully.");
-                }
-                Err(err) => {
-                    let succesful_rollback = tx.rollback().await;
-                    match succesful_rollback {
-                        Ok(()) => (),
-                        Err(err) => {
-                            error!("Error rolling back transaction: {}", err);
-                            return HttpResponse::InternalServerError().body("Unknown Error.");
-                        }
-                    }
-                    error!("Error updating guild banner: {}", err);
-                    return HttpResponse::InternalServerError()
-                        .body("Error updating guild banner.");
-                }
-            }
-        }
-        Ok((Some(response), None)) => {
-            return response;
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
-    }
+    .await?;
+    let new_banner = ImageReference::resolve(
+        &update_form.banner_media_id,
+        formatted_banner_url,
+        &user.user_id,
+        &**media_store,
+        db_pool.get_ref(),
+    )
+    .await?;
+    //update banner
+    let mut tx = db_pool.begin().await?;
+    Guild::update_guild_banner(&new_banner, &guild.guild_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Guild banner updated successfully."))
 }
@@ -0,0 +1,59 @@
+use crate::guild::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UpdateGuildTagForm {
+    new_guild_tag: String,
+}
+
+#[post("/{guild_tag}/admin/updatetag")]
+pub async fn handler(
+    guild_tag: web::Path<String>,
+    update_form: web::Json<UpdateGuildTagForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let formatted_tag = guild_tag.to_string().to_lowercase();
+    let new_guild_tag = update_form.new_guild_tag.to_lowercase();
+    //same rules as create_guild
+    if new_guild_tag.is_empty()
+        || new_guild_tag.len() > 15
+        || !new_guild_tag.chars().all(char::is_alphanumeric)
+    {
+        return Err(ApiError::invalid_field(
+            "new_guild_tag",
+            "Guild tags can only contain alphanumeric characters, and must be less than 15 characters",
+        ));
+    }
+    let guild = Guild::find_by_guild_tag(&formatted_tag, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    session_validation::policy_guild_admin(&session, &guild.guild_id, db_pool.get_ref()).await?;
+    if new_guild_tag == guild.guild_tag {
+        return Err(ApiError::invalid_field(
+            "new_guild_tag",
+            "That is already the guild's tag.",
+        ));
+    }
+    //taken by another guild, either as its tag or as one it used to have. the guild's own old tags are fine
+    if let Some(existing_guild) =
+        Guild::find_by_guild_tag(&new_guild_tag, db_pool.get_ref()).await?
+    {
+        if existing_guild.guild_id != guild.guild_id {
+            return Err(ApiError::conflicting_field(
+                "new_guild_tag",
+                "A guild with that tag already exists.",
+            ));
+        }
+    }
+    //update tag
+    let mut tx = db_pool.begin().await?;
+    Guild::update_guild_tag(&new_guild_tag, &guild, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Guild tag updated successfully."))
+}
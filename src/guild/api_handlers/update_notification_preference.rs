@@ -0,0 +1,41 @@
+use crate::guild::*;
+use crate::guild_membership::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/notifications/{guild_tag}")]
+pub async fn handler(
+    guild_tag: web::Path<String>,
+    preference_form: web::Json<NotificationPreferenceForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let formatted_guild_tag = guild_tag.to_string().to_lowercase();
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let notification_preference = preference_form.notification_preference.to_lowercase();
+    if !NOTIFICATION_PREFERENCES.contains(&notification_preference.as_str()) {
+        return Err(ApiError::invalid_field(
+            "notification_preference",
+            "Notification preference must be off, all or top.",
+        ));
+    }
+    let guild = Guild::find_by_guild_tag(&formatted_guild_tag, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    //make sure user is in guild
+    let membership = GuildMembership::find_by_user_and_guild_id(
+        &user.user_id,
+        &guild.guild_id,
+        db_pool.get_ref(),
+    )
+    .await?
+    .ok_or_else(|| ApiError::validation("You are not a member of this guild."))?;
+    let mut tx = db_pool.begin().await?;
+    GuildMembership::update_notification_preference(&notification_preference, membership, &mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Notification preference updated."))
+}
@@ -1,9 +1,11 @@
+use crate::media::ImageReference;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Postgres, Transaction};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Guild {
+    pub guild_id: i32,
     pub guild_tag: String,
     pub guild_name: String,
     pub guild_description: Option<String>,
@@ -24,30 +26,56 @@ pub struct GuildJoinForm {
 }
 
 impl Guild {
-    pub async fn create(guild_form: &GuildForm, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
-        sqlx::query!(
+    //returns the new guild's id
+    pub async fn create(guild_form: &GuildForm, tx: &mut Transaction<'_, Postgres>) -> Result<i32> {
+        let guild = sqlx::query!(
             r#"
             INSERT INTO guilds (guild_tag, guild_name)
             VALUES ($1, $2)
+            RETURNING guild_id
             "#,
             guild_form.guild_tag,
             guild_form.guild_name
         )
-        .execute(tx)
+        .fetch_one(tx)
         .await?;
-        Ok(())
+        Ok(guild.guild_id)
     }
+    //also finds guilds by a tag they used to have, check guild_tag on the result for the current one
     pub async fn find_by_guild_tag(guild_tag: &String, pool: &PgPool) -> Result<Option<Guild>> {
         let guild = sqlx::query!(
             r#"
             SELECT * FROM guilds
-            WHERE guild_tag = $1
+            WHERE guild_id = resolve_guild_tag($1)
             "#,
             guild_tag
         )
         .fetch_optional(&*pool)
         .await?;
         Ok(guild.map(|guild| Guild {
+            guild_id: guild.guild_id,
+            guild_tag: guild.guild_tag,
+            guild_name: guild.guild_name,
+            guild_description: guild.guild_description,
+            avatar_url: guild.avatar_url,
+            banner_url: guild.banner_url,
+            is_banned: guild.is_banned,
+            created_at: guild.created_at.to_string(), //convert time to string
+        }))
+    }
+
+    pub async fn find_by_guild_id(guild_id: &i32, pool: &PgPool) -> Result<Option<Guild>> {
+        let guild = sqlx::query!(
+            r#"
+            SELECT * FROM guilds
+            WHERE guild_id = $1
+            "#,
+            guild_id
+        )
+        .fetch_optional(&*pool)
+        .await?;
+        Ok(guild.map(|guild| Guild {
+            guild_id: guild.guild_id,
             guild_tag: guild.guild_tag,
             guild_name: guild.guild_name,
             guild_description: guild.guild_description,
@@ -66,7 +94,7 @@ impl Guild {
     ) -> Result<Vec<Guild>> {
         let guilds = sqlx::query!(
             r#"
-            SELECT guilds.*, (SELECT COUNT(*) FROM guild_memberships WHERE guild_memberships.guild_tag = guilds.guild_tag) AS members FROM guilds
+            SELECT guilds.*, (SELECT COUNT(*) FROM guild_memberships WHERE guild_memberships.guild_id = guilds.guild_id) AS members FROM guilds
             ORDER BY members
             LIMIT $1
             OFFSET $2
@@ -78,6 +106,7 @@ impl Guild {
         .await?
         .into_iter()
         .map(|guild| Guild {
+            guild_id: guild.guild_id,
             guild_tag: guild.guild_tag,
             guild_name: guild.guild_name,
             guild_description: guild.guild_description,
@@ -93,16 +122,16 @@ impl Guild {
     //update name
     pub async fn update_guild_name(
         new_guild_name: String,
-        guild_tag: &String,
+        guild_id: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
         sqlx::query!(
             r#"
             UPDATE guilds
             SET guild_name = $2
-            WHERE guild_tag = $1
+            WHERE guild_id = $1
             "#,
-            guild_tag,
+            guild_id,
             new_guild_name
         )
         .execute(tx)
@@ -110,33 +139,51 @@ impl Guild {
 
         Ok(())
     }
-    /*TODO: probably need to refactor a ton of this code to add guild ids, and allow for changing guild tags... really stupid decision I made here... not sure why..
-    :(
-    //update url
+    //update url. the old tag is kept as an alias so existing links keep resolving,
+    //and going back to one of the guild's own old tags just drops that alias
     pub async fn update_guild_tag(
         new_guild_tag: &String,
-        old_guild: Guild,
+        old_guild: &Guild,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            DELETE FROM guild_tag_aliases
+            WHERE guild_tag = $1 AND guild_id = $2
+            "#,
+            new_guild_tag,
+            old_guild.guild_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO guild_tag_aliases (guild_tag, guild_id)
+            VALUES ($1, $2)
+            "#,
+            old_guild.guild_tag,
+            old_guild.guild_id
+        )
+        .execute(&mut *tx)
+        .await?;
         sqlx::query!(
             r#"
             UPDATE guilds
             SET guild_tag = $2
-            WHERE guild_tag = $1
+            WHERE guild_id = $1
             "#,
-            old_guild.guild_tag,
+            old_guild.guild_id,
             new_guild_tag
         )
-        .execute(tx)
+        .execute(&mut *tx)
         .await?;
 
         Ok(())
     }
-    */
     //update description
     pub async fn update_guild_description(
         new_guild_description: &Option<String>,
-        guild_tag: &String,
+        guild_id: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
         match new_guild_description {
@@ -145,9 +192,9 @@ impl Guild {
                     r#"
                     UPDATE guilds
                     SET guild_description = $2
-                    WHERE guild_tag = $1
+                    WHERE guild_id = $1
                     "#,
-                    guild_tag,
+                    guild_id,
                     description
                 )
                 .execute(tx)
@@ -158,9 +205,9 @@ impl Guild {
                     r#"
                     UPDATE guilds
                     SET guild_description = NULL
-                    WHERE guild_tag = $1
+                    WHERE guild_id = $1
                     "#,
-                    guild_tag,
+                    guild_id,
                 )
                 .execute(tx)
                 .await?;
@@ -169,91 +216,59 @@ impl Guild {
 
         Ok(())
     }
-    //update avatar url
+    //update avatar, from a url or an uploaded image
     pub async fn update_guild_avatar(
-        new_avatar_url: &Option<String>,
-        guild_tag: &String,
+        new_avatar: &ImageReference,
+        guild_id: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
-        match new_avatar_url {
-            Some(avatar_url) => {
-                sqlx::query!(
-                    r#"
-                    UPDATE guilds
-                    SET avatar_url = $2
-                    WHERE guild_tag = $1
-                    "#,
-                    guild_tag,
-                    avatar_url
-                )
-                .execute(tx)
-                .await?;
-            }
-            None => {
-                sqlx::query!(
-                    r#"
-                    UPDATE guilds
-                    SET avatar_url = NULL
-                    WHERE guild_tag = $1
-                    "#,
-                    guild_tag,
-                )
-                .execute(tx)
-                .await?;
-            }
-        }
-
+        sqlx::query!(
+            r#"
+            UPDATE guilds
+            SET avatar_url = $2, avatar_media_id = $3
+            WHERE guild_id = $1
+            "#,
+            guild_id,
+            new_avatar.url.as_deref(),
+            new_avatar.media_id
+        )
+        .execute(tx)
+        .await?;
         Ok(())
     }
-    //update banner url
+    //update banner, from a url or an uploaded image
     pub async fn update_guild_banner(
-        new_banner_url: &Option<String>,
-        guild_tag: &String,
+        new_banner: &ImageReference,
+        guild_id: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
-        match new_banner_url {
-            Some(banner_url) => {
-                sqlx::query!(
-                    r#"
-                    UPDATE guilds
-                    SET banner_url = $2
-                    WHERE guild_tag = $1
-                    "#,
-                    guild_tag,
-                    banner_url
-                )
-                .execute(tx)
-                .await?;
-            }
-            None => {
-                sqlx::query!(
-                    r#"
-                    UPDATE guilds
-                    SET banner_url = NULL
-                    WHERE guild_tag = $1
-                    "#,
-                    guild_tag,
-                )
-                .execute(tx)
-                .await?;
-            }
-        }
-
+        sqlx::query!(
+            r#"
+            UPDATE guilds
+            SET banner_url = $2, banner_media_id = $3
+            WHERE guild_id = $1
+            "#,
+            guild_id,
+            new_banner.url.as_deref(),
+            new_banner.media_id
+        )
+        .execute(tx)
+        .await?;
         Ok(())
     }
     //update banned status
     pub async fn update_guild_ban_status(
         new_ban_status: bool,
-        guild_tag: &String,
+        guild_id: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
         sqlx::query!(
             r#"
             UPDATE guilds
             SET is_banned = $2
-            WHERE guild_tag = $1
+            WHERE guild_id = $1
             "#,
-            guild_tag,
+            guild_id,
             new_ban_status
         )
         .execute(tx)
@@ -262,15 +277,25 @@ impl Guild {
         Ok(())
     }
 
-    pub async fn delete(guild_tag: &String, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+    //posts and memberships go with the guild through the foreign keys, but comments don't cascade from posts
+    pub async fn delete(guild_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            DELETE FROM comments
+            WHERE post_id IN (SELECT post_id FROM posts WHERE guild_id = $1)
+            "#,
+            guild_id
+        )
+        .execute(&mut *tx)
+        .await?;
         sqlx::query!(
             r#"
             DELETE FROM guilds
-            where guild_tag = $1
+            where guild_id = $1
             "#,
-            guild_tag
+            guild_id
         )
-        .execute(tx)
+        .execute(&mut *tx)
         .await?;
         Ok(())
     }
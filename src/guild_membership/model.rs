@@ -1,3 +1,4 @@
+use crate::user::{BanDetails, BanForm};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Postgres, Transaction};
@@ -6,16 +7,26 @@ use sqlx::{PgPool, Postgres, Transaction};
 pub struct GuildMembership {
     pub membership_id: i32,
     pub user_id: i32,
-    pub guild_tag: String,
+    pub guild_id: i32,
     pub is_admin: bool,
     pub is_moderator: bool,
     pub is_banned: bool,
+    pub notification_preference: String, //off, all or top, see NOTIFICATION_PREFERENCES
+}
+
+//off: no post notifications, all: every new post, top: posts that reach POST_NOTIFICATION_TOP_SCORE within a day
+pub const NOTIFICATION_PREFERENCES: [&str; 3] = ["off", "all", "top"];
+
+//eg {"notification_preference": "top"}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NotificationPreferenceForm {
+    pub notification_preference: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GuildMembershipForm {
     pub user_id: i32,
-    pub guild_tag: String,
+    pub guild_id: i32,
 }
 
 impl GuildMembership {
@@ -25,11 +36,11 @@ impl GuildMembership {
     ) -> Result<()> {
         sqlx::query!(
             r#"
-                INSERT INTO guild_memberships (user_id, guild_tag)
+                INSERT INTO guild_memberships (user_id, guild_id)
                 VALUES ($1, $2)
             "#,
             guild_membership_form.user_id,
-            guild_membership_form.guild_tag,
+            guild_membership_form.guild_id,
         )
         .execute(tx)
         .await?;
@@ -41,11 +52,11 @@ impl GuildMembership {
     ) -> Result<()> {
         sqlx::query!(
             r#"
-                INSERT INTO guild_memberships (user_id, guild_tag, is_admin)
+                INSERT INTO guild_memberships (user_id, guild_id, is_admin)
                 VALUES ($1, $2, $3)
             "#,
             guild_membership_form.user_id,
-            guild_membership_form.guild_tag,
+            guild_membership_form.guild_id,
             true
         )
         .execute(tx)
@@ -69,10 +80,11 @@ impl GuildMembership {
         Ok(membership.map(|membership| GuildMembership {
             membership_id: membership.membership_id,
             user_id: membership.user_id,
-            guild_tag: membership.guild_tag,
+            guild_id: membership.guild_id,
             is_admin: membership.is_admin,
             is_moderator: membership.is_moderator,
             is_banned: membership.is_banned,
+            notification_preference: membership.notification_preference,
         }))
     }
 
@@ -99,10 +111,11 @@ impl GuildMembership {
             .map(|membership| GuildMembership {
                 membership_id: membership.membership_id,
                 user_id: membership.user_id,
-                guild_tag: membership.guild_tag,
+                guild_id: membership.guild_id,
                 is_admin: membership.is_admin,
                 is_moderator: membership.is_moderator,
                 is_banned: membership.is_banned,
+                notification_preference: membership.notification_preference,
             })
             .collect();
             Ok(memberships)
@@ -126,10 +139,11 @@ impl GuildMembership {
             .map(|membership| GuildMembership {
                 membership_id: membership.membership_id,
                 user_id: membership.user_id,
-                guild_tag: membership.guild_tag,
+                guild_id: membership.guild_id,
                 is_admin: membership.is_admin,
                 is_moderator: membership.is_moderator,
                 is_banned: membership.is_banned,
+                notification_preference: membership.notification_preference,
             })
             .collect();
             Ok(memberships)
@@ -137,8 +151,8 @@ impl GuildMembership {
     }
 
     //paginated
-    pub async fn find_all_by_guild_tag(
-        guild_tag: &String,
+    pub async fn find_all_by_guild_id(
+        guild_id: &i32,
         pool: &PgPool,
         results_per_page: &i64,
         page_number: &i64,
@@ -147,12 +161,12 @@ impl GuildMembership {
             r#"
             SELECT *
             FROM guild_memberships
-            WHERE guild_tag = $1
+            WHERE guild_id = $1
             ORDER BY membership_id
             LIMIT $2
             OFFSET $3
             "#,
-            guild_tag,
+            guild_id,
             results_per_page,
             ((page_number - 1) * results_per_page)
         )
@@ -162,38 +176,40 @@ impl GuildMembership {
         .map(|membership| GuildMembership {
             membership_id: membership.membership_id,
             user_id: membership.user_id,
-            guild_tag: membership.guild_tag,
+            guild_id: membership.guild_id,
             is_admin: membership.is_admin,
             is_moderator: membership.is_moderator,
             is_banned: membership.is_banned,
+            notification_preference: membership.notification_preference,
         })
         .collect();
 
         Ok(memberships)
     }
 
-    pub async fn find_by_user_and_guild_tag(
+    pub async fn find_by_user_and_guild_id(
         user_id: &i32,
-        guild_tag: &String,
+        guild_id: &i32,
         pool: &PgPool,
     ) -> Result<Option<GuildMembership>> {
         let membership = sqlx::query!(
             r#"
             SELECT * FROM guild_memberships
-            WHERE user_id = $1 AND guild_tag = $2
+            WHERE user_id = $1 AND guild_id = $2
             "#,
             user_id,
-            guild_tag
+            guild_id
         )
         .fetch_optional(&*pool)
         .await?;
         Ok(membership.map(|membership| GuildMembership {
             membership_id: membership.membership_id,
             user_id: membership.user_id,
-            guild_tag: membership.guild_tag,
+            guild_id: membership.guild_id,
             is_admin: membership.is_admin,
             is_moderator: membership.is_moderator,
             is_banned: membership.is_banned,
+            notification_preference: membership.notification_preference,
         }))
     }
 
@@ -237,20 +253,62 @@ impl GuildMembership {
 
         Ok(())
     }
-    //update banned status
-    pub async fn update_membership_ban_status(
-        new_ban_status: bool,
+    pub async fn update_notification_preference(
+        notification_preference: &String,
+        old_membership: GuildMembership,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE guild_memberships
+            SET notification_preference = $2
+            WHERE membership_id = $1
+            "#,
+            old_membership.membership_id,
+            notification_preference
+        )
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+    //guild ban. banned_by is the mod or admin who issued it, no duration_hours means permanent
+    pub async fn ban(
         old_membership: GuildMembership,
+        ban: &BanForm,
+        banned_by: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
         sqlx::query!(
             r#"
             UPDATE guild_memberships
-            SET is_banned = $2
+            SET is_banned = TRUE, ban_reason = $2, ban_message = $3, banned_by = $4, banned_at = LOCALTIMESTAMP,
+            ban_expires_at = LOCALTIMESTAMP + $5::INTEGER * INTERVAL '1 hour'
             WHERE membership_id = $1
             "#,
             old_membership.membership_id,
-            new_ban_status
+            ban.reason,
+            ban.message,
+            banned_by,
+            ban.duration_hours
+        )
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unban(
+        old_membership: GuildMembership,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE guild_memberships
+            SET is_banned = FALSE, ban_reason = NULL, ban_message = NULL, banned_by = NULL, banned_at = NULL, ban_expires_at = NULL
+            WHERE membership_id = $1
+            "#,
+            old_membership.membership_id
         )
         .execute(tx)
         .await?;
@@ -258,6 +316,40 @@ impl GuildMembership {
         Ok(())
     }
 
+    //same as User::find_active_ban, for a guild ban
+    pub async fn find_active_ban(membership_id: &i32, pool: &PgPool) -> Result<Option<BanDetails>> {
+        let ban = sqlx::query!(
+            r#"
+            SELECT ban_reason, ban_message, banned_at, ban_expires_at FROM guild_memberships
+            WHERE membership_id = $1 AND is_banned
+            AND (ban_expires_at IS NULL OR ban_expires_at > LOCALTIMESTAMP)
+            "#,
+            membership_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(ban.map(|ban| BanDetails {
+            reason: ban.ban_reason,
+            message: ban.ban_message,
+            banned_at: ban.banned_at.map(|b| b.to_string()),
+            expires_at: ban.ban_expires_at.map(|e| e.to_string()),
+        }))
+    }
+
+    //returns how many bans were lifted
+    pub async fn lift_expired_bans(pool: &PgPool) -> Result<u64> {
+        let lifted = sqlx::query!(
+            r#"
+            UPDATE guild_memberships
+            SET is_banned = FALSE, ban_reason = NULL, ban_message = NULL, banned_by = NULL, banned_at = NULL, ban_expires_at = NULL
+            WHERE is_banned AND ban_expires_at IS NOT NULL AND ban_expires_at <= LOCALTIMESTAMP
+            "#
+        )
+        .execute(pool)
+        .await?;
+        Ok(lifted.rows_affected())
+    }
+
     pub async fn delete(
         guild_membership: GuildMembership,
         tx: &mut Transaction<'_, Postgres>,
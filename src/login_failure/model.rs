@@ -0,0 +1,136 @@
+use anyhow::Result;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::time::Duration;
+
+//after free_attempts failures each attempt has to wait 1, 2, 4... seconds up to max_delay_seconds,
+//and from lockout_threshold failures on the key is locked for lockout_minutes at a time
+pub struct LockoutPolicy {
+    free_attempts: i32,
+    max_delay_seconds: i64,
+    lockout_threshold: i32,
+    lockout_minutes: i64,
+}
+
+pub const ACCOUNT_LOCKOUT: LockoutPolicy = LockoutPolicy {
+    free_attempts: 3,
+    max_delay_seconds: 60,
+    lockout_threshold: 10,
+    lockout_minutes: 15,
+};
+
+//ips can be shared by a lot of people, so they get more room
+pub const IP_LOCKOUT: LockoutPolicy = LockoutPolicy {
+    free_attempts: 20,
+    max_delay_seconds: 60,
+    lockout_threshold: 100,
+    lockout_minutes: 15,
+};
+
+impl LockoutPolicy {
+    pub fn is_locked_out(&self, failure_count: i32) -> bool {
+        failure_count >= self.lockout_threshold
+    }
+    fn delay_seconds(&self, failure_count: i32) -> i64 {
+        if self.is_locked_out(failure_count) {
+            self.lockout_minutes * 60
+        } else if failure_count > self.free_attempts {
+            let doublings = (failure_count - self.free_attempts - 1).min(30) as u32;
+            2_i64.pow(doublings).min(self.max_delay_seconds)
+        } else {
+            0
+        }
+    }
+}
+
+pub struct LoginFailure {
+    pub failure_key: String,
+    pub failure_count: i32,
+}
+
+impl LoginFailure {
+    //usernames are tracked whether or not they exist, see the login_failures migration
+    pub fn account_key(username: &str) -> String {
+        format!("account:{}", username.to_lowercase())
+    }
+    pub fn ip_key(ip_address: &str) -> String {
+        format!("ip:{}", ip_address)
+    }
+    //how long until every key is allowed another attempt, None if they all are now
+    pub async fn find_wait(failure_keys: &[String], pool: &PgPool) -> Result<Option<Duration>> {
+        let wait = sqlx::query!(
+            r#"
+            SELECT max(extract(epoch FROM locked_until - LOCALTIMESTAMP))::FLOAT8 AS wait
+            FROM login_failures
+            WHERE failure_key = ANY($1) AND locked_until > LOCALTIMESTAMP
+            "#,
+            failure_keys
+        )
+        .fetch_one(pool)
+        .await?
+        .wait;
+        Ok(wait.map(Duration::from_secs_f64))
+    }
+    //counts a failure and pushes back the next allowed attempt. a day without failures starts the count over
+    pub async fn record(
+        failure_key: &str,
+        policy: &LockoutPolicy,
+        pool: &PgPool,
+    ) -> Result<LoginFailure> {
+        let mut tx = pool.begin().await?;
+        let failure = sqlx::query!(
+            r#"
+            INSERT INTO login_failures (failure_key, failure_count)
+            VALUES ($1, 1)
+            ON CONFLICT (failure_key) DO UPDATE
+            SET failure_count = CASE WHEN login_failures.last_failed_at < LOCALTIMESTAMP - INTERVAL '1 day' THEN 1 ELSE login_failures.failure_count + 1 END,
+            last_failed_at = LOCALTIMESTAMP
+            RETURNING failure_key, failure_count
+            "#,
+            failure_key
+        )
+        .fetch_one(&mut tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE login_failures
+            SET locked_until = LOCALTIMESTAMP + $2 * INTERVAL '1 second'
+            WHERE failure_key = $1
+            "#,
+            failure_key,
+            policy.delay_seconds(failure.failure_count) as f64
+        )
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(LoginFailure {
+            failure_key: failure.failure_key,
+            failure_count: failure.failure_count,
+        })
+    }
+    //after a successful login or a password reset
+    pub async fn clear(failure_key: &str, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            DELETE FROM login_failures
+            WHERE failure_key = $1
+            "#,
+            failure_key
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+    //keys that would start over on their next failure and aren't locked
+    pub async fn purge_stale(pool: &PgPool) -> Result<u64> {
+        let purged = sqlx::query!(
+            r#"
+            DELETE FROM login_failures
+            WHERE last_failed_at < LOCALTIMESTAMP - INTERVAL '1 day'
+            AND (locked_until IS NULL OR locked_until < LOCALTIMESTAMP)
+            "#
+        )
+        .execute(pool)
+        .await?;
+        Ok(purged.rows_affected())
+    }
+}
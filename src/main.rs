@@ -1,32 +1,42 @@
 #[macro_use]
 extern crate log;
 
-use actix_session::CookieSession;
 use actix_web::{middleware, web, App, HttpServer};
 use anyhow::Result;
 use dotenv::dotenv;
 use sqlx::postgres::PgPool;
+use utils::rate_limit::{RateLimit, RouteGroup};
 
+mod account_deletion;
 mod aggregates;
+mod api_token;
 mod block;
+mod bookmark;
 mod comment;
 mod comment_vote;
+mod email_change;
 mod guild;
 mod guild_membership;
+mod login_failure;
+mod markdown;
+mod media;
+mod moderation_action;
 mod notification;
 mod password_reset;
 mod post;
 mod post_vote;
+mod realtime;
 mod report;
 mod routes;
 mod search;
 mod site;
+mod two_factor;
 mod user;
+mod user_export;
 mod user_registration;
 mod user_session;
 mod utils;
 mod view;
-//mod bookmark;
 
 #[actix_web::main]
 async fn main() -> Result<()> {
@@ -36,23 +46,79 @@ async fn main() -> Result<()> {
         dotenv::var("DATABASE_URL").expect("DATABASE_URL is not set in the .env file");
     info!("using postgres database at: {}", &database_url);
     let db_pool = PgPool::connect(&database_url).await?;
+    let mailer = utils::mailer::mailer_from_env()?;
+    let media_store = utils::media_store::media_store_from_env()?;
+    let session_config =
+        utils::session_config::init(utils::session_config::SessionConfig::from_env()?);
+    async_std::task::spawn(utils::session_validation::purge_expired_sessions(
+        db_pool.clone(),
+    ));
+    async_std::task::spawn(utils::session_validation::lift_expired_bans(
+        db_pool.clone(),
+    ));
+    async_std::task::spawn(account_deletion::delete_scheduled_accounts(
+        db_pool.clone(),
+    ));
+    async_std::task::spawn(markdown::render_missing_bodies(db_pool.clone()));
+    async_std::task::spawn(notification::send_guild_post_notifications(
+        db_pool.clone(),
+    ));
+    let rate_limiter = utils::rate_limit::rate_limiter_from_env(&db_pool)?;
+    async_std::task::spawn(utils::rate_limit::purge_rate_limits(rate_limiter.clone()));
+    let event_hub = realtime::EventHub::new();
+    async_std::task::spawn(realtime::ping(event_hub.clone()));
+    let listener_hub = event_hub.clone();
+    async_std::task::spawn(async move {
+        if let Err(err) = realtime::listen(listener_hub, database_url).await {
+            error!("Realtime listener stopped: {}", err);
+        }
+    });
 
     let server = HttpServer::new(move || {
         App::new()
             .data(db_pool.clone())
+            .app_data(web::Data::from(mailer.clone()))
+            .app_data(web::Data::from(media_store.clone()))
+            .app_data(web::Data::from(event_hub.clone()))
+            .app_data(web::Data::from(rate_limiter.clone()))
+            .app_data(utils::api_error::json_config())
+            .app_data(utils::api_error::path_config())
+            .app_data(utils::api_error::query_config())
             .wrap(middleware::Logger::default())
-            //TODO: MAKE THIS COOKIE ENCRYPTED
-            .wrap(CookieSession::signed(&[0; 32]).secure(false))
+            .wrap(session_config.cookie_session())
+            .wrap(utils::api_token_auth::ApiTokenAuth)
             .configure(routes::registration::init)
             .service(web::scope("/user").configure(routes::user::init))
+            .service(
+                web::scope("/2fa")
+                    .wrap(RateLimit::new(RouteGroup::Auth))
+                    .configure(routes::two_factor::init),
+            )
+            .service(web::scope("/tokens").configure(routes::api_token::init))
             .service(web::scope("/guild").configure(routes::guild::init))
             .service(web::scope("/post").configure(routes::post::init))
             .service(web::scope("/comment").configure(routes::comment::init))
-            .service(web::scope("/vote").configure(routes::vote::init))
-            .service(web::scope("/resetpassword").configure(routes::reset_password::init))
+            .service(
+                web::scope("/vote")
+                    .wrap(RateLimit::new(RouteGroup::Vote))
+                    .configure(routes::vote::init),
+            )
+            .service(
+                web::scope("/resetpassword")
+                    .wrap(RateLimit::new(RouteGroup::Auth))
+                    .configure(routes::reset_password::init),
+            )
+            .service(web::scope("/changeemail").configure(routes::email_change::init))
             .service(web::scope("/admin").configure(routes::site::init))
             .service(web::scope("/report").configure(routes::report::init))
             .service(web::scope("/view").configure(routes::view::init))
+            .service(web::scope("/notifications").configure(routes::notification::init))
+            .service(web::scope("/bookmarks").configure(routes::bookmark::init))
+            .service(web::scope("/search").configure(routes::search::init))
+            .service(web::scope("/events").configure(routes::realtime::init))
+            .service(web::scope("/modlog").configure(routes::modlog::init))
+            .service(web::scope("/media").configure(routes::media::init))
+            .service(web::scope("/markdown").configure(routes::markdown::init))
     })
     .bind("127.0.0.1:4567")?;
 
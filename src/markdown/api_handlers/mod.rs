@@ -0,0 +1 @@
+pub mod preview_markdown;
@@ -0,0 +1,29 @@
+use crate::markdown::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//renders a post or comment draft exactly like saving it would, without saving anything
+#[post("/preview")]
+pub async fn handler(
+    preview_form: web::Json<MarkdownPreviewForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    if preview_form.body.len() > MAX_PREVIEW_LENGTH {
+        return Err(ApiError::invalid_field(
+            "body",
+            format!(
+                "Drafts must be no longer than {} characters.",
+                MAX_PREVIEW_LENGTH
+            ),
+        ));
+    }
+    let references = References::resolve(&preview_form.body, db_pool.get_ref()).await?;
+    Ok(HttpResponse::Ok().json(MarkdownPreview {
+        body_html: render(&preview_form.body, &references),
+    }))
+}
@@ -0,0 +1,347 @@
+use ammonia::Builder;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use pulldown_cmark::escape::escape_html;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgPool, Postgres};
+use std::collections::{HashMap, HashSet};
+use std::iter::FromIterator;
+
+//longest draft the preview route renders
+pub const MAX_PREVIEW_LENGTH: usize = 40_000;
+//rows rendered per query by render_missing_bodies
+const BACKFILL_BATCH_SIZE: i64 = 500;
+//longer names can't be usernames or guild tags, so they aren't looked up
+const MAX_REFERENCE_LENGTH: usize = 20;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarkdownPreviewForm {
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarkdownPreview {
+    pub body_html: String,
+}
+
+//only what plain commonmark produces, minus images so bodies can't load anything from other sites. raw html in a
+//body goes through the same filter, everything else is dropped (script and style along with their contents)
+static SANITIZER: Lazy<Builder<'static>> = Lazy::new(|| {
+    let mut sanitizer = Builder::default();
+    sanitizer
+        .tags(HashSet::from_iter(vec![
+            "p",
+            "br",
+            "hr",
+            "h1",
+            "h2",
+            "h3",
+            "h4",
+            "h5",
+            "h6",
+            "blockquote",
+            "pre",
+            "code",
+            "em",
+            "strong",
+            "ul",
+            "ol",
+            "li",
+            "a",
+        ]))
+        .tag_attributes(HashMap::from_iter(vec![
+            ("a", HashSet::from_iter(vec!["href", "title"])),
+            ("ol", HashSet::from_iter(vec!["start"])),
+        ]))
+        .generic_attributes(HashSet::new())
+        .url_schemes(HashSet::from_iter(vec!["http", "https", "mailto"]))
+        .link_rel(Some("nofollow noopener noreferrer"));
+    sanitizer
+});
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReferenceKind {
+    User,
+    Guild,
+}
+
+//an @username or g/guild_tag (or /g/guild_tag) in a piece of text, start and end are byte offsets.
+//name is lowercased like usernames and guild tags are
+struct Reference {
+    kind: ReferenceKind,
+    start: usize,
+    end: usize,
+    name: String,
+}
+
+//references have to start a word, so email addresses and paths inside urls are left alone
+fn find_references(text: &str) -> Vec<Reference> {
+    let mut references = vec![];
+    let mut previous: Option<char> = None;
+    let mut index = 0;
+    while let Some(c) = text[index..].chars().next() {
+        let starts_word = previous.is_none_or(|p| !p.is_alphanumeric() && !"/.@_-:".contains(p));
+        let rest = &text[index..];
+        let prefix = if !starts_word {
+            None
+        } else if rest.starts_with('@') {
+            Some((ReferenceKind::User, 1))
+        } else if rest.starts_with("g/") {
+            Some((ReferenceKind::Guild, 2))
+        } else if rest.starts_with("/g/") {
+            Some((ReferenceKind::Guild, 3))
+        } else {
+            None
+        };
+        if let Some((kind, prefix_length)) = prefix {
+            let name: String = rest[prefix_length..]
+                .chars()
+                .take_while(|c| c.is_alphanumeric())
+                .collect();
+            let name_length = name.chars().count();
+            if name_length > 0 && name_length <= MAX_REFERENCE_LENGTH {
+                let end = index + prefix_length + name.len();
+                references.push(Reference {
+                    kind,
+                    start: index,
+                    end,
+                    name: name.to_lowercase(),
+                });
+                previous = name.chars().last();
+                index = end;
+                continue;
+            }
+        }
+        previous = Some(c);
+        index += c.len_utf8();
+    }
+    references
+}
+
+//the plain text of a body, without code or link text, which is the only place references count
+fn plain_text<'a>(parser: Parser<'a>) -> impl Iterator<Item = (bool, Event<'a>)> {
+    let mut depth = 0;
+    parser.map(move |event| {
+        match &event {
+            Event::Start(Tag::CodeBlock(_))
+            | Event::Start(Tag::Link(..))
+            | Event::Start(Tag::Image(..)) => depth += 1,
+            Event::End(Tag::CodeBlock(_))
+            | Event::End(Tag::Link(..))
+            | Event::End(Tag::Image(..)) => depth -= 1,
+            _ => (),
+        }
+        let is_plain_text = depth == 0 && matches!(event, Event::Text(_));
+        (is_plain_text, event)
+    })
+}
+
+//the users and guilds a body refers to that actually exist. anything else stays plain text
+#[derive(Debug, Clone, Default)]
+pub struct References {
+    users: HashMap<String, i32>,
+    guild_tags: HashMap<String, String>, //as written to the guild's current tag, so old tags link to the guild
+}
+
+impl References {
+    pub async fn resolve<'e, E>(body: &str, executor: E) -> Result<References>
+    where
+        E: Executor<'e, Database = Postgres>,
+    {
+        let mut usernames: Vec<String> = vec![];
+        let mut guild_tags: Vec<String> = vec![];
+        for (is_plain_text, event) in plain_text(Parser::new_ext(body, Options::empty())) {
+            if let (true, Event::Text(text)) = (is_plain_text, event) {
+                for reference in find_references(&text) {
+                    let names = match reference.kind {
+                        ReferenceKind::User => &mut usernames,
+                        ReferenceKind::Guild => &mut guild_tags,
+                    };
+                    if !names.contains(&reference.name) {
+                        names.push(reference.name);
+                    }
+                }
+            }
+        }
+        let mut references = References::default();
+        if usernames.is_empty() && guild_tags.is_empty() {
+            return Ok(references);
+        }
+        let found = sqlx::query!(
+            r#"
+            SELECT TRUE AS "is_user!", username AS "name!", user_id AS "user_id?", NULL::VARCHAR AS guild_tag FROM users
+            WHERE username = ANY($1)
+            UNION ALL
+            SELECT FALSE, tag, NULL, guilds.guild_tag FROM unnest($2::VARCHAR[]) tag
+            INNER JOIN guilds ON guilds.guild_id = resolve_guild_tag(tag)
+            "#,
+            &usernames,
+            &guild_tags
+        )
+        .fetch_all(executor)
+        .await?;
+        for row in found {
+            match (row.is_user, row.user_id, row.guild_tag) {
+                (true, Some(user_id), _) => {
+                    references.users.insert(row.name, user_id);
+                }
+                (false, _, Some(guild_tag)) => {
+                    references.guild_tags.insert(row.name, guild_tag);
+                }
+                _ => (),
+            }
+        }
+        Ok(references)
+    }
+    //who to send mention notifications to
+    pub fn mentioned_user_ids(&self) -> Vec<i32> {
+        self.users.values().copied().collect()
+    }
+    //the text as html, with the references in it linked
+    fn link(&self, text: &str) -> String {
+        let mut linked = String::new();
+        let mut index = 0;
+        for reference in find_references(text) {
+            let href = match reference.kind {
+                ReferenceKind::User if self.users.contains_key(&reference.name) => {
+                    format!("/u/{}", reference.name)
+                }
+                ReferenceKind::Guild => match self.guild_tags.get(&reference.name) {
+                    Some(guild_tag) => format!("/g/{}", guild_tag),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let _ = escape_html(&mut linked, &text[index..reference.start]);
+            linked.push_str("<a href=\"");
+            let _ = escape_html(&mut linked, &href);
+            linked.push_str("\">");
+            let _ = escape_html(&mut linked, &text[reference.start..reference.end]);
+            linked.push_str("</a>");
+            index = reference.end;
+        }
+        let _ = escape_html(&mut linked, &text[index..]);
+        linked
+    }
+}
+
+//commonmark to html that's safe to put straight into a page, with @username and g/guild_tag references linked
+pub fn render(body: &str, references: &References) -> String {
+    let events = plain_text(Parser::new_ext(body, Options::empty())).map(
+        |(is_plain_text, event)| match event {
+            Event::Text(text) if is_plain_text => Event::Html(CowStr::from(references.link(&text))),
+            event => event,
+        },
+    );
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events);
+    SANITIZER.clean(&unsafe_html).to_string()
+}
+
+//renders posts and comments written before body_html existed, a batch at a time so it doesn't hold up startup
+pub async fn render_missing_bodies(pool: PgPool) {
+    loop {
+        match render_missing_batch(&pool).await {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(err) => {
+                error!("Error rendering post and comment bodies: {}", err);
+                break;
+            }
+        }
+    }
+}
+
+async fn render_missing_batch(pool: &PgPool) -> Result<u64> {
+    let mut rendered = 0;
+    let posts = sqlx::query!(
+        r#"
+        SELECT post_id, body AS "body!" FROM posts
+        WHERE body_html IS NULL AND body IS NOT NULL
+        LIMIT $1
+        "#,
+        BACKFILL_BATCH_SIZE
+    )
+    .fetch_all(pool)
+    .await?;
+    for post in posts {
+        let references = References::resolve(&post.body, pool).await?;
+        sqlx::query!(
+            r#"
+            UPDATE posts
+            SET body_html = $2
+            WHERE post_id = $1
+            "#,
+            post.post_id,
+            render(&post.body, &references)
+        )
+        .execute(pool)
+        .await?;
+        rendered += 1;
+    }
+    let comments = sqlx::query!(
+        r#"
+        SELECT comment_id, body FROM comments
+        WHERE body_html IS NULL
+        LIMIT $1
+        "#,
+        BACKFILL_BATCH_SIZE
+    )
+    .fetch_all(pool)
+    .await?;
+    for comment in comments {
+        let references = References::resolve(&comment.body, pool).await?;
+        sqlx::query!(
+            r#"
+            UPDATE comments
+            SET body_html = $2
+            WHERE comment_id = $1
+            "#,
+            comment.comment_id,
+            render(&comment.body, &references)
+        )
+        .execute(pool)
+        .await?;
+        rendered += 1;
+    }
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_plain(body: &str) -> String {
+        render(body, &References::default())
+    }
+
+    #[test]
+    fn drops_javascript_links() {
+        let html = render_plain("[click](javascript:alert(1))");
+        assert!(!html.contains("javascript:"), "{}", html);
+        let html = render_plain("<a href=\"javascript:alert(1)\">click</a>");
+        assert!(!html.contains("javascript:"), "{}", html);
+    }
+
+    #[test]
+    fn strips_script_tags() {
+        let html = render_plain("hi <script>alert(1)</script>");
+        assert!(!html.contains("<script"), "{}", html);
+        let html = render_plain("<script>\nalert(1)\n</script>");
+        assert!(!html.contains("<script"), "{}", html);
+    }
+
+    #[test]
+    fn strips_event_handlers() {
+        let html = render_plain("<img src=\"x\" onerror=\"alert(1)\">");
+        assert!(!html.contains("onerror"), "{}", html);
+    }
+
+    #[test]
+    fn keeps_ordinary_markdown() {
+        let html = render_plain("**bold** [link](https://example.com)");
+        assert!(html.contains("<strong>bold</strong>"), "{}", html);
+        assert!(html.contains("href=\"https://example.com\""), "{}", html);
+    }
+}
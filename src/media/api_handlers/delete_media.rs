@@ -0,0 +1,34 @@
+use crate::media::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::media_store::MediaStore;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//the uploader or a site admin. posts, avatars and banners using it lose their image
+#[post("/{media_id}/delete")]
+pub async fn handler(
+    media_id: web::Path<i32>,
+    db_pool: web::Data<PgPool>,
+    media_store: web::Data<dyn MediaStore>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let media = Media::find_by_media_id(&media_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Media does not exist."))?;
+    if media.user_id != Some(user.user_id) {
+        session_validation::policy_admin(&session, db_pool.get_ref()).await?;
+    }
+    let mut tx = db_pool.begin().await?;
+    Media::delete(&media.media_id, &mut tx).await?;
+    tx.commit().await?;
+    //the row is gone either way, a file left behind is only wasted space
+    for key in [&media.storage_key, &media.thumbnail_key].iter() {
+        if let Err(err) = media_store.delete(key).await {
+            error!("Error deleting media file {}: {}", key, err);
+        }
+    }
+    Ok(HttpResponse::Ok().body("Media deleted successfully."))
+}
@@ -0,0 +1,17 @@
+use crate::media::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::media_store::MediaStore;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+#[get("/{media_id}")]
+pub async fn handler(
+    media_id: web::Path<i32>,
+    db_pool: web::Data<PgPool>,
+    media_store: web::Data<dyn MediaStore>,
+) -> Result<HttpResponse, ApiError> {
+    let media = Media::find_by_media_id(&media_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Media does not exist."))?;
+    Ok(HttpResponse::Ok().json(media.view(&**media_store)))
+}
@@ -0,0 +1,25 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::media_store::MediaStore;
+use actix_web::{get, web, HttpResponse};
+
+//serves files for the local media store. keys never get reused, so the files can be cached forever
+#[get("/files/{key}")]
+pub async fn handler(
+    key: web::Path<String>,
+    media_store: web::Data<dyn MediaStore>,
+) -> Result<HttpResponse, ApiError> {
+    let bytes = media_store
+        .get(&key)
+        .await?
+        .ok_or_else(|| ApiError::not_found("File does not exist."))?;
+    let content_type = if key.ends_with(".jpg") {
+        "image/jpeg"
+    } else {
+        "image/png"
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .header("Cache-Control", "public, max-age=31536000, immutable")
+        .header("X-Content-Type-Options", "nosniff")
+        .body(bytes))
+}
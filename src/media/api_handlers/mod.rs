@@ -0,0 +1,4 @@
+pub mod delete_media;
+pub mod get_media;
+pub mod get_media_file;
+pub mod upload_media;
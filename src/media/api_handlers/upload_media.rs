@@ -0,0 +1,74 @@
+use crate::media::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::media_store::MediaStore;
+use crate::utils::session_validation;
+use actix_multipart::Multipart;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use futures::TryStreamExt;
+use sqlx::PgPool;
+
+//multipart/form-data with the image in a field called file. returns the media, whose media_id can then be used for a
+//post image, an avatar or a guild banner
+pub async fn handler(
+    mut payload: Multipart,
+    db_pool: web::Data<PgPool>,
+    media_store: web::Data<dyn MediaStore>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let max_bytes = max_upload_bytes();
+    let mut upload: Option<Vec<u8>> = None;
+    while let Some(mut field) = payload
+        .try_next()
+        .await
+        .map_err(|err| ApiError::validation(format!("Invalid upload: {}", err)))?
+    {
+        let is_file = field
+            .content_disposition()
+            .is_some_and(|disposition| disposition.get_name() == Some("file"));
+        let mut bytes = vec![];
+        while let Some(chunk) = field
+            .try_next()
+            .await
+            .map_err(|err| ApiError::validation(format!("Invalid upload: {}", err)))?
+        {
+            //other fields are read and thrown away
+            if !is_file {
+                continue;
+            }
+            if bytes.len() + chunk.len() > max_bytes {
+                return Err(ApiError::invalid_field(
+                    "file",
+                    format!("Uploads must be no larger than {} bytes.", max_bytes),
+                ));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        if is_file {
+            upload = Some(bytes);
+        }
+    }
+    let upload =
+        upload.ok_or_else(|| ApiError::invalid_field("file", "Choose an image to upload."))?;
+    //decoding and resizing is slow, keep it off the server's threads
+    let image = web::block(move || process_image(&upload))
+        .await
+        .map_err(|err| match err {
+            actix_web::error::BlockingError::Error(err) => err,
+            actix_web::error::BlockingError::Canceled => {
+                ApiError::internal("image processing was cancelled")
+            }
+        })?;
+    let (storage_key, thumbnail_key) = Media::new_keys(image.extension);
+    media_store
+        .put(&storage_key, image.content_type, image.bytes.clone())
+        .await?;
+    media_store
+        .put(&thumbnail_key, image.content_type, image.thumbnail.clone())
+        .await?;
+    let mut tx = db_pool.begin().await?;
+    let media = Media::create(&user.user_id, &storage_key, &thumbnail_key, &image, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(media.view(&**media_store)))
+}
@@ -0,0 +1,306 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::media_store::MediaStore;
+use anyhow::Result;
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::io::Cursor;
+use uuid::Uuid;
+
+const DEFAULT_MAX_UPLOAD_BYTES: usize = 5 * 1024 * 1024;
+//checked before decoding, so a small file can't unpack into gigabytes of pixels
+const MAX_DIMENSION: u32 = 8000;
+const MAX_PIXELS: u64 = 40_000_000;
+const THUMBNAIL_SIZE: u32 = 320;
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Media {
+    pub media_id: i32,
+    pub user_id: Option<i32>,
+    pub storage_key: String,
+    pub thumbnail_key: String,
+    pub content_type: String,
+    pub byte_size: i32,
+    pub width: i32,
+    pub height: i32,
+    pub created_at: String, //convert time to string
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MediaView {
+    pub media_id: i32,
+    pub url: String,
+    pub thumbnail_url: String,
+    pub content_type: String,
+    pub byte_size: i32,
+    pub width: i32,
+    pub height: i32,
+    pub created_at: String, //convert time to string
+}
+
+//an upload that's been checked and re-encoded, ready for the media store
+pub struct ProcessedImage {
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub bytes: Vec<u8>,
+    pub thumbnail: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+//MEDIA_MAX_UPLOAD_BYTES, 5 MiB by default
+pub fn max_upload_bytes() -> usize {
+    dotenv::var("MEDIA_MAX_UPLOAD_BYTES")
+        .ok()
+        .and_then(|bytes| bytes.parse::<usize>().ok())
+        .filter(|bytes| *bytes > 0)
+        .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES)
+}
+
+//the format comes from the file's own bytes, whatever the client claims it is. everything is decoded and encoded
+//again, which drops exif and any other metadata (after turning jpegs the way their exif orientation says).
+//jpegs stay jpegs, everything else becomes png, so gifs keep only their first frame
+pub fn process_image(bytes: &[u8]) -> Result<ProcessedImage, ApiError> {
+    let unsupported =
+        || ApiError::invalid_field("file", "Uploads must be JPEG, PNG, GIF or WebP images.");
+    let format = image::guess_format(bytes).map_err(|_| unsupported())?;
+    if !matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Gif | ImageFormat::WebP
+    ) {
+        return Err(unsupported());
+    }
+    let (width, height) = image::io::Reader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(|_| unsupported())?;
+    if width > MAX_DIMENSION || height > MAX_DIMENSION || width as u64 * height as u64 > MAX_PIXELS
+    {
+        return Err(ApiError::invalid_field(
+            "file",
+            format!(
+                "Images can be at most {} pixels on a side and {} megapixels.",
+                MAX_DIMENSION,
+                MAX_PIXELS / 1_000_000
+            ),
+        ));
+    }
+    let mut image =
+        image::load_from_memory_with_format(bytes, format).map_err(|_| unsupported())?;
+    if format == ImageFormat::Jpeg {
+        image = apply_exif_orientation(image, bytes);
+    }
+    let thumbnail = if image.width() > THUMBNAIL_SIZE || image.height() > THUMBNAIL_SIZE {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    } else {
+        image.clone()
+    };
+    let (content_type, extension, output_format) = match format {
+        ImageFormat::Jpeg => ("image/jpeg", "jpg", ImageOutputFormat::Jpeg(JPEG_QUALITY)),
+        _ => ("image/png", "png", ImageOutputFormat::Png),
+    };
+    Ok(ProcessedImage {
+        content_type,
+        extension,
+        bytes: encode(&image, &output_format)?,
+        thumbnail: encode(&thumbnail, &output_format)?,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+fn encode(image: &DynamicImage, output_format: &ImageOutputFormat) -> Result<Vec<u8>, ApiError> {
+    //jpeg has no alpha channel
+    let image = match output_format {
+        ImageOutputFormat::Jpeg(_) => DynamicImage::ImageRgb8(image.to_rgb8()),
+        _ => image.clone(),
+    };
+    let mut bytes = vec![];
+    image
+        .write_to(&mut bytes, output_format.clone())
+        .map_err(anyhow::Error::from)?;
+    Ok(bytes)
+}
+
+//phones save photos sideways and set an exif flag to turn them upright, which would be lost with the rest of the exif
+fn apply_exif_orientation(image: DynamicImage, bytes: &[u8]) -> DynamicImage {
+    let orientation = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        });
+    match orientation {
+        Some(2) => image.fliph(),
+        Some(3) => image.rotate180(),
+        Some(4) => image.flipv(),
+        Some(5) => image.rotate90().fliph(),
+        Some(6) => image.rotate90(),
+        Some(7) => image.rotate270().fliph(),
+        Some(8) => image.rotate270(),
+        _ => image,
+    }
+}
+
+//where a post's image, a user's avatar or a guild's avatar or banner comes from. a media id wins over a url, and has
+//to be one of the acting user's own uploads. the media's url is copied next to the id so the views stay the same
+pub struct ImageReference {
+    pub url: Option<String>,
+    pub media_id: Option<i32>,
+}
+
+impl ImageReference {
+    pub async fn resolve(
+        media_id: &Option<i32>,
+        url: Option<String>,
+        user_id: &i32,
+        media_store: &dyn MediaStore,
+        pool: &PgPool,
+    ) -> Result<ImageReference, ApiError> {
+        let media_id = match media_id {
+            Some(media_id) => media_id,
+            None => {
+                return Ok(ImageReference {
+                    url,
+                    media_id: None,
+                })
+            }
+        };
+        match Media::find_by_media_id(media_id, pool).await? {
+            Some(media) if media.user_id == Some(*user_id) => Ok(ImageReference {
+                url: Some(media_store.url(&media.storage_key)),
+                media_id: Some(media.media_id),
+            }),
+            _ => Err(ApiError::invalid_field(
+                "media_id",
+                "You can only use images you uploaded.",
+            )),
+        }
+    }
+}
+
+impl Media {
+    pub fn view(&self, media_store: &dyn MediaStore) -> MediaView {
+        MediaView {
+            media_id: self.media_id,
+            url: media_store.url(&self.storage_key),
+            thumbnail_url: media_store.url(&self.thumbnail_key),
+            content_type: self.content_type.clone(),
+            byte_size: self.byte_size,
+            width: self.width,
+            height: self.height,
+            created_at: self.created_at.clone(),
+        }
+    }
+    //random keys, so files can't be found by counting
+    pub fn new_keys(extension: &str) -> (String, String) {
+        let name = Uuid::new_v4().to_simple().to_string();
+        (
+            format!("{}.{}", name, extension),
+            format!("{}_thumb.{}", name, extension),
+        )
+    }
+    pub async fn create(
+        user_id: &i32,
+        storage_key: &String,
+        thumbnail_key: &String,
+        image: &ProcessedImage,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Media> {
+        let media = sqlx::query!(
+            r#"
+            INSERT INTO media (user_id, storage_key, thumbnail_key, content_type, byte_size, width, height)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+            user_id,
+            storage_key,
+            thumbnail_key,
+            image.content_type,
+            image.bytes.len() as i32,
+            image.width as i32,
+            image.height as i32
+        )
+        .fetch_one(tx)
+        .await?;
+        Ok(Media {
+            media_id: media.media_id,
+            user_id: media.user_id,
+            storage_key: media.storage_key,
+            thumbnail_key: media.thumbnail_key,
+            content_type: media.content_type,
+            byte_size: media.byte_size,
+            width: media.width,
+            height: media.height,
+            created_at: media.created_at.to_string(),
+        })
+    }
+    pub async fn find_by_media_id(media_id: &i32, pool: &PgPool) -> Result<Option<Media>> {
+        let media = sqlx::query!(
+            r#"
+            SELECT * FROM media
+            WHERE media_id = $1
+            "#,
+            media_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(media.map(|media| Media {
+            media_id: media.media_id,
+            user_id: media.user_id,
+            storage_key: media.storage_key,
+            thumbnail_key: media.thumbnail_key,
+            content_type: media.content_type,
+            byte_size: media.byte_size,
+            width: media.width,
+            height: media.height,
+            created_at: media.created_at.to_string(),
+        }))
+    }
+    //whatever showed the media loses its url too, the ids are cleared by the foreign keys.
+    //the files are left for the caller to delete from the media store once this commits
+    pub async fn delete(media_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE posts
+            SET image_url = NULL
+            WHERE image_media_id = $1
+            "#,
+            media_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET avatar_url = NULL
+            WHERE avatar_media_id = $1
+            "#,
+            media_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE guilds
+            SET avatar_url = CASE WHEN avatar_media_id = $1 THEN NULL ELSE avatar_url END,
+            banner_url = CASE WHEN banner_media_id = $1 THEN NULL ELSE banner_url END
+            WHERE avatar_media_id = $1 OR banner_media_id = $1
+            "#,
+            media_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM media
+            WHERE media_id = $1
+            "#,
+            media_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use crate::guild::Guild;
+use crate::moderation_action::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{PageForm, PageRequest};
+use actix_web::{get, web, HttpResponse};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Deserialize)]
+pub struct GetGuildModlog {
+    guild_tag: String,
+    page_number: i64,
+}
+
+//public, anyone can see what a guild's moderators have done
+#[get("/guild/{guild_tag}/{page_number}")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    request_form: web::Path<GetGuildModlog>,
+    filter_form: web::Query<ModerationLogFilterForm>,
+    page_form: web::Query<PageForm>,
+) -> Result<HttpResponse, ApiError> {
+    filter_form.validate()?;
+    let page = PageRequest::new(&page_form, &request_form.page_number, &50)
+        .map_err(|_| ApiError::validation("Invalid page number or cursor."))?;
+    let guild = Guild::find_by_guild_tag(&request_form.guild_tag.to_lowercase(), db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    let actions = ModerationAction::find_all(
+        &Some(guild.guild_id),
+        &filter_form,
+        &page,
+        db_pool.get_ref(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(actions))
+}
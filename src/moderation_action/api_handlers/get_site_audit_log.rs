@@ -0,0 +1,24 @@
+use crate::moderation_action::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{PageForm, PageRequest};
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+//every guild and site action, site admins only
+#[get("/site/{page_number}")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    page_number: web::Path<i64>,
+    filter_form: web::Query<ModerationLogFilterForm>,
+    page_form: web::Query<PageForm>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    session_validation::policy_admin(&session, db_pool.get_ref()).await?;
+    filter_form.validate()?;
+    let page = PageRequest::new(&page_form, &page_number, &50)
+        .map_err(|_| ApiError::validation("Invalid page number or cursor."))?;
+    let actions = ModerationAction::find_all(&None, &filter_form, &page, db_pool.get_ref()).await?;
+    Ok(HttpResponse::Ok().json(actions))
+}
@@ -0,0 +1,2 @@
+pub mod get_guild_modlog;
+pub mod get_site_audit_log;
@@ -0,0 +1,227 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{Cursor, CursorTimestamp, Page, PageRequest};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::types::time::Date;
+use sqlx::{PgPool, Postgres, Transaction};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationActionType {
+    //guild moderators and admins
+    BanUser,
+    UnbanUser,
+    RemovePost,
+    RemoveComment,
+    AppointModerator,
+    RemoveModerator,
+    LockPost,
+    UnlockPost,
+    //site admins
+    SiteBanUser,
+    SiteUnbanUser,
+    SiteDeleteUser,
+    SiteVerifyUser,
+    SiteUnverifyUser,
+    SiteMakeUserAdmin,
+    SiteRequireTwoFactor,
+    SiteUnrequireTwoFactor,
+}
+
+impl ModerationActionType {
+    //what gets stored in moderation_actions.action, matches the serde name
+    fn as_str(&self) -> &'static str {
+        match self {
+            ModerationActionType::BanUser => "ban_user",
+            ModerationActionType::UnbanUser => "unban_user",
+            ModerationActionType::RemovePost => "remove_post",
+            ModerationActionType::RemoveComment => "remove_comment",
+            ModerationActionType::AppointModerator => "appoint_moderator",
+            ModerationActionType::RemoveModerator => "remove_moderator",
+            ModerationActionType::LockPost => "lock_post",
+            ModerationActionType::UnlockPost => "unlock_post",
+            ModerationActionType::SiteBanUser => "site_ban_user",
+            ModerationActionType::SiteUnbanUser => "site_unban_user",
+            ModerationActionType::SiteDeleteUser => "site_delete_user",
+            ModerationActionType::SiteVerifyUser => "site_verify_user",
+            ModerationActionType::SiteUnverifyUser => "site_unverify_user",
+            ModerationActionType::SiteMakeUserAdmin => "site_make_user_admin",
+            ModerationActionType::SiteRequireTwoFactor => "site_require_two_factor",
+            ModerationActionType::SiteUnrequireTwoFactor => "site_unrequire_two_factor",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModerationAction {
+    pub action_id: Option<i32>,
+    pub action: Option<String>,
+    pub guild_id: Option<i32>,
+    pub guild_tag: Option<String>,
+    pub actor_username: Option<String>,
+    pub target_username: Option<String>,
+    pub target_post_id: Option<i32>,
+    pub target_comment_id: Option<i32>,
+    pub reason: Option<String>,
+    pub created_at: Option<String>, //convert time to string
+}
+
+//what a handler records. guild_id is None for site actions
+#[derive(Debug, Clone)]
+pub struct ModerationActionForm {
+    pub actor_id: i32,
+    pub action: ModerationActionType,
+    pub guild_id: Option<i32>,
+    pub target_user_id: Option<i32>,
+    pub target_post_id: Option<i32>,
+    pub target_comment_id: Option<i32>,
+    pub reason: Option<String>,
+}
+
+impl ModerationActionForm {
+    pub fn new(actor_id: i32, action: ModerationActionType) -> ModerationActionForm {
+        ModerationActionForm {
+            actor_id,
+            action,
+            guild_id: None,
+            target_user_id: None,
+            target_post_id: None,
+            target_comment_id: None,
+            reason: None,
+        }
+    }
+}
+
+//optional json body for mod actions that otherwise only take path params, eg {"reason": "spam"}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModerationReasonForm {
+    pub reason: Option<String>,
+}
+
+//blank reasons are dropped, long ones rejected
+pub fn format_reason(reason: &Option<String>) -> Result<Option<String>, ApiError> {
+    match reason.as_deref().map(str::trim) {
+        Some(reason) if reason.len() > 500 => Err(ApiError::invalid_field(
+            "reason",
+            "Reasons must be no longer than 500 characters.",
+        )),
+        Some(reason) if !reason.is_empty() => Ok(Some(reason.to_string())),
+        _ => Ok(None),
+    }
+}
+
+//query string for the log routes, eg ?actor=alice&action=remove_post&from=2021-11-01&to=2021-11-30
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModerationLogFilterForm {
+    pub actor: Option<String>,
+    pub action: Option<ModerationActionType>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl ModerationLogFilterForm {
+    //dates are YYYY-MM-DD, both ends inclusive
+    pub fn validate(&self) -> Result<(), ApiError> {
+        for (field, date) in [("from", &self.from), ("to", &self.to)].iter() {
+            if let Some(date) = date {
+                if !is_date(date) {
+                    return Err(ApiError::invalid_field(
+                        field,
+                        "Dates must be valid and formatted as YYYY-MM-DD.",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+//parsing catches impossible dates like 2021-02-31, which would fail the cast in postgres. the length check keeps
+//out signed and five digit years
+fn is_date(date: &str) -> bool {
+    date.len() == 10 && matches!(Date::parse(date, "%F"), Ok(date) if date.year() >= 1)
+}
+
+impl ModerationAction {
+    pub async fn create(
+        form: &ModerationActionForm,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO moderation_actions (actor_id, action, guild_id, guild_tag, target_user_id, target_username, target_post_id, target_comment_id, reason)
+            VALUES ($1, $2, $3, (SELECT guild_tag FROM guilds WHERE guild_id = $3), $4, (SELECT username FROM users WHERE user_id = $4), $5, $6, $7)
+            "#,
+            form.actor_id,
+            form.action.as_str(),
+            form.guild_id,
+            form.target_user_id,
+            form.target_post_id,
+            form.target_comment_id,
+            form.reason
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+    //newest first. guild_id None lists every guild and site action
+    pub async fn find_all(
+        guild_id: &Option<i32>,
+        filter: &ModerationLogFilterForm,
+        page: &PageRequest,
+        pool: &PgPool,
+    ) -> Result<Page<ModerationAction>> {
+        let actions = sqlx::query!(
+            r#"
+            SELECT * FROM detailed_moderation_action_view
+            WHERE ($1::INTEGER IS NULL OR guild_id = $1)
+            AND ($2::VARCHAR IS NULL OR actor_username = lower($2))
+            AND ($3::VARCHAR IS NULL OR action = $3)
+            AND ($4::VARCHAR IS NULL OR created_at >= $4::VARCHAR::DATE)
+            AND ($5::VARCHAR IS NULL OR created_at < $5::VARCHAR::DATE + 1)
+            AND ($8::TIMESTAMP IS NULL OR (created_at, action_id) < ($8, $9::INTEGER))
+            ORDER BY created_at DESC, action_id DESC
+            LIMIT $6
+            OFFSET $7
+            "#,
+            *guild_id,
+            filter.actor.as_deref(),
+            filter.action.map(|action| action.as_str()),
+            filter.from.as_deref(),
+            filter.to.as_deref(),
+            page.results_per_page,
+            page.offset(),
+            page.key::<CursorTimestamp>("new")?.map(|key| key.0),
+            page.id::<i32>()?
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(Page {
+            next_cursor: page.next_cursor(&actions, |action| {
+                Cursor::new(
+                    "new",
+                    action
+                        .created_at
+                        .map(|c| CursorTimestamp(c).to_string())
+                        .unwrap_or_default(),
+                    action.action_id.unwrap_or(0),
+                )
+            }),
+            items: actions
+                .into_iter()
+                .map(|action| ModerationAction {
+                    action_id: action.action_id,
+                    action: action.action,
+                    guild_id: action.guild_id,
+                    guild_tag: action.guild_tag,
+                    actor_username: action.actor_username,
+                    target_username: action.target_username,
+                    target_post_id: action.target_post_id,
+                    target_comment_id: action.target_comment_id,
+                    reason: action.reason,
+                    created_at: action.created_at.map(|c| c.to_string()),
+                })
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,37 @@
+use crate::notification::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/delete/{notification_source}/{notification_id}")]
+pub async fn handler(
+    notification_form: web::Path<NotificationPathForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    //make sure notification exists and belongs to user
+    let owner_id = notification_form
+        .find_owner_id(db_pool.get_ref())
+        .await?
+        .ok_or_else(|| {
+            ApiError::not_found("The notification you are trying to delete does not exist.")
+        })?;
+    if owner_id != user.user_id {
+        return Err(ApiError::forbidden(
+            "That notification belongs to another user.",
+        ));
+    }
+    //delete notification
+    let mut tx = db_pool.begin().await?;
+    match notification_form.notification_source.as_str() {
+        "comment" => {
+            CommentNotification::delete(&notification_form.notification_id, &mut tx).await?
+        }
+        _ => PostNotification::delete(&notification_form.notification_id, &mut tx).await?,
+    }
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Notification deleted."))
+}
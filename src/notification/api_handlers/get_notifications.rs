@@ -0,0 +1,26 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{PageForm, PageRequest};
+use crate::utils::session_validation;
+use crate::view::DetailedNotificationView;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+#[get("/inbox/{page_number}")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    page_number: web::Path<i64>,
+    page_form: web::Query<PageForm>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let page = PageRequest::new(&page_form, &page_number, &20)
+        .map_err(|_| ApiError::validation("Invalid page number or cursor."))?;
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let notifications = DetailedNotificationView::get_notifications_by_user_id(
+        &user.user_id,
+        db_pool.get_ref(),
+        &page,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(notifications))
+}
@@ -0,0 +1,17 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use crate::view::DetailedNotificationView;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+#[get("/unread")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let count =
+        DetailedNotificationView::count_unread_by_user_id(&user.user_id, db_pool.get_ref()).await?;
+    Ok(HttpResponse::Ok().json(count))
+}
@@ -0,0 +1,19 @@
+use crate::notification::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/readall")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let mut tx = db_pool.begin().await?;
+    CommentNotification::mark_all_read_by_user_id(&user.user_id, &mut tx).await?;
+    PostNotification::mark_all_read_by_user_id(&user.user_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("All notifications marked as read."))
+}
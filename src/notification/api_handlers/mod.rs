@@ -0,0 +1,5 @@
+pub mod delete_notification;
+pub mod get_notifications;
+pub mod get_unread_count;
+pub mod mark_all_read;
+pub mod mark_read;
@@ -1,10 +1,28 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{Cursor, CursorTimestamp, Page, PageRequest};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Postgres, Transaction};
+use std::time::Duration;
+
+//members notified per transaction when a post goes out to a guild
+const GUILD_POST_BATCH_SIZE: i64 = 1000;
+const DEFAULT_TOP_SCORE: i32 = 10;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum CommentNotificationType {
     ReplyNotification,
+    MentionNotification,
+}
+
+impl CommentNotificationType {
+    //what gets stored in comment_notifications.notification_type
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentNotificationType::ReplyNotification => "reply",
+            CommentNotificationType::MentionNotification => "mention",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -24,7 +42,60 @@ pub struct CommentNotificationForm {
     pub target_id: i32,
 }
 
+//identifies a single notification in the merged inbox, source is either "comment" or "post"
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NotificationPathForm {
+    pub notification_source: String,
+    pub notification_id: i32,
+}
+
+impl NotificationPathForm {
+    //who the notification belongs to, None if it doesn't exist
+    pub async fn find_owner_id(&self, pool: &PgPool) -> Result<Option<i32>, ApiError> {
+        match self.notification_source.as_str() {
+            "comment" => Ok(CommentNotification::find_by_id(&self.notification_id, pool)
+                .await?
+                .map(|noti| noti.user_id)),
+            "post" => Ok(PostNotification::find_by_id(&self.notification_id, pool)
+                .await?
+                .map(|noti| noti.user_id)),
+            _ => Err(ApiError::invalid_field(
+                "notification_source",
+                "Notification source must be either comment or post.",
+            )),
+        }
+    }
+}
+
 impl CommentNotification {
+    //mention notifications for the users a comment mentions, except its author, users who blocked the author and
+    //anyone already notified about the comment, eg by a reply notification or when an edit keeps a mention
+    pub async fn create_mentions(
+        comment_id: &i32,
+        user_ids: &[i32],
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO comment_notifications (notification_type, user_id, comment_id)
+            SELECT $3, users.user_id, comments.comment_id FROM users
+            INNER JOIN comments ON comments.comment_id = $1
+            INNER JOIN users authors ON authors.user_id = comments.user_id
+            WHERE users.user_id = ANY($2) AND users.user_id <> comments.user_id
+            AND NOT EXISTS (SELECT 1 FROM blocks WHERE blocks.user_id = users.user_id AND blocks.blocked_user_username = authors.username)
+            AND NOT EXISTS (SELECT 1 FROM comment_notifications notified WHERE notified.comment_id = $1 AND notified.user_id = users.user_id)
+            "#,
+            comment_id,
+            user_ids,
+            CommentNotificationType::MentionNotification.as_str()
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
     pub async fn find_by_id(
         notification_id: &i32,
         pool: &PgPool,
@@ -47,37 +118,83 @@ impl CommentNotification {
             created_at: noti.created_at.to_string(),
         }))
     }
+    //newest first, paged by (created_at, notification_id)
     pub async fn find_notis_by_user_id(
         user_id: &i32,
         pool: &PgPool,
-        results_per_page: &i64,
-        page_number: &i64,
-    ) -> Result<Vec<CommentNotification>> {
+        page: &PageRequest,
+    ) -> Result<Page<CommentNotification>> {
         let notis = sqlx::query!(
             r#"
             SELECT * FROM comment_notifications
             WHERE user_id = $1
-            ORDER BY created_at
+            AND ($4::TIMESTAMP IS NULL OR (created_at, notification_id) < ($4, $5::INTEGER))
+            ORDER BY created_at DESC, notification_id DESC
             LIMIT $2
             OFFSET $3
             "#,
             user_id,
-            results_per_page,
-            ((page_number - 1) * results_per_page)
+            page.results_per_page,
+            page.offset(),
+            page.key::<CursorTimestamp>("new")?.map(|key| key.0),
+            page.id::<i32>()?
         )
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|noti| CommentNotification {
-            notification_id: noti.notification_id,
-            notification_type: noti.notification_type,
-            user_id: noti.user_id,
-            comment_id: noti.comment_id,
-            is_read: noti.is_read,
-            created_at: noti.created_at.to_string(),
+        .await?;
+        Ok(Page {
+            next_cursor: page.next_cursor(&notis, |noti| {
+                Cursor::new(
+                    "new",
+                    CursorTimestamp(noti.created_at),
+                    noti.notification_id,
+                )
+            }),
+            items: notis
+                .into_iter()
+                .map(|noti| CommentNotification {
+                    notification_id: noti.notification_id,
+                    notification_type: noti.notification_type,
+                    user_id: noti.user_id,
+                    comment_id: noti.comment_id,
+                    is_read: noti.is_read,
+                    created_at: noti.created_at.to_string(),
+                })
+                .collect(),
         })
-        .collect();
-        Ok(notis)
+    }
+    pub async fn update_read_status(
+        notification_id: &i32,
+        is_read: bool,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE comment_notifications
+            SET is_read = $2
+            WHERE notification_id = $1
+            "#,
+            notification_id,
+            is_read
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+    pub async fn mark_all_read_by_user_id(
+        user_id: &i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE comment_notifications
+            SET is_read = TRUE
+            WHERE user_id = $1 AND is_read = FALSE
+            "#,
+            user_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
     }
     pub async fn delete(notification_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
         sqlx::query!(
@@ -96,6 +213,19 @@ impl CommentNotification {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum PostNotificationType {
     GuildPostNotification,
+    TopPostNotification,
+    MentionNotification,
+}
+
+impl PostNotificationType {
+    //what gets stored in post_notifications.notification_type
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostNotificationType::GuildPostNotification => "guild_post",
+            PostNotificationType::TopPostNotification => "top_post",
+            PostNotificationType::MentionNotification => "mention",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -116,6 +246,34 @@ pub struct PostNotificationForm {
 }
 
 impl PostNotification {
+    //mention notifications for the users a post mentions, except its author, users who blocked the author and
+    //anyone already mentioned before an edit
+    pub async fn create_mentions(
+        post_id: &i32,
+        user_ids: &[i32],
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO post_notifications (notification_type, user_id, post_id)
+            SELECT $3::VARCHAR, users.user_id, posts.post_id FROM users
+            INNER JOIN posts ON posts.post_id = $1
+            INNER JOIN users authors ON authors.user_id = posts.user_id
+            WHERE users.user_id = ANY($2) AND users.user_id <> posts.user_id
+            AND NOT EXISTS (SELECT 1 FROM blocks WHERE blocks.user_id = users.user_id AND blocks.blocked_user_username = authors.username)
+            AND NOT EXISTS (SELECT 1 FROM post_notifications notified WHERE notified.post_id = $1 AND notified.user_id = users.user_id AND notified.notification_type = $3)
+            "#,
+            post_id,
+            user_ids,
+            PostNotificationType::MentionNotification.as_str()
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
     pub async fn find_by_id(
         notification_id: &i32,
         pool: &PgPool,
@@ -138,37 +296,83 @@ impl PostNotification {
             created_at: noti.created_at.to_string(),
         }))
     }
+    //newest first, paged by (created_at, notification_id)
     pub async fn find_notis_by_user_id(
         user_id: &i32,
         pool: &PgPool,
-        results_per_page: &i64,
-        page_number: &i64,
-    ) -> Result<Vec<PostNotification>> {
+        page: &PageRequest,
+    ) -> Result<Page<PostNotification>> {
         let notis = sqlx::query!(
             r#"
             SELECT * FROM post_notifications
             WHERE user_id = $1
-            ORDER BY created_at
+            AND ($4::TIMESTAMP IS NULL OR (created_at, notification_id) < ($4, $5::INTEGER))
+            ORDER BY created_at DESC, notification_id DESC
             LIMIT $2
             OFFSET $3
             "#,
             user_id,
-            results_per_page,
-            ((page_number - 1) * results_per_page)
+            page.results_per_page,
+            page.offset(),
+            page.key::<CursorTimestamp>("new")?.map(|key| key.0),
+            page.id::<i32>()?
         )
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|noti| PostNotification {
-            notification_id: noti.notification_id,
-            notification_type: noti.notification_type,
-            user_id: noti.user_id,
-            post_id: noti.post_id,
-            is_read: noti.is_read,
-            created_at: noti.created_at.to_string(),
+        .await?;
+        Ok(Page {
+            next_cursor: page.next_cursor(&notis, |noti| {
+                Cursor::new(
+                    "new",
+                    CursorTimestamp(noti.created_at),
+                    noti.notification_id,
+                )
+            }),
+            items: notis
+                .into_iter()
+                .map(|noti| PostNotification {
+                    notification_id: noti.notification_id,
+                    notification_type: noti.notification_type,
+                    user_id: noti.user_id,
+                    post_id: noti.post_id,
+                    is_read: noti.is_read,
+                    created_at: noti.created_at.to_string(),
+                })
+                .collect(),
         })
-        .collect();
-        Ok(notis)
+    }
+    pub async fn update_read_status(
+        notification_id: &i32,
+        is_read: bool,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE post_notifications
+            SET is_read = $2
+            WHERE notification_id = $1
+            "#,
+            notification_id,
+            is_read
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+    pub async fn mark_all_read_by_user_id(
+        user_id: &i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE post_notifications
+            SET is_read = TRUE
+            WHERE user_id = $1 AND is_read = FALSE
+            "#,
+            user_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
     }
     pub async fn delete(notification_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
         sqlx::query!(
@@ -182,4 +386,124 @@ impl PostNotification {
         .await?;
         Ok(())
     }
+    //top post jobs for posts from the last day that reached top_score, in guilds where someone wants top posts.
+    //returns how many were queued. finished jobs are kept for two days so a post is never queued twice
+    pub async fn queue_top_posts(top_score: i32, pool: &PgPool) -> Result<u64> {
+        sqlx::query!(
+            r#"
+            DELETE FROM post_notification_jobs
+            WHERE is_done AND created_at < LOCALTIMESTAMP - INTERVAL '2 days'
+            "#
+        )
+        .execute(pool)
+        .await?;
+        let queued = sqlx::query!(
+            r#"
+            INSERT INTO post_notification_jobs (post_id, audience)
+            SELECT posts.post_id, 'top' FROM posts
+            INNER JOIN post_aggregates ON post_aggregates.post_id = posts.post_id
+            WHERE posts.created_at > LOCALTIMESTAMP - INTERVAL '1 day' AND post_aggregates.score >= $1
+            AND EXISTS (SELECT 1 FROM guild_memberships WHERE guild_memberships.guild_id = posts.guild_id AND notification_preference = 'top')
+            ON CONFLICT (post_id, audience) DO NOTHING
+            "#,
+            top_score
+        )
+        .execute(pool)
+        .await?;
+        Ok(queued.rows_affected())
+    }
+    //works through the next batch of members for the oldest unfinished job, skipping the author, banned members and
+    //members who blocked the author. returns false if there was nothing to do. jobs are locked while a batch runs,
+    //so several backend instances can share the queue
+    pub async fn send_next_guild_post_batch(pool: &PgPool) -> Result<bool> {
+        let mut tx = pool.begin().await?;
+        let job = sqlx::query!(
+            r#"
+            SELECT job_id, post_id, audience, last_membership_id FROM post_notification_jobs
+            WHERE NOT is_done
+            ORDER BY job_id
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+        let job = match job {
+            Some(job) => job,
+            None => return Ok(false),
+        };
+        let batch = sqlx::query!(
+            r#"
+            WITH batch AS (
+                SELECT guild_memberships.membership_id, guild_memberships.user_id, guild_memberships.is_banned FROM guild_memberships
+                INNER JOIN posts ON posts.guild_id = guild_memberships.guild_id
+                WHERE posts.post_id = $1 AND guild_memberships.notification_preference = $2
+                AND guild_memberships.membership_id > $3
+                ORDER BY guild_memberships.membership_id
+                LIMIT $4
+            ), notified AS (
+                INSERT INTO post_notifications (notification_type, user_id, post_id)
+                SELECT CASE WHEN $2 = 'top' THEN $5::VARCHAR ELSE $6::VARCHAR END, batch.user_id, posts.post_id FROM batch
+                INNER JOIN posts ON posts.post_id = $1
+                INNER JOIN users authors ON authors.user_id = posts.user_id
+                WHERE NOT batch.is_banned AND batch.user_id <> posts.user_id
+                AND NOT EXISTS (SELECT 1 FROM blocks WHERE blocks.user_id = batch.user_id AND blocks.blocked_user_username = authors.username)
+                RETURNING 1
+            )
+            SELECT max(membership_id) AS last_membership_id, count(*) AS "members!" FROM batch
+            "#,
+            job.post_id,
+            job.audience,
+            job.last_membership_id,
+            GUILD_POST_BATCH_SIZE,
+            PostNotificationType::TopPostNotification.as_str(),
+            PostNotificationType::GuildPostNotification.as_str()
+        )
+        .fetch_one(&mut tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE post_notification_jobs
+            SET last_membership_id = $2, is_done = $3
+            WHERE job_id = $1
+            "#,
+            job.job_id,
+            batch.last_membership_id.unwrap_or(job.last_membership_id),
+            batch.members < GUILD_POST_BATCH_SIZE
+        )
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+}
+
+//POST_NOTIFICATION_TOP_SCORE, the score a post needs to go out to members who only want top posts
+pub fn top_score() -> i32 {
+    dotenv::var("POST_NOTIFICATION_TOP_SCORE")
+        .ok()
+        .and_then(|score| score.parse::<i32>().ok())
+        .filter(|score| *score > 0)
+        .unwrap_or(DEFAULT_TOP_SCORE)
+}
+
+//runs for the life of the server, sending guild post notifications a batch at a time and checking for new top
+//posts whenever the queue is empty
+pub async fn send_guild_post_notifications(pool: PgPool) {
+    let top_score = top_score();
+    loop {
+        match PostNotification::send_next_guild_post_batch(&pool).await {
+            Ok(true) => continue,
+            Ok(false) => (),
+            Err(err) => error!("Error sending guild post notifications: {}", err),
+        }
+        match PostNotification::queue_top_posts(top_score, &pool).await {
+            Ok(0) => async_std::task::sleep(Duration::from_secs(10)).await,
+            Ok(_) => (),
+            Err(err) => {
+                error!("Error queueing top post notifications: {}", err);
+                async_std::task::sleep(Duration::from_secs(10)).await
+            }
+        }
+    }
 }
@@ -1,8 +1,11 @@
 use crate::guild::*;
+use crate::media::ImageReference;
 use crate::post::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::media_store::MediaStore;
 use crate::utils::session_validation;
 use actix_session::Session;
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
@@ -10,7 +13,9 @@ use sqlx::PgPool;
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreatePostForm {
     pub guild_tag: String,
+    #[serde(default)]
     pub image_url: String,
+    pub image_media_id: Option<i32>,
     pub link_url: String,
     pub title: String,
     pub body: String,
@@ -19,111 +24,64 @@ pub struct CreatePostForm {
 pub async fn handler(
     post_form: web::Json<CreatePostForm>,
     db_pool: web::Data<PgPool>,
+    media_store: web::Data<dyn MediaStore>,
     session: Session,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     //validate input (for now title must be less than 100 chars)
     //TODO: stricter input validation on this, make sure links are valid
     if post_form.title.len() > 100 {
-        return HttpResponse::BadRequest().body("Title must be less than 100 characters.");
+        return Err(ApiError::invalid_field(
+            "title",
+            "Title must be less than 100 characters.",
+        ));
     }
-    if post_form.title == "" {
-        return HttpResponse::BadRequest().body("Title cannot be empty");
-    }
-
-    //TODO: posts with out a title should not be accepted, investigate
-
-    let valid_session =
-        session_validation::policy_guild_member(&session, &post_form.guild_tag, db_pool.get_ref())
-            .await;
-    match valid_session {
-        Ok((None, Some(user))) => {
-            //format link url and body
-            let formatted_link: Option<String>;
-            if post_form.link_url == "" {
-                formatted_link = None;
-            } else {
-                formatted_link = Some(post_form.link_url.clone());
-            }
-            let formatted_image: Option<String>;
-            if post_form.image_url == "" {
-                formatted_image = None;
-            } else {
-                formatted_image = Some(post_form.image_url.clone());
-            }
-            let formatted_body: Option<String>;
-            if post_form.body == "" {
-                formatted_body = None;
-            } else {
-                formatted_body = Some(post_form.body.clone());
-            }
-            //format form
-            let formatted_form = PostForm {
-                guild_tag: post_form.guild_tag.clone().to_lowercase(),
-                user_id: user.user_id,
-                image_url: formatted_image,
-                link_url: formatted_link,
-                title: post_form.title.clone(),
-                body: formatted_body,
-            };
-            //make sure guild exists (this logic can probably be removed now that i added the guild member policy)
-            let existing_guild =
-                Guild::find_by_guild_tag(&formatted_form.guild_tag, db_pool.get_ref()).await;
-            match existing_guild {
-                Ok(Some(guild)) => {
-                    //make sure user is guild member, and isn't banned.
-
-                    //create post
-                    let mut tx = db_pool.begin().await.unwrap();
-                    let created_post = Post::create(&formatted_form, &mut tx).await;
-                    match created_post {
-                        Ok(()) => {
-                            let succesful_commit = tx.commit().await;
-                            match succesful_commit {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error committing transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            return HttpResponse::Ok().body("Post created successfully");
-                        }
-                        Err(err) => {
-                            let succesful_rollback = tx.rollback().await;
-                            match succesful_rollback {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error rolling back transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            error!("Error creating post: {}", err);
-                            return HttpResponse::InternalServerError()
-                                .body("Error creating post.");
-                        }
-                    }
-                }
-                Err(err) => {
-                    error!("Error checking guilds: {}", err);
-                    return HttpResponse::InternalServerError().body("Error creating post.");
-                }
-                _ => {
-                    return HttpResponse::BadRequest()
-                        .body("Error creating post: a guild with that tag does not exist.");
-                }
-            }
-            //create post
-        }
-        Ok((Some(response), None)) => {
-            return response;
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
+    if post_form.title.is_empty() {
+        return Err(ApiError::invalid_field("title", "Title cannot be empty"));
     }
+    //make sure guild exists
+    let guild = Guild::find_by_guild_tag(&post_form.guild_tag.to_lowercase(), db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("A guild with that tag does not exist."))?;
+    let user =
+        session_validation::policy_guild_member(&session, &guild.guild_id, db_pool.get_ref())
+            .await?;
+    //format link url and body
+    let formatted_link = if post_form.link_url.is_empty() {
+        None
+    } else {
+        Some(post_form.link_url.clone())
+    };
+    let formatted_image = if post_form.image_url.is_empty() {
+        None
+    } else {
+        Some(post_form.image_url.clone())
+    };
+    let formatted_body = if post_form.body.is_empty() {
+        None
+    } else {
+        Some(post_form.body.clone())
+    };
+    let image = ImageReference::resolve(
+        &post_form.image_media_id,
+        formatted_image,
+        &user.user_id,
+        &**media_store,
+        db_pool.get_ref(),
+    )
+    .await?;
+    //format form
+    let formatted_form = PostForm {
+        guild_id: guild.guild_id,
+        user_id: user.user_id,
+        image_url: image.url,
+        image_media_id: image.media_id,
+        link_url: formatted_link,
+        title: post_form.title.clone(),
+        body: formatted_body,
+    };
+    //create post
+    let mut tx = db_pool.begin().await?;
+    Post::create(&formatted_form, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Post created successfully"))
 }
@@ -1,7 +1,9 @@
+use crate::moderation_action::*;
 use crate::post::*;
+use crate::utils::api_error::ApiError;
 use crate::utils::session_validation;
 use actix_session::Session;
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
@@ -9,76 +11,37 @@ use sqlx::PgPool;
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PostLockForm {
     pub post_id: i32,
+    pub reason: Option<String>,
 }
 
 pub async fn handler(
     post_lock_form: web::Json<PostLockForm>,
     db_pool: web::Data<PgPool>,
     session: Session,
-) -> impl Responder {
-    let existing_post = Post::find_by_post_id(&post_lock_form.post_id, db_pool.get_ref()).await;
-    match existing_post {
-        Ok(Some(post)) => {
-            let valid_session = session_validation::policy_guild_moderator_or_admin(
-                &session,
-                &post.guild_tag,
-                db_pool.get_ref(),
-            )
-            .await;
-            match valid_session {
-                Ok((None, Some(user))) => {
-                    //lock post
-                    let mut tx = db_pool.begin().await.unwrap();
-                    let locked_post =
-                        Post::update_lock(&post_lock_form.post_id, false, &mut tx).await;
-                    match locked_post {
-                        Ok(()) => {
-                            let succesful_commit = tx.commit().await;
-                            match succesful_commit {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error committing transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            return HttpResponse::Ok().body("Post locked successfully");
-                        }
-                        Err(err) => {
-                            let succesful_rollback = tx.rollback().await;
-                            match succesful_rollback {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error rolling back transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            error!("Error locking post: {}", err);
-                            return HttpResponse::InternalServerError().body("Error locking post.");
-                        }
-                    }
-                }
-                Ok((Some(response), None)) => {
-                    return response;
-                }
-                Err(err) => {
-                    error!("Error verifying user session: {}", err);
-                    return HttpResponse::InternalServerError()
-                        .body("Error verifying user session.");
-                }
-                _ => {
-                    return HttpResponse::InternalServerError().body("Unknown Error.");
-                }
-            }
-        }
-        Ok(None) => {
-            return HttpResponse::BadRequest()
-                .body("The post you are trying to lock does not exist.");
-        }
-        Err(err) => {
-            error!("Error fetching post: {}", err);
-            return HttpResponse::InternalServerError().body("Error fetching post.");
-        }
-    }
+) -> Result<HttpResponse, ApiError> {
+    let post = Post::find_by_post_id(&post_lock_form.post_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("The post you are trying to unlock does not exist."))?;
+    let reason = format_reason(&post_lock_form.reason)?;
+    let actor = session_validation::policy_guild_moderator_or_admin(
+        &session,
+        &post.guild_id,
+        db_pool.get_ref(),
+    )
+    .await?;
+    let mut tx = db_pool.begin().await?;
+    Post::update_lock(&post_lock_form.post_id, false, &mut tx).await?;
+    ModerationAction::create(
+        &ModerationActionForm {
+            guild_id: Some(post.guild_id),
+            target_user_id: Some(post.user_id),
+            target_post_id: Some(post.post_id),
+            reason,
+            ..ModerationActionForm::new(actor.user_id, ModerationActionType::UnlockPost)
+        },
+        &mut tx,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Post unlocked successfully"))
 }
@@ -1,3 +1,5 @@
+use crate::markdown::{self, References};
+use crate::notification::PostNotification;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Postgres, Transaction};
@@ -5,7 +7,7 @@ use sqlx::{PgPool, Postgres, Transaction};
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Post {
     pub post_id: i32,
-    pub guild_tag: String,
+    pub guild_id: i32,
     pub user_id: i32,
     pub image_url: Option<String>,
     pub link_url: Option<String>,
@@ -18,9 +20,10 @@ pub struct Post {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PostForm {
-    pub guild_tag: String,
+    pub guild_id: i32,
     pub user_id: i32,
     pub image_url: Option<String>,
+    pub image_media_id: Option<i32>,
     pub link_url: Option<String>,
     pub title: String,
     pub body: Option<String>,
@@ -30,28 +33,42 @@ pub struct PostForm {
 pub struct PostEditForm {
     pub post_id: i32,
     pub new_image_url: Option<String>,
+    pub new_image_media_id: Option<i32>,
     pub new_link_url: Option<String>,
     pub new_title: String,
     pub new_body: Option<String>,
 }
 
 impl Post {
-    pub async fn create(post_form: &PostForm, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
-        sqlx::query!(
+    //returns the new post's id. users mentioned in the body are notified
+    pub async fn create(post_form: &PostForm, tx: &mut Transaction<'_, Postgres>) -> Result<i32> {
+        let references = match &post_form.body {
+            Some(body) => References::resolve(body, &mut *tx).await?,
+            None => References::default(),
+        };
+        let post = sqlx::query!(
             r#"
-            INSERT INTO posts (guild_tag, user_id, image_url, link_url, title, body)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO posts (guild_id, user_id, image_url, link_url, title, body, image_media_id, body_html)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING post_id
             "#,
-            post_form.guild_tag,
+            post_form.guild_id,
             post_form.user_id,
             post_form.image_url,
             post_form.link_url,
             post_form.title,
-            post_form.body
+            post_form.body,
+            post_form.image_media_id,
+            post_form
+                .body
+                .as_deref()
+                .map(|body| markdown::render(body, &references))
         )
-        .execute(tx)
+        .fetch_one(&mut *tx)
         .await?;
-        Ok(())
+        PostNotification::create_mentions(&post.post_id, &references.mentioned_user_ids(), tx)
+            .await?;
+        Ok(post.post_id)
     }
     pub async fn find_by_post_id(post_id: &i32, pool: &PgPool) -> Result<Option<Post>> {
         let post = sqlx::query!(
@@ -65,7 +82,7 @@ impl Post {
         .await?;
         Ok(post.map(|post| Post {
             post_id: post.post_id,
-            guild_tag: post.guild_tag,
+            guild_id: post.guild_id,
             user_id: post.user_id,
             image_url: post.image_url,
             link_url: post.link_url,
@@ -99,7 +116,7 @@ impl Post {
         .into_iter()
         .map(|post| Post {
             post_id: post.post_id,
-            guild_tag: post.guild_tag,
+            guild_id: post.guild_id,
             user_id: post.user_id,
             image_url: post.image_url,
             link_url: post.link_url,
@@ -114,7 +131,7 @@ impl Post {
         Ok(posts)
     }
     pub async fn find_latest_posts_by_guild_id(
-        guild_tag: &String,
+        guild_id: &i32,
         pool: &PgPool,
         results_per_page: &i64,
         page_number: &i64,
@@ -122,12 +139,12 @@ impl Post {
         let posts = sqlx::query!(
             r#"
             SELECT * FROM posts
-            WHERE guild_tag = $1
+            WHERE guild_id = $1
             ORDER BY created_at
             LIMIT $2
             OFFSET $3
             "#,
-            guild_tag,
+            guild_id,
             results_per_page,
             ((page_number - 1) * results_per_page)
         )
@@ -136,7 +153,7 @@ impl Post {
         .into_iter()
         .map(|post| Post {
             post_id: post.post_id,
-            guild_tag: post.guild_tag,
+            guild_id: post.guild_id,
             user_id: post.user_id,
             image_url: post.image_url,
             link_url: post.link_url,
@@ -150,11 +167,16 @@ impl Post {
 
         Ok(posts)
     }
+    //only users newly mentioned by the edit are notified
     pub async fn update(edits: &PostEditForm, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        let references = match &edits.new_body {
+            Some(body) => References::resolve(body, &mut *tx).await?,
+            None => References::default(),
+        };
         sqlx::query!(
             r#"
             UPDATE posts
-            SET link_url = $2, title = $3, body = $4, is_edited = $5, image_url = $6
+            SET link_url = $2, title = $3, body = $4, is_edited = $5, image_url = $6, image_media_id = $7, body_html = $8
             WHERE post_id = $1
             "#,
             edits.post_id,
@@ -162,10 +184,17 @@ impl Post {
             edits.new_title,
             edits.new_body,
             true,
-            edits.new_image_url
+            edits.new_image_url,
+            edits.new_image_media_id,
+            edits
+                .new_body
+                .as_deref()
+                .map(|body| markdown::render(body, &references))
         )
-        .execute(tx)
+        .execute(&mut *tx)
         .await?;
+        PostNotification::create_mentions(&edits.post_id, &references.mentioned_user_ids(), tx)
+            .await?;
         Ok(())
     }
     pub async fn update_lock(
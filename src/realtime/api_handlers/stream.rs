@@ -0,0 +1,23 @@
+use crate::realtime::{EventHub, SubscriptionForm};
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use futures::StreamExt;
+use sqlx::PgPool;
+
+//server sent events, open with EventSource('/events/stream?posts=1&guilds=rust')
+#[get("/stream")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    hub: web::Data<EventHub>,
+    subscription_form: web::Query<SubscriptionForm>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let events = hub.subscribe(subscription_form.topics(&user.user_id));
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .streaming(events.map(Ok::<_, actix_web::Error>)))
+}
@@ -0,0 +1,3 @@
+pub mod api_handlers;
+mod model;
+pub use model::*;
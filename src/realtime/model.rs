@@ -0,0 +1,148 @@
+use actix_web::web::Bytes;
+use anyhow::Result;
+use futures::channel::mpsc::{channel, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::postgres::PgListener;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+//the postgres channel the notify_realtime() function in the realtime migration publishes to
+const CHANNEL: &str = "realtime_events";
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+//post and guild topics one stream can follow, the user's own topic doesn't count towards it
+const MAX_TOPICS: usize = 50;
+//events waiting for a client that isn't reading them. a subscriber that falls this far behind is dropped instead
+//of buffering without limit, it can reconnect and catch up through the /view routes
+const BUFFER_SIZE: usize = 64;
+
+//one NOTIFY payload, topic is user:<user_id>, post:<post_id> or guild:<guild_tag>
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RealtimeEvent {
+    pub topic: String,
+    pub event: String,
+    pub data: Value,
+}
+
+impl RealtimeEvent {
+    //server sent events wire format, the topic goes along so one stream can carry several
+    fn to_sse(&self) -> Bytes {
+        let data = serde_json::json!({ "topic": self.topic, "data": self.data });
+        Bytes::from(format!("event: {}\ndata: {}\n\n", self.event, data))
+    }
+}
+
+//query string for /events/stream, eg ?posts=1,2&guilds=rust. the user's own notifications are always included
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubscriptionForm {
+    pub posts: Option<String>,
+    pub guilds: Option<String>,
+}
+
+impl SubscriptionForm {
+    pub fn topics(&self, user_id: &i32) -> HashSet<String> {
+        let mut topics = HashSet::new();
+        for post_id in split_list(&self.posts) {
+            if let Ok(post_id) = post_id.parse::<i32>() {
+                topics.insert(format!("post:{}", post_id));
+            }
+        }
+        for guild_tag in split_list(&self.guilds) {
+            topics.insert(format!("guild:{}", guild_tag.to_lowercase()));
+        }
+        let mut topics: HashSet<String> = topics.into_iter().take(MAX_TOPICS).collect();
+        topics.insert(format!("user:{}", user_id));
+        topics
+    }
+}
+
+fn split_list(list: &Option<String>) -> Vec<String> {
+    match list {
+        Some(list) => list
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+struct Subscriber {
+    topics: HashSet<String>,
+    sender: Sender<Bytes>,
+}
+
+//fans events from this instance's LISTEN connection out to its open streams.
+//a subscriber whose stream has gone away, or whose buffer is full, is dropped the next time a send to it fails
+pub struct EventHub {
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+impl EventHub {
+    pub fn new() -> Arc<EventHub> {
+        Arc::new(EventHub {
+            subscribers: Mutex::new(Vec::new()),
+        })
+    }
+    pub fn subscribe(&self, topics: HashSet<String>) -> Receiver<Bytes> {
+        let (mut sender, receiver) = channel(BUFFER_SIZE);
+        //tells the client the stream is open, before the first real event
+        let _ = sender.try_send(Bytes::from_static(b": connected\n\n"));
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(Subscriber { topics, sender });
+        }
+        receiver
+    }
+    pub fn publish(&self, event: &RealtimeEvent) {
+        let message = event.to_sse();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain_mut(|subscriber| {
+                if subscriber.topics.contains(&event.topic) {
+                    subscriber.sender.try_send(message.clone()).is_ok()
+                } else {
+                    !subscriber.sender.is_closed()
+                }
+            });
+        }
+    }
+    //keeps idle streams open through proxies, and clears out disconnected clients
+    fn ping(&self) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain_mut(|subscriber| {
+                subscriber
+                    .sender
+                    .try_send(Bytes::from_static(b": ping\n\n"))
+                    .is_ok()
+            });
+        }
+    }
+}
+
+//runs for the life of the server. PgListener reconnects by itself on the next recv after a dropped connection,
+//events sent while it was down are lost, clients catch up through the /view routes
+pub async fn listen(hub: Arc<EventHub>, database_url: String) -> Result<()> {
+    let mut listener = PgListener::connect(&database_url).await?;
+    listener.listen(CHANNEL).await?;
+    loop {
+        match listener.recv().await {
+            Ok(notification) => {
+                match serde_json::from_str::<RealtimeEvent>(notification.payload()) {
+                    Ok(event) => hub.publish(&event),
+                    Err(err) => error!("Error parsing realtime event: {}", err),
+                }
+            }
+            Err(err) => {
+                error!("Error receiving realtime events: {}", err);
+                async_std::task::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+}
+
+pub async fn ping(hub: Arc<EventHub>) {
+    loop {
+        async_std::task::sleep(PING_INTERVAL).await;
+        hub.ping();
+    }
+}
@@ -0,0 +1,37 @@
+use crate::guild::Guild;
+use crate::report::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{PageForm, PageRequest};
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Deserialize)]
+pub struct GetGuildQueue {
+    guild_tag: String,
+    page_number: i64,
+}
+
+#[get("/guild/{guild_tag}/queue/{page_number}")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    request_form: web::Path<GetGuildQueue>,
+    page_form: web::Query<PageForm>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let guild = Guild::find_by_guild_tag(&request_form.guild_tag.to_lowercase(), db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    session_validation::policy_guild_moderator_or_admin(
+        &session,
+        &guild.guild_id,
+        db_pool.get_ref(),
+    )
+    .await?;
+    let page = PageRequest::new(&page_form, &request_form.page_number, &20)
+        .map_err(|_| ApiError::validation("Invalid page number or cursor."))?;
+    let queue = ReportQueueItem::find_all(&Some(guild.guild_id), &page, db_pool.get_ref()).await?;
+    Ok(HttpResponse::Ok().json(queue))
+}
@@ -0,0 +1,22 @@
+use crate::report::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{PageForm, PageRequest};
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+//every guild's open reports, site admins only
+#[get("/queue/{page_number}")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    page_number: web::Path<i64>,
+    page_form: web::Query<PageForm>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    session_validation::policy_admin(&session, db_pool.get_ref()).await?;
+    let page = PageRequest::new(&page_form, &page_number, &20)
+        .map_err(|_| ApiError::validation("Invalid page number or cursor."))?;
+    let queue = ReportQueueItem::find_all(&None, &page, db_pool.get_ref()).await?;
+    Ok(HttpResponse::Ok().json(queue))
+}
@@ -0,0 +1,135 @@
+use crate::comment::*;
+use crate::guild_membership::*;
+use crate::moderation_action::*;
+use crate::post::*;
+use crate::report::*;
+use crate::user::BanForm;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+//resolves every open report on the reported post or comment: dismiss them, remove the content, or ban its author from the guild
+pub async fn handler(
+    resolve_form: web::Json<ResolveReportForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let reason = format_reason(&resolve_form.reason)?;
+    let ban = BanForm {
+        reason: reason.clone(),
+        message: resolve_form.ban_message.clone(),
+        duration_hours: resolve_form.ban_duration_hours,
+    }
+    .validate()?;
+    let report = Report::find_by_report_id(&resolve_form.report_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Report does not exist."))?;
+    //reports from before they were tied to a guild can only be handled by site admins
+    let actor = match report.guild_id {
+        Some(guild_id) => {
+            session_validation::policy_guild_moderator_or_admin(
+                &session,
+                &guild_id,
+                db_pool.get_ref(),
+            )
+            .await?
+        }
+        None => session_validation::policy_admin(&session, db_pool.get_ref()).await?,
+    };
+    if report.addressed {
+        return Err(ApiError::conflict("This report has already been resolved."));
+    }
+    //find what was reported, and who wrote it
+    let comment = match report.comment_id {
+        Some(comment_id) => Some(
+            Comment::find_by_comment_id(&comment_id, db_pool.get_ref())
+                .await?
+                .ok_or_else(|| ApiError::not_found("The reported comment no longer exists."))?,
+        ),
+        None => None,
+    };
+    let post_id = match &comment {
+        Some(comment) => comment.post_id,
+        None => report
+            .post_id
+            .ok_or_else(|| ApiError::not_found("The reported post no longer exists."))?,
+    };
+    let post = Post::find_by_post_id(&post_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("The reported post no longer exists."))?;
+    let author_id = match &comment {
+        Some(comment) => comment.user_id,
+        None => post.user_id,
+    };
+
+    let mut tx = db_pool.begin().await?;
+    //resolve first, removing the content takes its reports with it
+    Report::resolve_all_for_target(&report, resolve_form.resolution, &actor.user_id, &mut tx)
+        .await?;
+    match resolve_form.resolution {
+        ReportResolution::Dismiss => {}
+        ReportResolution::RemoveContent => {
+            let action = match &comment {
+                Some(comment) => {
+                    Comment::delete(&comment.comment_id, &mut tx).await?;
+                    ModerationActionForm {
+                        target_comment_id: Some(comment.comment_id),
+                        ..ModerationActionForm::new(
+                            actor.user_id,
+                            ModerationActionType::RemoveComment,
+                        )
+                    }
+                }
+                None => {
+                    Post::delete(&post.post_id, &mut tx).await?;
+                    ModerationActionForm::new(actor.user_id, ModerationActionType::RemovePost)
+                }
+            };
+            ModerationAction::create(
+                &ModerationActionForm {
+                    guild_id: Some(post.guild_id),
+                    target_user_id: Some(author_id),
+                    target_post_id: Some(post.post_id),
+                    reason,
+                    ..action
+                },
+                &mut tx,
+            )
+            .await?;
+        }
+        ReportResolution::BanAuthor => {
+            //same rules as ban_user
+            let membership = GuildMembership::find_by_user_and_guild_id(
+                &author_id,
+                &post.guild_id,
+                db_pool.get_ref(),
+            )
+            .await?
+            .ok_or_else(|| ApiError::validation("The author is not a member of the guild."))?;
+            if membership.is_moderator || membership.is_admin {
+                return Err(ApiError::forbidden("The author is a mod or admin."));
+            }
+            let already_banned =
+                GuildMembership::find_active_ban(&membership.membership_id, db_pool.get_ref())
+                    .await?
+                    .is_some();
+            if !already_banned {
+                GuildMembership::ban(membership, &ban, &actor.user_id, &mut tx).await?;
+                ModerationAction::create(
+                    &ModerationActionForm {
+                        guild_id: Some(post.guild_id),
+                        target_user_id: Some(author_id),
+                        reason,
+                        ..ModerationActionForm::new(actor.user_id, ModerationActionType::BanUser)
+                    },
+                    &mut tx,
+                )
+                .await?;
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Report resolved."))
+}
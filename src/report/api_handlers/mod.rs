@@ -1 +1,4 @@
 pub mod create_report;
+pub mod get_guild_queue;
+pub mod get_site_queue;
+pub mod mark_addressed;
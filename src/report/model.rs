@@ -1,3 +1,5 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{Cursor, CursorTimestamp, Page, PageRequest};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Postgres, Transaction};
@@ -7,8 +9,11 @@ pub struct Report {
     pub report_id: i32,
     pub post_id: Option<i32>,
     pub comment_id: Option<i32>,
+    pub guild_id: Option<i32>,
+    pub reporter_id: Option<i32>,
     pub reason: String,
     pub addressed: bool,
+    pub resolution: Option<String>,
     pub created_at: String, //convert time to string
 }
 
@@ -19,36 +24,72 @@ pub struct ReportForm {
     pub reason: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportResolution {
+    Dismiss,
+    RemoveContent,
+    BanAuthor,
+}
+
+impl ReportResolution {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReportResolution::Dismiss => "dismissed",
+            ReportResolution::RemoveContent => "content_removed",
+            ReportResolution::BanAuthor => "author_banned",
+        }
+    }
+}
+
+//body for /report/markaddressed. report_id can be any report on the target, usually the queue_id
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResolveReportForm {
+    pub report_id: i32,
+    pub resolution: ReportResolution,
+    pub reason: Option<String>,
+    //ban_author only, same as the ban routes' message and duration_hours
+    pub ban_message: Option<String>,
+    pub ban_duration_hours: Option<i32>,
+}
+
 //todo: auto-dele
 impl Report {
-    pub async fn find_all(
-        results_per_page: &i64,
-        page_number: &i64,
-        pool: &PgPool,
-    ) -> Result<Vec<Report>> {
+    pub async fn find_all(page: &PageRequest, pool: &PgPool) -> Result<Page<Report>> {
         let reports = sqlx::query!(
             r#"
             SELECT * FROM reports
-            ORDER BY created_at DESC
+            WHERE ($3::TIMESTAMP IS NULL OR (created_at, report_id) < ($3, $4::INTEGER))
+            ORDER BY created_at DESC, report_id DESC
             LIMIT $1
             OFFSET $2
             "#,
-            results_per_page,
-            ((page_number - 1) * results_per_page)
+            page.results_per_page,
+            page.offset(),
+            page.key::<CursorTimestamp>("new")?.map(|key| key.0),
+            page.id::<i32>()?
         )
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|report| Report {
-            report_id: report.report_id,
-            post_id: report.post_id,
-            comment_id: report.comment_id,
-            reason: report.reason,
-            addressed: report.addressed,
-            created_at: report.created_at.to_string(),
+        .await?;
+        Ok(Page {
+            next_cursor: page.next_cursor(&reports, |report| {
+                Cursor::new("new", CursorTimestamp(report.created_at), report.report_id)
+            }),
+            items: reports
+                .into_iter()
+                .map(|report| Report {
+                    report_id: report.report_id,
+                    post_id: report.post_id,
+                    comment_id: report.comment_id,
+                    guild_id: report.guild_id,
+                    reporter_id: report.reporter_id,
+                    reason: report.reason,
+                    addressed: report.addressed,
+                    resolution: report.resolution,
+                    created_at: report.created_at.to_string(),
+                })
+                .collect(),
         })
-        .collect();
-        Ok(reports)
     }
     pub async fn find_all_by_post_id(
         post_id: &i32,
@@ -74,8 +115,11 @@ impl Report {
             report_id: report.report_id,
             post_id: report.post_id,
             comment_id: report.comment_id,
+            guild_id: report.guild_id,
+            reporter_id: report.reporter_id,
             reason: report.reason,
             addressed: report.addressed,
+            resolution: report.resolution,
             created_at: report.created_at.to_string(),
         })
         .collect();
@@ -95,40 +139,96 @@ impl Report {
             report_id: report.report_id,
             post_id: report.post_id,
             comment_id: report.comment_id,
+            guild_id: report.guild_id,
+            reporter_id: report.reporter_id,
             reason: report.reason,
             addressed: report.addressed,
+            resolution: report.resolution,
             created_at: report.created_at.to_string(),
         }))
     }
+    //guild_id is the guild of the reported post, or of the reported comment's post
     pub async fn create(
         report_form: &ReportForm,
+        reporter_id: &i32,
+        guild_id: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
         if report_form.comment_id == 0 {
             sqlx::query!(
                 r#"
-                INSERT INTO reports (post_id, reason)
-                VALUES ($1, $2)
+                INSERT INTO reports (post_id, reason, reporter_id, guild_id)
+                VALUES ($1, $2, $3, $4)
                 "#,
                 report_form.post_id,
-                report_form.reason
+                report_form.reason,
+                reporter_id,
+                guild_id
             )
             .execute(tx)
-            .await?;
+            .await
+            .map_err(duplicate_report)?;
         } else if report_form.post_id == 0 {
             sqlx::query!(
                 r#"
-                INSERT INTO reports (comment_id, reason)
-                VALUES ($1, $2)
+                INSERT INTO reports (comment_id, reason, reporter_id, guild_id)
+                VALUES ($1, $2, $3, $4)
                 "#,
                 report_form.comment_id,
-                report_form.reason
+                report_form.reason,
+                reporter_id,
+                guild_id
             )
             .execute(tx)
-            .await?;
+            .await
+            .map_err(duplicate_report)?;
         }
         Ok(())
     }
+    //whether this user already reported this post or comment, resolved or not
+    pub async fn exists_for_reporter(
+        report_form: &ReportForm,
+        reporter_id: &i32,
+        pool: &PgPool,
+    ) -> Result<bool> {
+        let report = sqlx::query!(
+            r#"
+            SELECT report_id FROM reports
+            WHERE reporter_id = $1
+            AND ((post_id = $2 AND $3 = 0) OR (comment_id = $3 AND $2 = 0))
+            "#,
+            reporter_id,
+            report_form.post_id,
+            report_form.comment_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(report.is_some())
+    }
+    //resolves every open report on the same post or comment as this one
+    pub async fn resolve_all_for_target(
+        report: &Report,
+        resolution: ReportResolution,
+        resolved_by: &i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE reports
+            SET addressed = TRUE, resolution = $3, resolved_by = $4, resolved_at = LOCALTIMESTAMP
+            WHERE NOT addressed
+            AND post_id IS NOT DISTINCT FROM $1
+            AND comment_id IS NOT DISTINCT FROM $2
+            "#,
+            report.post_id,
+            report.comment_id,
+            resolution.as_str(),
+            resolved_by
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
     pub async fn update_addressed_status(
         report_id: &i32,
         addressed: bool,
@@ -148,3 +248,83 @@ impl Report {
         Ok(())
     }
 }
+
+//open reports grouped by the post or comment they're about
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReportQueueItem {
+    pub queue_id: Option<i32>,
+    pub guild_id: Option<i32>,
+    pub guild_tag: Option<String>,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub post_title: Option<String>,
+    pub comment_body: Option<String>,
+    pub author_username: Option<String>,
+    pub report_count: Option<i64>,
+    pub reasons: Option<Vec<String>>,
+    pub first_reported_at: Option<String>, //convert time to string
+    pub last_reported_at: Option<String>,  //convert time to string
+}
+
+impl ReportQueueItem {
+    //oldest first. guild_id None is the site wide queue
+    pub async fn find_all(
+        guild_id: &Option<i32>,
+        page: &PageRequest,
+        pool: &PgPool,
+    ) -> Result<Page<ReportQueueItem>> {
+        let items = sqlx::query!(
+            r#"
+            SELECT * FROM report_queue_view
+            WHERE ($1::INTEGER IS NULL OR guild_id = $1)
+            AND ($4::INTEGER IS NULL OR queue_id > $4)
+            ORDER BY queue_id ASC
+            LIMIT $2
+            OFFSET $3
+            "#,
+            *guild_id,
+            page.results_per_page,
+            page.offset(),
+            page.key::<i32>("queue")?
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(Page {
+            next_cursor: page.next_cursor(&items, |item| {
+                Cursor::new(
+                    "queue",
+                    item.queue_id.unwrap_or(0),
+                    item.queue_id.unwrap_or(0),
+                )
+            }),
+            items: items
+                .into_iter()
+                .map(|item| ReportQueueItem {
+                    queue_id: item.queue_id,
+                    guild_id: item.guild_id,
+                    guild_tag: item.guild_tag,
+                    post_id: item.post_id,
+                    comment_id: item.comment_id,
+                    post_title: item.post_title,
+                    comment_body: item.comment_body,
+                    author_username: item.author_username,
+                    report_count: item.report_count,
+                    reasons: item.reasons,
+                    first_reported_at: item.first_reported_at.map(|c| c.to_string()),
+                    last_reported_at: item.last_reported_at.map(|c| c.to_string()),
+                })
+                .collect(),
+        })
+    }
+}
+
+//two requests can both get past exists_for_reporter, the second then hits the unique index on the reporter and
+//the post or comment. that's the same mistake as reporting twice, not a server error
+fn duplicate_report(err: sqlx::Error) -> anyhow::Error {
+    match &err {
+        sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("23505") => {
+            ApiError::conflict("You have already reported this.").into()
+        }
+        _ => err.into(),
+    }
+}
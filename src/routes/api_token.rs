@@ -0,0 +1,11 @@
+use crate::api_token::api_handlers;
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /tokens
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(api_handlers::get_tokens::handler)
+        .service(api_handlers::create_token::handler)
+        .service(api_handlers::revoke_all_tokens::handler)
+        .service(api_handlers::revoke_token::handler);
+}
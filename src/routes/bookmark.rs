@@ -0,0 +1,10 @@
+use crate::bookmark::api_handlers;
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /bookmarks
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(api_handlers::save_post::handler)
+        .service(api_handlers::unsave_post::handler)
+        .service(api_handlers::get_bookmarks::handler);
+}
@@ -1,12 +1,14 @@
 use crate::comment::api_handlers;
+use crate::utils::rate_limit::{RateLimit, RouteGroup};
 use actix_web::web;
 
 //all these routes are preceded by the namespaced /guild
 
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.route(
-        "/create",
-        web::post().to(api_handlers::create_comment::handler),
+    cfg.service(
+        web::resource("/create")
+            .wrap(RateLimit::new(RouteGroup::Comment))
+            .route(web::post().to(api_handlers::create_comment::handler)),
     )
     .route(
         "/delete",
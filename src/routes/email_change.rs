@@ -0,0 +1,14 @@
+use crate::email_change::api_handlers;
+use crate::utils::rate_limit::{RateLimit, RouteGroup};
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /changeemail
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/request")
+            .wrap(RateLimit::new(RouteGroup::Auth))
+            .route(web::post().to(api_handlers::request_email_change::handler)),
+    )
+    .service(api_handlers::confirm_email_change::handler);
+}
@@ -13,6 +13,9 @@ pub fn init(cfg: &mut web::ServiceConfig) {
     .service(api_handlers::remove_guild::handler)
     .service(api_handlers::remove_moderator::handler)
     .service(api_handlers::appoint_moderator::handler)
+    .service(api_handlers::ban_user::handler)
+    .service(api_handlers::admin_ban_user::handler)
+    .service(api_handlers::unban_user::handler)
     .route(
         "mod/removepost",
         web::post().to(api_handlers::mod_remove_post::handler),
@@ -22,7 +25,9 @@ pub fn init(cfg: &mut web::ServiceConfig) {
         web::post().to(api_handlers::mod_remove_comment::handler),
     )
     .service(api_handlers::update_guild_name::handler)
+    .service(api_handlers::update_guild_tag::handler)
     .service(api_handlers::update_guild_description::handler)
     .service(api_handlers::update_guild_banner::handler)
-    .service(api_handlers::update_guild_avatar::handler);
+    .service(api_handlers::update_guild_avatar::handler)
+    .service(api_handlers::update_notification_preference::handler);
 }
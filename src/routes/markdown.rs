@@ -0,0 +1,8 @@
+use crate::markdown::api_handlers;
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /markdown
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(api_handlers::preview_markdown::handler);
+}
@@ -0,0 +1,16 @@
+use crate::media::api_handlers;
+use crate::utils::rate_limit::{RateLimit, RouteGroup};
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /media
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/upload")
+            .wrap(RateLimit::new(RouteGroup::Upload))
+            .route(web::post().to(api_handlers::upload_media::handler)),
+    )
+    .service(api_handlers::get_media_file::handler)
+    .service(api_handlers::get_media::handler)
+    .service(api_handlers::delete_media::handler);
+}
@@ -1,10 +1,20 @@
+pub mod api_token;
+pub mod bookmark;
 pub mod comment;
+pub mod email_change;
 pub mod guild;
+pub mod markdown;
+pub mod media;
+pub mod modlog;
+pub mod notification;
 pub mod post;
+pub mod realtime;
 pub mod registration;
 pub mod report;
 pub mod reset_password;
+pub mod search;
 pub mod site;
+pub mod two_factor;
 pub mod user;
 pub mod view;
 pub mod vote;
@@ -0,0 +1,9 @@
+use crate::moderation_action::api_handlers;
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /modlog
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(api_handlers::get_guild_modlog::handler)
+        .service(api_handlers::get_site_audit_log::handler);
+}
@@ -0,0 +1,12 @@
+use crate::notification::api_handlers;
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /notifications
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(api_handlers::get_notifications::handler)
+        .service(api_handlers::get_unread_count::handler)
+        .service(api_handlers::mark_read::handler)
+        .service(api_handlers::mark_all_read::handler)
+        .service(api_handlers::delete_notification::handler);
+}
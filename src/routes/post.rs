@@ -1,12 +1,14 @@
 use crate::post::api_handlers;
+use crate::utils::rate_limit::{RateLimit, RouteGroup};
 use actix_web::web;
 
 //all these routes are preceded by the namespaced /guild
 
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.route(
-        "/create",
-        web::post().to(api_handlers::create_post::handler),
+    cfg.service(
+        web::resource("/create")
+            .wrap(RateLimit::new(RouteGroup::Post))
+            .route(web::post().to(api_handlers::create_post::handler)),
     )
     .route("/edit", web::post().to(api_handlers::edit_post::handler))
     .route(
@@ -0,0 +1,8 @@
+use crate::realtime::api_handlers;
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /events
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(api_handlers::stream::handler);
+}
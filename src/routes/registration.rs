@@ -1,7 +1,12 @@
 use crate::user_registration::api_handlers;
+use crate::utils::rate_limit::{RateLimit, RouteGroup};
 use actix_web::web;
 
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.route("/register", web::post().to(api_handlers::register::handler))
-        .service(api_handlers::confirm_registration::handler);
+    cfg.service(
+        web::resource("/register")
+            .wrap(RateLimit::new(RouteGroup::Auth))
+            .route(web::post().to(api_handlers::register::handler)),
+    )
+    .service(api_handlers::confirm_registration::handler);
 }
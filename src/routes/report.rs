@@ -1,11 +1,19 @@
 use crate::report::api_handlers;
+use crate::utils::rate_limit::{RateLimit, RouteGroup};
 use actix_web::web;
 
-//all these routes are preceded by the namespaced /guild
+//all these routes are preceded by the namespaced /report
 
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.route(
-        "/create",
-        web::post().to(api_handlers::create_report::handler),
-    );
+    cfg.service(
+        web::resource("/create")
+            .wrap(RateLimit::new(RouteGroup::Report))
+            .route(web::post().to(api_handlers::create_report::handler)),
+    )
+    .route(
+        "/markaddressed",
+        web::post().to(api_handlers::mark_addressed::handler),
+    )
+    .service(api_handlers::get_guild_queue::handler)
+    .service(api_handlers::get_site_queue::handler);
 }
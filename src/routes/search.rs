@@ -0,0 +1,8 @@
+use crate::search::api_handlers;
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /search
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(api_handlers::search::handler);
+}
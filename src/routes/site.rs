@@ -7,5 +7,6 @@ pub fn init(cfg: &mut web::ServiceConfig) {
         .service(api_handlers::site_delete_user::handler)
         .service(api_handlers::site_verify_user::handler)
         .service(api_handlers::site_unverify_user::handler)
-        .service(api_handlers::site_make_user_admin::handler);
+        .service(api_handlers::site_make_user_admin::handler)
+        .service(api_handlers::site_require_two_factor::handler);
 }
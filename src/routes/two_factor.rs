@@ -0,0 +1,11 @@
+use crate::two_factor::api_handlers;
+use actix_web::web;
+
+//all these routes are preceded by the namespaced /2fa
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(api_handlers::enroll::handler)
+        .service(api_handlers::enable::handler)
+        .service(api_handlers::disable::handler)
+        .service(api_handlers::regenerate_recovery_codes::handler);
+}
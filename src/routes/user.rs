@@ -1,11 +1,31 @@
 use crate::user::api_handlers;
+use crate::utils::rate_limit::{RateLimit, RouteGroup};
 use actix_web::web;
 
 //all these routes are preceded by the namespaced /user
 
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.route("/login", web::post().to(api_handlers::login::handler))
-        .route("/logout", web::post().to(api_handlers::logout::handler))
-        .service(api_handlers::block_user::handler)
-        .service(api_handlers::unblock_user::handler);
+    cfg.service(
+        web::resource("/login")
+            .wrap(RateLimit::new(RouteGroup::Auth))
+            .route(web::post().to(api_handlers::login::handler)),
+    )
+    .service(
+        web::resource("/login/2fa")
+            .wrap(RateLimit::new(RouteGroup::Auth))
+            .route(web::post().to(api_handlers::login_two_factor::handler)),
+    )
+    .route("/logout", web::post().to(api_handlers::logout::handler))
+    .service(api_handlers::block_user::handler)
+    .service(api_handlers::unblock_user::handler)
+    .service(api_handlers::get_sessions::handler)
+    .service(api_handlers::revoke_other_sessions::handler)
+    .service(api_handlers::revoke_session::handler)
+    .service(api_handlers::export_data::handler)
+    .service(api_handlers::get_account_deletion::handler)
+    .service(api_handlers::delete_account::handler)
+    .service(api_handlers::cancel_account_deletion::handler)
+    .service(api_handlers::update_avatar::handler)
+    .service(api_handlers::update_profile::handler)
+    .service(api_handlers::update_username::handler);
 }
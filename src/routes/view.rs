@@ -9,6 +9,7 @@ pub fn init(cfg: &mut web::ServiceConfig) {
         .service(api_handlers::get_user_comments::handler)
         .service(api_handlers::get_user_posts::handler)
         .service(api_handlers::get_post_comments::handler)
+        .service(api_handlers::get_post_comment_tree::handler)
         .service(api_handlers::get_user_personal_info::handler)
         .service(api_handlers::get_short_guild_details::handler);
 }
@@ -0,0 +1,89 @@
+use crate::search::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{Page, PageForm, PageRequest};
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+//a cursor only continues the list it came from, the other lists come back empty
+#[get("/{page_number}")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    page_number: web::Path<i64>,
+    search_form: web::Query<Search>,
+    page_form: web::Query<PageForm>,
+) -> Result<HttpResponse, ApiError> {
+    let page = |results_per_page: i64| {
+        PageRequest::new(&page_form, &page_number, &results_per_page)
+            .map_err(|_| ApiError::validation("Invalid page number or cursor."))
+    };
+    let (list_page, short_list_page) = (page(20)?, page(10)?);
+    let continues = |list: &str| {
+        list_page
+            .cursor
+            .as_ref()
+            .is_none_or(|cursor| cursor.sort == list)
+    };
+    if search_form.query.trim().is_empty() {
+        return Err(ApiError::invalid_field(
+            "query",
+            "Search query cannot be empty.",
+        ));
+    }
+    if search_form.query.len() > 100 {
+        return Err(ApiError::invalid_field(
+            "query",
+            "Search query must be less than 100 characters.",
+        ));
+    }
+    let formatted_search = Search {
+        query: search_form.query.trim().to_string(),
+        guild_tag: search_form
+            .guild_tag
+            .clone()
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_lowercase()),
+    };
+
+    let mut results = SearchResults {
+        posts: empty_page(),
+        comments: empty_page(),
+        guilds: empty_page(),
+        users: empty_page(),
+    };
+    if continues("posts") {
+        results.posts =
+            Search::search_posts(&formatted_search, &list_page, db_pool.get_ref()).await?;
+    }
+    if continues("comments") {
+        results.comments =
+            Search::search_comments(&formatted_search, &list_page, db_pool.get_ref()).await?;
+    }
+    //guild and user results only make sense when the search isn't restricted to a guild
+    if formatted_search.guild_tag.is_none() {
+        if continues("guilds") {
+            results.guilds =
+                Search::search_guilds(&formatted_search, &short_list_page, db_pool.get_ref())
+                    .await?;
+        }
+        //usernames are alphanumeric, so anything else can't match (and keeps LIKE wildcards out)
+        let username_prefix: String = formatted_search
+            .query
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        if !username_prefix.is_empty() && continues("users") {
+            results.users =
+                Search::search_users(&username_prefix, &short_list_page, db_pool.get_ref()).await?;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+fn empty_page<T>() -> Page<T> {
+    Page {
+        items: Vec::new(),
+        next_cursor: None,
+    }
+}
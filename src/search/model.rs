@@ -1,15 +1,303 @@
-use crate::comment::*;
-use crate::guild::*;
-use crate::post::*;
-use crate::user::*;
-use actix_web::{Error, HttpRequest, HttpResponse, Responder};
-use anyhow::Result;
+use crate::utils::pagination::{Cursor, Page, PageRequest};
+use crate::view::{DetailedCommentView, DetailedPostView, DetailedUserView, ShortGuildView};
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgRow;
-use sqlx::{FromRow, PgPool, Postgres, Row, Transaction};
+use sqlx::PgPool;
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Search {
     pub query: String,
     pub guild_tag: Option<String>,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchResults {
+    pub posts: Page<DetailedPostView>,
+    pub comments: Page<DetailedCommentView>,
+    pub guilds: Page<ShortGuildView>,
+    pub users: Page<DetailedUserView>,
+}
+
+//guilds sort by exact tag match, then rank, then size, so their cursor key has to carry all three
+#[derive(Debug, Clone, Copy)]
+pub struct GuildSearchKey {
+    pub is_tag_match: bool,
+    pub search_rank: f32,
+    pub members: i32,
+}
+
+impl fmt::Display for GuildSearchKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}",
+            self.is_tag_match, self.search_rank, self.members
+        )
+    }
+}
+
+impl FromStr for GuildSearchKey {
+    type Err = anyhow::Error;
+    fn from_str(key: &str) -> Result<GuildSearchKey> {
+        let mut parts = key.split('/');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(is_tag_match), Some(search_rank), Some(members), None) => Ok(GuildSearchKey {
+                is_tag_match: is_tag_match.parse()?,
+                search_rank: search_rank.parse()?,
+                members: members.parse()?,
+            }),
+            _ => Err(anyhow!("Invalid guild search key.")),
+        }
+    }
+}
+
+//results are ranked with ts_rank, the to_tsvector expressions have to match the indexes in the search migration.
+//each list pages on its own, its cursors are tagged with the list they continue
+impl Search {
+    pub async fn search_posts(
+        search: &Search,
+        page: &PageRequest,
+        pool: &PgPool,
+    ) -> Result<Page<DetailedPostView>> {
+        let posts = sqlx::query!(
+            r#"
+            SELECT * FROM (
+                SELECT detailed_post_view.*,
+                ts_rank(to_tsvector('english', posts.title || ' ' || coalesce(posts.body, '')), query) AS search_rank
+                FROM detailed_post_view
+                INNER JOIN posts ON posts.post_id = detailed_post_view.post_id,
+                websearch_to_tsquery('english', $1) query
+                WHERE to_tsvector('english', posts.title || ' ' || coalesce(posts.body, '')) @@ query
+                AND ($2::VARCHAR IS NULL OR posts.guild_id = resolve_guild_tag($2))
+            ) ranked
+            WHERE ($5::REAL IS NULL OR (search_rank, post_id) < ($5, $6::INTEGER))
+            ORDER BY search_rank DESC, post_id DESC
+            LIMIT $3
+            OFFSET $4
+            "#,
+            search.query,
+            search.guild_tag,
+            page.results_per_page,
+            page.offset(),
+            page.key::<f32>("posts")?,
+            page.id::<i32>()?
+        )
+        .fetch_all(pool)
+        .await?;
+        let next_cursor = page.next_cursor(&posts, |post| {
+            Cursor::new(
+                "posts",
+                post.search_rank.unwrap_or(0.0),
+                post.post_id.unwrap_or(0),
+            )
+        });
+        let posts = posts
+            .into_iter()
+            .map(|post| DetailedPostView {
+                post_id: post.post_id,
+                guild_id: post.guild_id,
+                guild_tag: post.guild_tag,
+                image_url: post.image_url,
+                link_url: post.link_url,
+                title: post.title,
+                body: post.body,
+                body_html: post.body_html,
+                is_locked: post.is_locked,
+                is_edited: post.is_edited,
+                created_at: post.created_at.map(|c| c.to_string()),
+                username: post.username,
+                avatar_url: post.avatar_url,
+                is_admin: post.is_admin,
+                is_verified: post.is_verified,
+                upvotes: post.upvotes,
+                downvotes: post.downvotes,
+                replies: post.replies,
+                is_blocked: false,
+                is_upvoted: false,
+                is_downvoted: false,
+            })
+            .collect();
+
+        Ok(Page {
+            items: posts,
+            next_cursor,
+        })
+    }
+    pub async fn search_comments(
+        search: &Search,
+        page: &PageRequest,
+        pool: &PgPool,
+    ) -> Result<Page<DetailedCommentView>> {
+        let comments = sqlx::query!(
+            r#"
+            SELECT * FROM (
+                SELECT detailed_comment_view.*, ts_rank(to_tsvector('english', comments.body), query) AS search_rank
+                FROM detailed_comment_view
+                INNER JOIN comments ON comments.comment_id = detailed_comment_view.comment_id
+                INNER JOIN posts ON posts.post_id = comments.post_id,
+                websearch_to_tsquery('english', $1) query
+                WHERE to_tsvector('english', comments.body) @@ query
+                AND ($2::VARCHAR IS NULL OR posts.guild_id = resolve_guild_tag($2))
+            ) ranked
+            WHERE ($5::REAL IS NULL OR (search_rank, comment_id) < ($5, $6::INTEGER))
+            ORDER BY search_rank DESC, comment_id DESC
+            LIMIT $3
+            OFFSET $4
+            "#,
+            search.query,
+            search.guild_tag,
+            page.results_per_page,
+            page.offset(),
+            page.key::<f32>("comments")?,
+            page.id::<i32>()?
+        )
+        .fetch_all(pool)
+        .await?;
+        let next_cursor = page.next_cursor(&comments, |comment| {
+            Cursor::new(
+                "comments",
+                comment.search_rank.unwrap_or(0.0),
+                comment.comment_id.unwrap_or(0),
+            )
+        });
+        let comments = comments
+            .into_iter()
+            .map(|comment| DetailedCommentView {
+                comment_id: comment.comment_id,
+                post_id: comment.post_id,
+                parent_comment_id: comment.parent_comment_id,
+                body: comment.body,
+                body_html: comment.body_html,
+                is_edited: comment.is_edited,
+                created_at: comment.created_at.map(|c| c.to_string()),
+                username: comment.username,
+                avatar_url: comment.avatar_url,
+                is_admin: comment.is_admin,
+                is_verified: comment.is_verified,
+                upvotes: comment.upvotes,
+                downvotes: comment.downvotes,
+                is_blocked: false,
+                is_upvoted: false,
+                is_downvoted: false,
+            })
+            .collect();
+
+        Ok(Page {
+            items: comments,
+            next_cursor,
+        })
+    }
+    pub async fn search_guilds(
+        search: &Search,
+        page: &PageRequest,
+        pool: &PgPool,
+    ) -> Result<Page<ShortGuildView>> {
+        let key = page.key::<GuildSearchKey>("guilds")?;
+        let guilds = sqlx::query!(
+            r#"
+            SELECT * FROM (
+                SELECT short_guild_view.*, guilds.guild_tag = lower($1) AS is_tag_match,
+                ts_rank(to_tsvector('english', guilds.guild_name || ' ' || coalesce(guilds.guild_description, '')), query) AS search_rank
+                FROM short_guild_view
+                INNER JOIN guilds ON guilds.guild_id = short_guild_view.guild_id,
+                websearch_to_tsquery('english', $1) query
+                WHERE to_tsvector('english', guilds.guild_name || ' ' || coalesce(guilds.guild_description, '')) @@ query
+                OR guilds.guild_tag = lower($1)
+            ) ranked
+            WHERE ($4::BOOLEAN IS NULL OR (is_tag_match, search_rank, members, guild_id) < ($4, $5::REAL, $6::INTEGER, $7::INTEGER))
+            ORDER BY is_tag_match DESC, search_rank DESC, members DESC, guild_id DESC
+            LIMIT $2
+            OFFSET $3
+            "#,
+            search.query,
+            page.results_per_page,
+            page.offset(),
+            key.map(|key| key.is_tag_match),
+            key.map(|key| key.search_rank),
+            key.map(|key| key.members),
+            page.id::<i32>()?
+        )
+        .fetch_all(pool)
+        .await?;
+        let next_cursor = page.next_cursor(&guilds, |guild| {
+            Cursor::new(
+                "guilds",
+                GuildSearchKey {
+                    is_tag_match: guild.is_tag_match.unwrap_or(false),
+                    search_rank: guild.search_rank.unwrap_or(0.0),
+                    members: guild.members.unwrap_or(0),
+                },
+                guild.guild_id.unwrap_or(0),
+            )
+        });
+        let guilds = guilds
+            .into_iter()
+            .map(|guild| ShortGuildView {
+                guild_id: guild.guild_id,
+                guild_tag: guild.guild_tag,
+                guild_name: guild.guild_name,
+                avatar_url: guild.avatar_url,
+                members: guild.members,
+                number_of_posts: guild.number_of_posts,
+                is_member: false,
+            })
+            .collect();
+
+        Ok(Page {
+            items: guilds,
+            next_cursor,
+        })
+    }
+    pub async fn search_users(
+        username_prefix: &String,
+        page: &PageRequest,
+        pool: &PgPool,
+    ) -> Result<Page<DetailedUserView>> {
+        let users = sqlx::query!(
+            r#"
+            SELECT * FROM detailed_user_view
+            WHERE username LIKE $1 || '%'
+            AND ($4::INTEGER IS NULL OR (length(username), username) > ($4, $5::VARCHAR))
+            ORDER BY length(username), username
+            LIMIT $2
+            OFFSET $3
+            "#,
+            username_prefix,
+            page.results_per_page,
+            page.offset(),
+            page.key::<i32>("users")?,
+            page.id::<String>()?
+        )
+        .fetch_all(pool)
+        .await?;
+        let next_cursor = page.next_cursor(&users, |user| {
+            let username = user.username.clone().unwrap_or_default();
+            Cursor::new("users", username.chars().count(), username)
+        });
+        let users = users
+            .into_iter()
+            .map(|user| DetailedUserView {
+                username: user.username,
+                avatar_url: user.avatar_url,
+                is_admin: user.is_admin,
+                is_verified: user.is_verified,
+                is_banned: user.is_banned,
+                created_at: user.created_at.map(|c| c.to_string()),
+                upvotes: user.upvotes,
+                downvotes: user.downvotes,
+                number_of_posts: user.number_of_posts,
+                number_of_comments: user.number_of_comments,
+                number_of_memberships: user.number_of_memberships,
+                display_name: user.display_name,
+                bio: user.bio,
+            })
+            .collect();
+
+        Ok(Page {
+            items: users,
+            next_cursor,
+        })
+    }
+}
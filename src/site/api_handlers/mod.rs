@@ -1,6 +1,7 @@
 pub mod site_ban_user;
 pub mod site_delete_user;
 pub mod site_make_user_admin;
+pub mod site_require_two_factor;
 pub mod site_unban_user;
 pub mod site_unverify_user;
 pub mod site_verify_user;
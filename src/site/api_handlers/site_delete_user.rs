@@ -1,7 +1,10 @@
+use crate::account_deletion::AccountDeletion;
+use crate::moderation_action::*;
 use crate::user::User;
+use crate::utils::api_error::ApiError;
 use crate::utils::session_validation;
 use actix_session::Session;
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{post, web, HttpResponse};
 use sqlx::PgPool;
 
 #[post("/delete/{username}")]
@@ -9,62 +12,32 @@ pub async fn handler(
     username: web::Path<String>,
     db_pool: web::Data<PgPool>,
     session: Session,
-) -> impl Responder {
+    reason_form: Option<web::Json<ModerationReasonForm>>,
+) -> Result<HttpResponse, ApiError> {
+    let reason = format_reason(&reason_form.and_then(|form| form.into_inner().reason))?;
     let formatted_username = username.to_string().to_lowercase();
-    let valid_session = session_validation::policy_admin(&session, db_pool.get_ref()).await;
-    match valid_session {
-        Ok((None, Some(user))) => {
-            //make sure user exists
-            let user_exists =
-                User::find_by_username_sensitive(&formatted_username, db_pool.get_ref()).await;
-            match user_exists {
-                Ok(Some(user_to_delete)) => {
-                    //delete user
-                    if user_to_delete.is_admin {
-                        return HttpResponse::Forbidden().body("You cannot delete a site admin.");
-                    }
-                    let mut tx = db_pool.begin().await.unwrap();
-                    let delete_success = User::delete(&user_to_delete.user_id, &mut tx).await;
-                    match delete_success {
-                        Ok(()) => {
-                            tx.commit().await.unwrap();
-                            return HttpResponse::Ok().body("User has been deleted.");
-                        }
-                        Err(err) => {
-                            let succesful_rollback = tx.rollback().await;
-                            match succesful_rollback {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error rolling back transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            error!("Error deleting user: {}", err);
-                            return HttpResponse::InternalServerError()
-                                .body("Error deleting user.");
-                        }
-                    }
-                }
-                Ok(None) => {
-                    return HttpResponse::BadRequest()
-                        .body("User you are trying to delete does not exist.");
-                }
-                Err(err) => {
-                    error!("Error fetching user: {}", err);
-                    return HttpResponse::InternalServerError().body("Error fetching user.");
-                }
-            }
-        }
-        Ok((Some(response), None)) => {
-            return response;
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
+    let actor = session_validation::policy_admin(&session, db_pool.get_ref()).await?;
+    //make sure user exists
+    let user_to_delete = User::find_by_username_sensitive(&formatted_username, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("User you are trying to delete does not exist."))?;
+    if user_to_delete.is_admin {
+        return Err(ApiError::forbidden("You cannot delete a site admin."));
     }
+    //delete user
+    let mut tx = db_pool.begin().await?;
+    //logged first, so the entry keeps the username of the deleted user
+    ModerationAction::create(
+        &ModerationActionForm {
+            target_user_id: Some(user_to_delete.user_id),
+            reason,
+            ..ModerationActionForm::new(actor.user_id, ModerationActionType::SiteDeleteUser)
+        },
+        &mut tx,
+    )
+    .await?;
+    //their content goes, but not other people's replies to it
+    AccountDeletion::delete_user(&user_to_delete.user_id, false, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("User has been deleted."))
 }
@@ -0,0 +1,40 @@
+use crate::moderation_action::*;
+use crate::site::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//while on, site admins without two factor can't use admin routes until they turn it on
+#[post("/settings/requiretwofactor")]
+pub async fn handler(
+    require_form: web::Json<RequireTwoFactorForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let reason = format_reason(&require_form.reason)?;
+    let actor = session_validation::policy_admin(&session, db_pool.get_ref()).await?;
+    //don't let an admin lock themselves out
+    if require_form.required && !actor.totp_enabled {
+        return Err(ApiError::forbidden(
+            "Turn on two-factor authentication for your own account first.",
+        ));
+    }
+    let action = match require_form.required {
+        true => ModerationActionType::SiteRequireTwoFactor,
+        false => ModerationActionType::SiteUnrequireTwoFactor,
+    };
+    let mut tx = db_pool.begin().await?;
+    Site::update_require_admin_two_factor(require_form.required, &mut tx).await?;
+    ModerationAction::create(
+        &ModerationActionForm {
+            reason,
+            ..ModerationActionForm::new(actor.user_id, action)
+        },
+        &mut tx,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Site settings updated."))
+}
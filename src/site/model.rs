@@ -1,12 +1,50 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use sqlx::{Postgres, Transaction};
+use sqlx::{PgPool, Postgres, Transaction};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Site {
     pub description: Option<String>,
 }
+
+//body for /admin/settings/requiretwofactor
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RequireTwoFactorForm {
+    pub required: bool,
+    pub reason: Option<String>,
+}
+
 impl Site {
+    //whether site admins have to have two factor on to use their admin powers
+    pub async fn requires_admin_two_factor(pool: &PgPool) -> Result<bool> {
+        let site = sqlx::query!(
+            r#"
+            SELECT require_admin_two_factor FROM site_info
+            LIMIT 1
+            "#
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(site
+            .map(|site| site.require_admin_two_factor)
+            .unwrap_or(false))
+    }
+    pub async fn update_require_admin_two_factor(
+        required: bool,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE site_info
+            SET require_admin_two_factor = $1
+            "#,
+            required
+        )
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
     pub async fn update_description(
         new_description: &String,
         tx: &mut Transaction<'_, Postgres>,
@@ -0,0 +1,55 @@
+use crate::login_failure::*;
+use crate::site::Site;
+use crate::two_factor::*;
+use crate::user::User;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//needs both the password and a code, so a stolen session alone can't turn it off
+#[post("/disable")]
+pub async fn handler(
+    disable_form: web::Json<TwoFactorPasswordForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    if !user.totp_enabled {
+        return Err(ApiError::validation("Two-factor authentication is not on."));
+    }
+    if user.is_admin && Site::requires_admin_two_factor(db_pool.get_ref()).await? {
+        return Err(ApiError::forbidden(
+            "Site admins can't turn off two-factor authentication while the site requires it.",
+        ));
+    }
+    //wrong passwords and codes count towards the login lockout
+    let account_key = LoginFailure::account_key(&user.username);
+    if let Some(wait) =
+        LoginFailure::find_wait(std::slice::from_ref(&account_key), db_pool.get_ref()).await?
+    {
+        return Err(ApiError::rate_limited(
+            "Too many failed attempts, try again later.",
+            wait,
+        ));
+    }
+    if !User::verify_password(&user, &disable_form.password).await? {
+        LoginFailure::record(&account_key, &ACCOUNT_LOCKOUT, db_pool.get_ref()).await?;
+        return Err(ApiError::invalid_field("password", "Incorrect password."));
+    }
+    let two_factor = TwoFactor::find_by_user_id(&user.user_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("User does not exist."))?;
+    let mut tx = db_pool.begin().await?;
+    if !two_factor
+        .verify_totp_or_recovery_code(&disable_form.code, &mut tx)
+        .await?
+    {
+        LoginFailure::record(&account_key, &ACCOUNT_LOCKOUT, db_pool.get_ref()).await?;
+        return Err(ApiError::invalid_field("code", "Incorrect code."));
+    }
+    TwoFactor::disable(&user.user_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Two-factor authentication is off."))
+}
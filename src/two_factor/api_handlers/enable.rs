@@ -0,0 +1,49 @@
+use crate::login_failure::*;
+use crate::two_factor::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//turns two factor on once the authenticator has proven it has the secret, and hands out the recovery codes
+#[post("/enable")]
+pub async fn handler(
+    code_form: web::Json<TwoFactorCodeForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    if user.totp_enabled {
+        return Err(ApiError::conflict(
+            "Two-factor authentication is already on.",
+        ));
+    }
+    let two_factor = TwoFactor::find_by_user_id(&user.user_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("User does not exist."))?;
+    if two_factor.totp_secret.is_none() {
+        return Err(ApiError::validation(
+            "Start two-factor setup at /2fa/enroll first.",
+        ));
+    }
+    //wrong codes count towards the login lockout
+    let account_key = LoginFailure::account_key(&user.username);
+    if let Some(wait) =
+        LoginFailure::find_wait(std::slice::from_ref(&account_key), db_pool.get_ref()).await?
+    {
+        return Err(ApiError::rate_limited(
+            "Too many failed attempts, try again later.",
+            wait,
+        ));
+    }
+    let mut tx = db_pool.begin().await?;
+    if !two_factor.verify_totp(&code_form.code, &mut tx).await? {
+        LoginFailure::record(&account_key, &ACCOUNT_LOCKOUT, db_pool.get_ref()).await?;
+        return Err(ApiError::invalid_field("code", "Incorrect code."));
+    }
+    TwoFactor::enable(&user.user_id, &mut tx).await?;
+    let recovery_codes = TwoFactor::create_recovery_codes(&user.user_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesView { recovery_codes }))
+}
@@ -0,0 +1,27 @@
+use crate::two_factor::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::{session_validation, totp};
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//starts two factor setup. nothing changes at login until /2fa/enable gets a code from the new secret
+#[post("/enroll")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    if user.totp_enabled {
+        return Err(ApiError::conflict(
+            "Two-factor authentication is already on.",
+        ));
+    }
+    let mut tx = db_pool.begin().await?;
+    let secret = TwoFactor::start_enrollment(&user.user_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(TwoFactorEnrollmentView {
+        otpauth_uri: totp::otpauth_uri(&user.username, &secret),
+        secret,
+    }))
+}
@@ -0,0 +1,4 @@
+pub mod disable;
+pub mod enable;
+pub mod enroll;
+pub mod regenerate_recovery_codes;
@@ -0,0 +1,4 @@
+pub mod api_handlers;
+mod model;
+
+pub use model::*;
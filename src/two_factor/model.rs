@@ -0,0 +1,265 @@
+use crate::utils::{token, totp};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactor {
+    pub user_id: i32,
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+}
+
+//returned once from enroll, for the authenticator app
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactorEnrollmentView {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+//returned once whenever a new set is made, only hashes are kept
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecoveryCodesView {
+    pub recovery_codes: Vec<String>,
+}
+
+//code is a 6 digit code from the authenticator, or a recovery code where noted
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactorCodeForm {
+    pub code: String,
+}
+
+//for changes a stolen session alone shouldn't be able to make, eg turning two factor off
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactorPasswordForm {
+    pub password: String,
+    pub code: String,
+}
+
+impl TwoFactor {
+    pub async fn find_by_user_id(user_id: &i32, pool: &PgPool) -> Result<Option<TwoFactor>> {
+        let two_factor = sqlx::query!(
+            r#"
+            SELECT user_id, totp_secret, totp_enabled FROM users
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(two_factor.map(|two_factor| TwoFactor {
+            user_id: two_factor.user_id,
+            totp_secret: two_factor.totp_secret,
+            totp_enabled: two_factor.totp_enabled,
+        }))
+    }
+    //a new secret, not enabled until a code from it is verified. enrolling again replaces an unverified secret
+    pub async fn start_enrollment(
+        user_id: &i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<String> {
+        let secret = totp::generate_secret();
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_secret = $2, totp_enabled = FALSE, totp_last_step = NULL
+            WHERE user_id = $1
+            "#,
+            user_id,
+            &secret
+        )
+        .execute(tx)
+        .await?;
+        Ok(secret)
+    }
+    pub async fn enable(user_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_enabled = TRUE
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+    pub async fn disable(user_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_secret = NULL, totp_enabled = FALSE, totp_last_step = NULL
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        Ok(())
+    }
+    //checks a code from the authenticator. each 30 second step is only accepted once, so a code seen over
+    //someone's shoulder can't be replayed
+    pub async fn verify_totp(
+        &self,
+        code: &str,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<bool> {
+        let secret = match &self.totp_secret {
+            Some(secret) => secret,
+            None => return Ok(false),
+        };
+        let step = match totp::verify_code(secret, code, totp::current_step()) {
+            Some(step) => step,
+            None => return Ok(false),
+        };
+        let accepted = sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_last_step = $2
+            WHERE user_id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)
+            "#,
+            self.user_id,
+            step
+        )
+        .execute(tx)
+        .await?;
+        Ok(accepted.rows_affected() == 1)
+    }
+    //an authenticator code or an unused recovery code, which is then used up
+    pub async fn verify_totp_or_recovery_code(
+        &self,
+        code: &str,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<bool> {
+        if self.verify_totp(code, &mut *tx).await? {
+            return Ok(true);
+        }
+        let code = code.trim().replace('-', "").to_lowercase();
+        let redeemed = sqlx::query!(
+            r#"
+            UPDATE recovery_codes
+            SET used_at = LOCALTIMESTAMP
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+            self.user_id,
+            token::hash_token(&code)
+        )
+        .execute(tx)
+        .await?;
+        Ok(redeemed.rows_affected() == 1)
+    }
+    //replaces any earlier codes. formatted xxxxx-xxxxx, the dash is optional when they're typed back in
+    pub async fn create_recovery_codes(
+        user_id: &i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Vec<String>> {
+        sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let mut recovery_codes = vec![];
+        for _ in 0..RECOVERY_CODE_COUNT {
+            let code = token::random_token(10);
+            sqlx::query!(
+                r#"
+                INSERT INTO recovery_codes (user_id, code_hash)
+                VALUES ($1, $2)
+                "#,
+                user_id,
+                token::hash_token(&code)
+            )
+            .execute(&mut *tx)
+            .await?;
+            recovery_codes.push(format!("{}-{}", &code[..5], &code[5..]));
+        }
+        Ok(recovery_codes)
+    }
+}
+
+//password checked, code still needed. see the pending_logins migration
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingLogin {
+    pub pending_id: String,
+    pub user_id: i32,
+}
+
+//what login returns instead of a session when a code is needed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactorChallengeView {
+    pub two_factor_required: bool,
+}
+
+impl PendingLogin {
+    pub async fn create(user_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<String> {
+        let pending_id = Uuid::new_v4().to_string();
+        sqlx::query!(
+            r#"
+            INSERT INTO pending_logins (pending_id, user_id)
+            VALUES ($1, $2)
+            "#,
+            &pending_id,
+            user_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(pending_id)
+    }
+    //pending logins last 5 minutes
+    pub async fn find_active(pending_id: &String, pool: &PgPool) -> Result<Option<PendingLogin>> {
+        let pending = sqlx::query!(
+            r#"
+            SELECT pending_id, user_id FROM pending_logins
+            WHERE pending_id = $1
+            AND created_at > LOCALTIMESTAMP - INTERVAL '5 minutes'
+            "#,
+            pending_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(pending.map(|pending| PendingLogin {
+            pending_id: pending.pending_id,
+            user_id: pending.user_id,
+        }))
+    }
+    pub async fn delete(pending_id: &String, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            DELETE FROM pending_logins
+            WHERE pending_id = $1
+            "#,
+            pending_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+    pub async fn purge_expired(pool: &PgPool) -> Result<u64> {
+        let purged = sqlx::query!(
+            r#"
+            DELETE FROM pending_logins
+            WHERE created_at <= LOCALTIMESTAMP - INTERVAL '5 minutes'
+            "#
+        )
+        .execute(pool)
+        .await?;
+        Ok(purged.rows_affected())
+    }
+}
@@ -0,0 +1,22 @@
+use crate::account_deletion::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/delete/cancel")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let mut tx = db_pool.begin().await?;
+    if !AccountDeletion::cancel(&user.user_id, &mut tx).await? {
+        return Err(ApiError::not_found(
+            "Your account is not scheduled for deletion.",
+        ));
+    }
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Your account will not be deleted."))
+}
@@ -0,0 +1,64 @@
+use crate::account_deletion::*;
+use crate::guild_membership::GuildMembership;
+use crate::user::User;
+use crate::utils::api_error::ApiError;
+use crate::utils::mailer::{MailTemplate, Mailer};
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//schedules the account for deletion after the grace period, until then it can be cancelled
+#[post("/delete")]
+pub async fn handler(
+    delete_form: web::Json<DeleteAccountForm>,
+    db_pool: web::Data<PgPool>,
+    mailer: web::Data<dyn Mailer>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    if !User::verify_password(&user, &delete_form.password).await? {
+        return Err(ApiError::invalid_field("password", "Incorrect password."));
+    }
+    if user.is_admin {
+        return Err(ApiError::forbidden(
+            "Site admins cannot delete their own account.",
+        ));
+    }
+    //same as leaving, guilds need their admin
+    let memberships =
+        GuildMembership::find_all_by_user_id(&user.user_id, db_pool.get_ref(), &0, &1).await?;
+    if memberships.iter().any(|membership| membership.is_admin) {
+        return Err(ApiError::forbidden(
+            "You cannot delete your account while you are a guild admin.",
+        ));
+    }
+    if AccountDeletion::find_by_user_id(&user.user_id, db_pool.get_ref())
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::conflict(
+            "Your account is already scheduled for deletion.",
+        ));
+    }
+    let mut tx = db_pool.begin().await?;
+    let deletion = AccountDeletion::schedule(
+        &user.user_id,
+        delete_form.anonymise.unwrap_or(false),
+        grace_days(),
+        &mut tx,
+    )
+    .await?;
+    mailer
+        .send_template(
+            MailTemplate::AccountDeletion,
+            &user.email,
+            &[
+                ("username", &user.username),
+                ("scheduled_for", &deletion.scheduled_for),
+            ],
+        )
+        .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(deletion))
+}
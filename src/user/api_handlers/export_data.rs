@@ -0,0 +1,22 @@
+use crate::user_export::UserExport;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+//a json file of everything the user has on the site
+#[get("/export")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let export = UserExport::build(&user.user_id, db_pool.get_ref()).await?;
+    Ok(HttpResponse::Ok()
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}-export.json\"", user.username),
+        )
+        .json(export))
+}
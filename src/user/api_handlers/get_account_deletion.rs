@@ -0,0 +1,18 @@
+use crate::account_deletion::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+#[get("/delete")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let deletion = AccountDeletion::find_by_user_id(&user.user_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Your account is not scheduled for deletion."))?;
+    Ok(HttpResponse::Ok().json(deletion))
+}
@@ -0,0 +1,28 @@
+use crate::user_session::{DeviceSessionView, UserSession};
+use crate::utils::api_error::ApiError;
+use crate::utils::{session_config, session_validation};
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+#[get("/sessions")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let config = session_config::get();
+    let sessions = UserSession::find_sessions_by_user_id(
+        &user.user_id,
+        &config.idle_timeout_minutes,
+        &config.absolute_timeout_hours,
+        db_pool.get_ref(),
+    )
+    .await?;
+    let current_session_id = session.get::<String>("session_id").unwrap_or(None);
+    let devices: Vec<DeviceSessionView> = sessions
+        .into_iter()
+        .map(|user_session| DeviceSessionView::from_session(user_session, &current_session_id))
+        .collect();
+    Ok(HttpResponse::Ok().json(devices))
+}
@@ -1,27 +1,25 @@
+use crate::login_failure::*;
+use crate::two_factor::{PendingLogin, TwoFactorChallengeView};
 use crate::user::*;
 use crate::user_session::*;
-use crate::utils::session_validation;
+use crate::utils::api_error::ApiError;
+use crate::utils::{request_info, session_validation};
 use actix_session::Session;
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse};
 use sqlx::PgPool;
 
 pub async fn handler(
     login_form: web::Json<UserLoginForm>,
     db_pool: web::Data<PgPool>,
     session: Session,
-) -> impl Responder {
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
     //check if there is a session in cookie
-    let valid = session_validation::validate_session(&session, db_pool.get_ref()).await;
-    match valid {
-        Ok(Some(user)) => {
-            return HttpResponse::InternalServerError()
-                .body("Login error. User is already logged in");
-        }
-        Err(err) => {
-            error!("Error validating session: {}", err);
-            return HttpResponse::InternalServerError().body("Login error.");
-        }
-        Ok(None) => (),
+    if session_validation::validate_session(&session, db_pool.get_ref())
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::conflict("User is already logged in."));
     }
 
     //format login form
@@ -31,90 +29,97 @@ pub async fn handler(
         password: cloned_form.password,
     };
 
-    //check to make sure user with username exists
-    let user_exists =
-        User::find_by_username_sensitive(&formatted_form.username, db_pool.get_ref()).await;
-    match user_exists {
-        Ok(None) => {
-            return HttpResponse::InternalServerError()
-                .body("A user with that username does not exist");
-        }
-        Err(err) => {
-            error!("Error finding user: {}", err);
-            return HttpResponse::InternalServerError().body("Login error.");
-        }
-        Ok(Some(user)) => {
-            //user exists
-            if user.is_banned {
-                return HttpResponse::Forbidden().body("You are banned.");
+    //failed logins slow down and then lock out the username and the client ip. the ip is the connection's, or
+    //X-Forwarded-For's from a trusted proxy, so a password spray can't get a fresh ip with every request
+    let account_key = LoginFailure::account_key(&formatted_form.username);
+    let ip_key = request_info::client_ip(&req).map(|ip| LoginFailure::ip_key(&ip));
+    let failure_keys: Vec<String> = std::iter::once(account_key.clone())
+        .chain(ip_key.clone())
+        .collect();
+    if let Some(wait) = LoginFailure::find_wait(&failure_keys, db_pool.get_ref()).await? {
+        return Err(ApiError::rate_limited(
+            "Too many failed login attempts, try again later.",
+            wait,
+        ));
+    }
+
+    //same answer and roughly the same time whether or not the username exists
+    let user =
+        User::find_by_username_sensitive(&formatted_form.username, db_pool.get_ref()).await?;
+    let password_matches = match &user {
+        Some(user) => User::verify_password(user, &formatted_form.password).await?,
+        None => User::verify_dummy_password(&formatted_form.password),
+    };
+    let user = match user {
+        Some(user) if password_matches => user,
+        _ => {
+            let account_failure =
+                LoginFailure::record(&account_key, &ACCOUNT_LOCKOUT, db_pool.get_ref()).await?;
+            if ACCOUNT_LOCKOUT.is_locked_out(account_failure.failure_count) {
+                warn!(
+                    "Locked out {} after {} failed logins",
+                    account_failure.failure_key, account_failure.failure_count
+                );
             }
-            //check that password matches
-            let password_check = User::verify_password(&user, &formatted_form.password).await;
-            match password_check {
-                Ok(true) => {
-                    //password matches
-                    //create session
-                    let mut tx = db_pool.begin().await.unwrap();
-                    let created_session = UserSession::create(user.user_id, &mut tx).await;
-                    match created_session {
-                        Ok(new_session) => {
-                            //store session id in cookie
-                            let create_session = session.set("session_id", &new_session.session_id);
-                            match create_session {
-                                Ok(()) => {
-                                    let succesful_commit = tx.commit().await;
-                                    match succesful_commit {
-                                        Ok(()) => (),
-                                        Err(err) => {
-                                            error!("Error committing transaction: {}", err);
-                                            return HttpResponse::InternalServerError()
-                                                .body("Unknown Error.");
-                                        }
-                                    }
-                                    return HttpResponse::Ok().json(UserSessionView {
-                                        session_id: new_session.session_id,
-                                        user_id: new_session.user_id,
-                                    });
-                                }
-                                Err(err) => {
-                                    let succesful_rollback = tx.rollback().await;
-                                    match succesful_rollback {
-                                        Ok(()) => (),
-                                        Err(err) => {
-                                            error!("Error rolling back transaction: {}", err);
-                                            return HttpResponse::InternalServerError()
-                                                .body("Unknown Error.");
-                                        }
-                                    }
-                                    error!("Error creating session cookie: {}", err);
-                                    return HttpResponse::InternalServerError().body("Login error");
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            let succesful_rollback = tx.rollback().await;
-                            match succesful_rollback {
-                                Ok(()) => (),
-                                Err(err) => {
-                                    error!("Error rolling back transaction: {}", err);
-                                    return HttpResponse::InternalServerError()
-                                        .body("Unknown Error.");
-                                }
-                            }
-                            error!("Error creating session in db: {}", err);
-                            return HttpResponse::InternalServerError().body("Login error");
-                        }
-                    }
-                }
-                Ok(false) => {
-                    //password doesnt match
-                    return HttpResponse::InternalServerError().body("Incorrect password");
-                }
-                Err(err) => {
-                    error!("Error verifying password: {}", err);
-                    return HttpResponse::InternalServerError().body("Login error.");
+            if let Some(ip_key) = &ip_key {
+                let ip_failure =
+                    LoginFailure::record(ip_key, &IP_LOCKOUT, db_pool.get_ref()).await?;
+                if IP_LOCKOUT.is_locked_out(ip_failure.failure_count) {
+                    warn!(
+                        "Locked out {} after {} failed logins",
+                        ip_failure.failure_key, ip_failure.failure_count
+                    );
                 }
             }
+            return Err(ApiError::unauthorized("Incorrect username or password."));
         }
+    };
+    //only after the password, the ban reason is for the banned user
+    session_validation::check_site_ban(&user, db_pool.get_ref()).await?;
+
+    //with two factor on, the password only gets a pending login that /user/login/2fa promotes
+    if user.totp_enabled {
+        let mut tx = db_pool.begin().await?;
+        let pending_id = PendingLogin::create(&user.user_id, &mut tx).await?;
+        session.renew();
+        session
+            .set("pending_login_id", &pending_id)
+            .map_err(|err| ApiError::internal(format!("Error creating session cookie: {}", err)))?;
+        tx.commit().await?;
+        return Ok(HttpResponse::Accepted().json(TwoFactorChallengeView {
+            two_factor_required: true,
+        }));
     }
+    start_session(&user, &req, &session, db_pool.get_ref()).await
+}
+
+//creates the session and its cookie once the user has proven who they are
+pub async fn start_session(
+    user: &User,
+    req: &HttpRequest,
+    session: &Session,
+    pool: &PgPool,
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = pool.begin().await?;
+    LoginFailure::clear(&LoginFailure::account_key(&user.username), &mut tx).await?;
+    //remember which device this is for the user's session list
+    let new_session = UserSession::create(
+        user.user_id,
+        request_info::user_agent(req),
+        request_info::client_ip(req),
+        &mut tx,
+    )
+    .await?;
+    //fresh cookie on login, so a cookie set before login can't be reused
+    session.renew();
+    session.remove("pending_login_id");
+    //store session id in cookie
+    session
+        .set("session_id", &new_session.session_id)
+        .map_err(|err| ApiError::internal(format!("Error creating session cookie: {}", err)))?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(UserSessionView {
+        session_id: new_session.session_id,
+        user_id: new_session.user_id,
+    }))
 }
@@ -0,0 +1,60 @@
+use super::login::start_session;
+use crate::login_failure::*;
+use crate::two_factor::*;
+use crate::user::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+//second step of a login with two factor on. takes an authenticator code or a recovery code
+pub async fn handler(
+    code_form: web::Json<TwoFactorCodeForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let pending_id = match session.get::<String>("pending_login_id") {
+        Ok(Some(pending_id)) => pending_id,
+        _ => return Err(ApiError::unauthorized("Log in with your password first.")),
+    };
+    let pending = match PendingLogin::find_active(&pending_id, db_pool.get_ref()).await? {
+        Some(pending) => pending,
+        None => {
+            session.remove("pending_login_id");
+            return Err(ApiError::unauthorized(
+                "Your login has expired, log in with your password again.",
+            ));
+        }
+    };
+    let user = User::find_by_id(&pending.user_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::unauthorized("Log in with your password first."))?;
+    //wrong codes count towards the same lockout as wrong passwords
+    let account_key = LoginFailure::account_key(&user.username);
+    if let Some(wait) =
+        LoginFailure::find_wait(std::slice::from_ref(&account_key), db_pool.get_ref()).await?
+    {
+        return Err(ApiError::rate_limited(
+            "Too many failed login attempts, try again later.",
+            wait,
+        ));
+    }
+    let two_factor = TwoFactor::find_by_user_id(&user.user_id, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::unauthorized("Log in with your password first."))?;
+
+    let mut tx = db_pool.begin().await?;
+    if !two_factor
+        .verify_totp_or_recovery_code(&code_form.code, &mut tx)
+        .await?
+    {
+        LoginFailure::record(&account_key, &ACCOUNT_LOCKOUT, db_pool.get_ref()).await?;
+        return Err(ApiError::unauthorized("Incorrect code."));
+    }
+    PendingLogin::delete(&pending_id, &mut tx).await?;
+    tx.commit().await?;
+    session_validation::check_site_ban(&user, db_pool.get_ref()).await?;
+    start_session(&user, &req, &session, db_pool.get_ref()).await
+}
@@ -1,4 +1,15 @@
 pub mod block_user;
+pub mod cancel_account_deletion;
+pub mod delete_account;
+pub mod export_data;
+pub mod get_account_deletion;
+pub mod get_sessions;
 pub mod login;
+pub mod login_two_factor;
 pub mod logout;
+pub mod revoke_other_sessions;
+pub mod revoke_session;
 pub mod unblock_user;
+pub mod update_avatar;
+pub mod update_profile;
+pub mod update_username;
@@ -0,0 +1,21 @@
+use crate::user_session::UserSession;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//log out everywhere else
+#[post("/sessions/revokeothers")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let current_session_id = session.get::<String>("session_id").unwrap_or(None);
+    let mut tx = db_pool.begin().await?;
+    UserSession::delete_all_by_user_id(&user.user_id, current_session_id.as_deref(), &mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Logged out of all other sessions."))
+}
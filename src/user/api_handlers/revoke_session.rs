@@ -0,0 +1,25 @@
+use crate::user_session::UserSession;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/sessions/{device_id}/revoke")]
+pub async fn handler(
+    device_id: web::Path<i32>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let mut tx = db_pool.begin().await?;
+    let session_id = UserSession::delete_by_device_id(&device_id, &user.user_id, &mut tx)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Session not found."))?;
+    tx.commit().await?;
+    //revoking the device you're on is just logging out
+    if session.get::<String>("session_id").unwrap_or(None) == Some(session_id) {
+        session.remove("session_id");
+    }
+    Ok(HttpResponse::Ok().body("Session revoked."))
+}
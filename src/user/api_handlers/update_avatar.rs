@@ -0,0 +1,50 @@
+use crate::media::ImageReference;
+use crate::user::User;
+use crate::utils::api_error::ApiError;
+use crate::utils::media_store::MediaStore;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UpdateAvatarForm {
+    #[serde(default)]
+    avatar_url: String,
+    avatar_media_id: Option<i32>,
+}
+
+//a url or the media_id of an uploaded image. an empty avatar_url with no media_id removes the avatar
+#[post("/updateavatar")]
+pub async fn handler(
+    update_form: web::Json<UpdateAvatarForm>,
+    db_pool: web::Data<PgPool>,
+    media_store: web::Data<dyn MediaStore>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let formatted_avatar_url = match update_form.avatar_url.trim() {
+        "" => None,
+        avatar_url if avatar_url.len() > 255 => {
+            return Err(ApiError::invalid_field(
+                "avatar_url",
+                "Avatar urls must be no longer than 255 characters.",
+            ))
+        }
+        avatar_url => Some(avatar_url.to_string()),
+    };
+    let new_avatar = ImageReference::resolve(
+        &update_form.avatar_media_id,
+        formatted_avatar_url,
+        &user.user_id,
+        &**media_store,
+        db_pool.get_ref(),
+    )
+    .await?;
+    //update avatar
+    let mut tx = db_pool.begin().await?;
+    User::update_avatar(&new_avatar, &user.user_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Avatar updated successfully."))
+}
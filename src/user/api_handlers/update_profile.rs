@@ -0,0 +1,21 @@
+use crate::user::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use sqlx::PgPool;
+
+//sets both fields, eg {"display_name": "Alice", "bio": "..."}
+#[post("/updateprofile")]
+pub async fn handler(
+    profile_form: web::Json<UserProfileForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_user(&session, db_pool.get_ref()).await?;
+    let profile_form = profile_form.validate()?;
+    let mut tx = db_pool.begin().await?;
+    User::update_profile(&profile_form, &user.user_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Profile updated successfully."))
+}
@@ -1 +1,65 @@
+use crate::user::User;
+use crate::user_registration::UserRegistration;
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use actix_session::Session;
+use actix_web::{post, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UpdateUsernameForm {
+    new_username: String,
+}
+
+//once per cooldown. the old username is held for a while so nobody can pick it up and pose as the user
+#[post("/updateusername")]
+pub async fn handler(
+    update_form: web::Json<UpdateUsernameForm>,
+    db_pool: web::Data<PgPool>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let user = session_validation::policy_cookie_user(&session, db_pool.get_ref()).await?;
+    let new_username = update_form.new_username.trim().to_lowercase();
+    if !User::is_valid_username(&new_username) {
+        return Err(ApiError::invalid_field(
+            "new_username",
+            "Usernames can only contain alphanumeric characters, and must be no longer than 15 characters",
+        ));
+    }
+    if new_username == user.username {
+        return Err(ApiError::invalid_field(
+            "new_username",
+            "That is already your username.",
+        ));
+    }
+    if let Some(wait) = User::username_change_wait(&user.user_id, db_pool.get_ref()).await? {
+        return Err(ApiError::rate_limited(
+            "You can only change your username once every 30 days.",
+            wait,
+        ));
+    }
+    //taken, waiting on a registration, or recently given up by someone else
+    if User::find_by_username(&new_username, db_pool.get_ref())
+        .await?
+        .is_some()
+        || UserRegistration::find_by_username(&new_username, db_pool.get_ref())
+            .await?
+            .is_some()
+    {
+        return Err(ApiError::conflicting_field(
+            "new_username",
+            "User with that username already exists",
+        ));
+    }
+    if User::is_username_held(&new_username, Some(user.user_id), db_pool.get_ref()).await? {
+        return Err(ApiError::conflicting_field(
+            "new_username",
+            "That username was recently used by someone else.",
+        ));
+    }
+    let mut tx = db_pool.begin().await?;
+    User::update_username(new_username, &user.user_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("Username updated successfully."))
+}
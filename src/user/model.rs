@@ -1,9 +1,74 @@
+use crate::media::ImageReference;
+use crate::moderation_action::format_reason;
+use crate::utils::api_error::ApiError;
 use anyhow::Result;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use sqlx::Postgres;
 use sqlx::Transaction;
+use std::time::Duration;
+
+//users can change their username once per cooldown, and nobody else can take the old one until the hold is up
+const USERNAME_CHANGE_COOLDOWN_DAYS: i32 = 30;
+const USERNAME_HOLD_DAYS: i32 = 90;
+
+//optional json body for the ban routes, eg {"reason": "spam", "message": "see you next week", "duration_hours": 168}.
+//no duration is a permanent ban
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BanForm {
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    pub duration_hours: Option<i32>,
+}
+
+impl BanForm {
+    //the body is optional, but one that's there has to parse. an empty body is a permanent ban with no details
+    pub fn from_body(body: &[u8]) -> Result<BanForm, ApiError> {
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return BanForm::default().validate();
+        }
+        serde_json::from_slice::<BanForm>(body)
+            .map_err(|err| ApiError::validation(format!("Invalid request body: {}", err)))?
+            .validate()
+    }
+    //blank reasons and messages are dropped like in format_reason, durations are capped at ten years
+    pub fn validate(&self) -> Result<BanForm, ApiError> {
+        let message = match self.message.as_deref().map(str::trim) {
+            Some(message) if message.len() > 1000 => {
+                return Err(ApiError::invalid_field(
+                    "message",
+                    "Ban messages must be no longer than 1000 characters.",
+                ))
+            }
+            Some(message) if !message.is_empty() => Some(message.to_string()),
+            _ => None,
+        };
+        if let Some(duration_hours) = self.duration_hours {
+            if !(1..=24 * 365 * 10).contains(&duration_hours) {
+                return Err(ApiError::invalid_field(
+                    "duration_hours",
+                    "Ban durations must be between 1 hour and 10 years.",
+                ));
+            }
+        }
+        Ok(BanForm {
+            reason: format_reason(&self.reason)?,
+            message,
+            duration_hours: self.duration_hours,
+        })
+    }
+}
+
+//what a banned user is told when they're turned away. expires_at is None for permanent bans
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BanDetails {
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    pub banned_at: Option<String>,
+    pub expires_at: Option<String>,
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct User {
@@ -15,6 +80,7 @@ pub struct User {
     pub is_admin: bool,
     pub is_verified: bool,
     pub is_banned: bool,
+    pub totp_enabled: bool,
     pub created_at: String, //convert time to string
 }
 
@@ -33,6 +99,8 @@ pub struct UserViewPersonal {
     pub email: String,
     pub username: String,
     pub avatar_url: Option<String>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
     pub is_admin: bool,
     pub is_verified: bool,
     pub is_banned: bool,
@@ -46,6 +114,39 @@ pub struct UserCreateForm {
     pub password_hash: String,
 }
 
+//blank fields clear them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserProfileForm {
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+}
+
+impl UserProfileForm {
+    pub fn validate(&self) -> Result<UserProfileForm, ApiError> {
+        let display_name = match self.display_name.as_deref().map(str::trim) {
+            Some(display_name) if display_name.chars().count() > 50 => {
+                return Err(ApiError::invalid_field(
+                    "display_name",
+                    "Display names must be no longer than 50 characters.",
+                ))
+            }
+            Some(display_name) if !display_name.is_empty() => Some(display_name.to_string()),
+            _ => None,
+        };
+        let bio = match self.bio.as_deref().map(str::trim) {
+            Some(bio) if bio.chars().count() > 1000 => {
+                return Err(ApiError::invalid_field(
+                    "bio",
+                    "Bios must be no longer than 1000 characters.",
+                ))
+            }
+            Some(bio) if !bio.is_empty() => Some(bio.to_string()),
+            _ => None,
+        };
+        Ok(UserProfileForm { display_name, bio })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserLoginForm {
     pub username: String,
@@ -53,6 +154,17 @@ pub struct UserLoginForm {
 }
 
 impl User {
+    //same rules as registration: alphanumeric, no longer than 15 characters
+    pub fn is_valid_username(username: &str) -> bool {
+        !username.is_empty() && username.len() <= 15 && username.chars().all(char::is_alphanumeric)
+    }
+    //for logins with a username that doesn't exist, so they take as long as a wrong password. always false
+    pub fn verify_dummy_password(password_input: &String) -> bool {
+        static DUMMY_PASSWORD_HASH: Lazy<String> =
+            Lazy::new(|| hash("not a real password", DEFAULT_COST).unwrap_or_default());
+        verify(password_input, &DUMMY_PASSWORD_HASH).unwrap_or(false);
+        false
+    }
     pub async fn verify_password(user: &User, password_input: &String) -> Result<bool> {
         let valid = verify(password_input, &user.password_hash).unwrap_or(false);
         Ok(valid)
@@ -97,6 +209,7 @@ impl User {
             is_admin: user.is_admin,
             is_verified: user.is_verified,
             is_banned: user.is_banned,
+            totp_enabled: user.totp_enabled,
             created_at: user.created_at.to_string(),
         }))
     }
@@ -139,6 +252,7 @@ impl User {
             is_admin: user.is_admin,
             is_verified: user.is_verified,
             is_banned: user.is_banned,
+            totp_enabled: user.totp_enabled,
             created_at: user.created_at.to_string(),
         }))
     }
@@ -171,20 +285,92 @@ impl User {
             created_at: user.created_at.to_string(),
         }))
     }
+    //keeps the old username in username_history and starts the cooldown
     pub async fn update_username(
         new_username: String,
         user_id: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO username_history (user_id, username)
+            SELECT user_id, username FROM users
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
         sqlx::query!(
             r#"
             UPDATE users
-            SET username = $2
+            SET username = $2, username_changed_at = LOCALTIMESTAMP
             WHERE user_id = $1
             "#,
             user_id,
             new_username
         )
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(())
+    }
+
+    //how long until the user can change their username again, None if they can now
+    pub async fn username_change_wait(user_id: &i32, pool: &PgPool) -> Result<Option<Duration>> {
+        let wait = sqlx::query!(
+            r#"
+            SELECT EXTRACT(EPOCH FROM username_changed_at + make_interval(days => $2) - LOCALTIMESTAMP)::FLOAT8 AS "wait!"
+            FROM users
+            WHERE user_id = $1 AND username_changed_at + make_interval(days => $2) > LOCALTIMESTAMP
+            "#,
+            user_id,
+            USERNAME_CHANGE_COOLDOWN_DAYS
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(wait.map(|wait| Duration::from_secs_f64(wait.wait.max(0.0))))
+    }
+
+    //whether someone gave up the username recently. a user's own old usernames are never held from them
+    pub async fn is_username_held(
+        username: &String,
+        user_id: Option<i32>,
+        pool: &PgPool,
+    ) -> Result<bool> {
+        let held = sqlx::query!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM username_history
+                WHERE username = $1
+                AND changed_at > LOCALTIMESTAMP - make_interval(days => $3)
+                AND ($2::INTEGER IS NULL OR user_id <> $2)
+            ) AS "held!"
+            "#,
+            username,
+            user_id,
+            USERNAME_HOLD_DAYS
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(held.held)
+    }
+
+    pub async fn update_profile(
+        profile_form: &UserProfileForm,
+        user_id: &i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET display_name = $2, bio = $3
+            WHERE user_id = $1
+            "#,
+            user_id,
+            profile_form.display_name,
+            profile_form.bio
+        )
         .execute(tx)
         .await?;
 
@@ -232,19 +418,21 @@ impl User {
         Ok(())
     }
 
-    pub async fn update_avatar_url(
-        new_avatar_url: String,
+    //from a url or an uploaded image, no url clears it
+    pub async fn update_avatar(
+        new_avatar: &ImageReference,
         user_id: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
         sqlx::query!(
             r#"
             UPDATE users
-            SET avatar_url = $2
+            SET avatar_url = $2, avatar_media_id = $3
             WHERE user_id = $1
             "#,
             user_id,
-            new_avatar_url
+            new_avatar.url.as_deref(),
+            new_avatar.media_id
         )
         .execute(tx)
         .await?;
@@ -292,19 +480,40 @@ impl User {
         Ok(())
     }
 
-    pub async fn update_banned_status(
-        new_banned_status: bool,
+    //site ban. banned_by is the admin who issued it, no duration_hours means permanent
+    pub async fn ban(
         user_id: &i32,
+        ban: &BanForm,
+        banned_by: &i32,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<()> {
         sqlx::query!(
             r#"
             UPDATE users
-            SET is_banned = $2
+            SET is_banned = TRUE, ban_reason = $2, ban_message = $3, banned_by = $4, banned_at = LOCALTIMESTAMP,
+            ban_expires_at = LOCALTIMESTAMP + $5::INTEGER * INTERVAL '1 hour'
             WHERE user_id = $1
             "#,
             user_id,
-            new_banned_status
+            ban.reason,
+            ban.message,
+            banned_by,
+            ban.duration_hours
+        )
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unban(user_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET is_banned = FALSE, ban_reason = NULL, ban_message = NULL, banned_by = NULL, banned_at = NULL, ban_expires_at = NULL
+            WHERE user_id = $1
+            "#,
+            user_id
         )
         .execute(tx)
         .await?;
@@ -312,6 +521,40 @@ impl User {
         Ok(())
     }
 
+    //the user's site ban if it's still in effect. a ban past its expiry counts as lifted even before lift_expired_bans clears it
+    pub async fn find_active_ban(user_id: &i32, pool: &PgPool) -> Result<Option<BanDetails>> {
+        let ban = sqlx::query!(
+            r#"
+            SELECT ban_reason, ban_message, banned_at, ban_expires_at FROM users
+            WHERE user_id = $1 AND is_banned
+            AND (ban_expires_at IS NULL OR ban_expires_at > LOCALTIMESTAMP)
+            "#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(ban.map(|ban| BanDetails {
+            reason: ban.ban_reason,
+            message: ban.ban_message,
+            banned_at: ban.banned_at.map(|b| b.to_string()),
+            expires_at: ban.ban_expires_at.map(|e| e.to_string()),
+        }))
+    }
+
+    //returns how many bans were lifted
+    pub async fn lift_expired_bans(pool: &PgPool) -> Result<u64> {
+        let lifted = sqlx::query!(
+            r#"
+            UPDATE users
+            SET is_banned = FALSE, ban_reason = NULL, ban_message = NULL, banned_by = NULL, banned_at = NULL, ban_expires_at = NULL
+            WHERE is_banned AND ban_expires_at IS NOT NULL AND ban_expires_at <= LOCALTIMESTAMP
+            "#
+        )
+        .execute(pool)
+        .await?;
+        Ok(lifted.rows_affected())
+    }
+
     pub async fn delete(user_id: &i32, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
         sqlx::query!(
             r#"
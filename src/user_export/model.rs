@@ -0,0 +1,309 @@
+use crate::user::UserViewPersonal;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+//everything we have that belongs to a user, for /user/export
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserExport {
+    pub exported_at: String, //convert time to string
+    pub profile: UserViewPersonal,
+    pub previous_usernames: Vec<String>,
+    pub posts: Vec<ExportedPost>,
+    pub comments: Vec<ExportedComment>,
+    pub post_votes: Vec<ExportedPostVote>,
+    pub comment_votes: Vec<ExportedCommentVote>,
+    pub memberships: Vec<ExportedMembership>,
+    pub blocked_usernames: Vec<String>,
+    pub bookmarks: Vec<ExportedBookmark>,
+    pub notifications: Vec<ExportedNotification>,
+    pub media: Vec<ExportedMedia>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedPost {
+    pub post_id: i32,
+    pub guild_tag: String,
+    pub title: String,
+    pub body: Option<String>,
+    pub link_url: Option<String>,
+    pub image_url: Option<String>,
+    pub is_edited: bool,
+    pub created_at: String, //convert time to string
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedComment {
+    pub comment_id: i32,
+    pub post_id: i32,
+    pub parent_comment_id: Option<i32>,
+    pub body: String,
+    pub is_edited: bool,
+    pub created_at: String, //convert time to string
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedPostVote {
+    pub post_id: i32,
+    pub up: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedCommentVote {
+    pub comment_id: i32,
+    pub up: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedMembership {
+    pub guild_tag: String,
+    pub is_admin: bool,
+    pub is_moderator: bool,
+    pub is_banned: bool,
+    pub notification_preference: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedBookmark {
+    pub post_id: i32,
+    pub created_at: String, //convert time to string
+}
+
+//post_id for post notifications, comment_id for comment notifications
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedNotification {
+    pub notification_type: String,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub is_read: bool,
+    pub created_at: String, //convert time to string
+}
+
+//the urls of the files are at /media/{media_id}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedMedia {
+    pub media_id: i32,
+    pub content_type: String,
+    pub byte_size: i32,
+    pub created_at: String, //convert time to string
+}
+
+impl UserExport {
+    pub async fn build(user_id: &i32, pool: &PgPool) -> Result<UserExport> {
+        let profile = sqlx::query!(
+            r#"
+            SELECT email, username, avatar_url, display_name, bio, is_admin, is_verified, is_banned, created_at, LOCALTIMESTAMP AS "exported_at!" FROM users
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow!("user {} does not exist", user_id))?;
+        let previous_usernames = sqlx::query!(
+            r#"
+            SELECT username FROM username_history
+            WHERE user_id = $1
+            ORDER BY changed_at, history_id
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|history| history.username)
+        .collect();
+        let posts = sqlx::query!(
+            r#"
+            SELECT posts.post_id, guilds.guild_tag, posts.title, posts.body, posts.link_url, posts.image_url, posts.is_edited, posts.created_at
+            FROM posts
+            INNER JOIN guilds ON guilds.guild_id = posts.guild_id
+            WHERE posts.user_id = $1
+            ORDER BY posts.created_at, posts.post_id
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|post| ExportedPost {
+            post_id: post.post_id,
+            guild_tag: post.guild_tag,
+            title: post.title,
+            body: post.body,
+            link_url: post.link_url,
+            image_url: post.image_url,
+            is_edited: post.is_edited,
+            created_at: post.created_at.to_string(),
+        })
+        .collect();
+        let comments = sqlx::query!(
+            r#"
+            SELECT comment_id, post_id, parent_comment_id, body, is_edited, created_at FROM comments
+            WHERE user_id = $1
+            ORDER BY created_at, comment_id
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|comment| ExportedComment {
+            comment_id: comment.comment_id,
+            post_id: comment.post_id,
+            parent_comment_id: comment.parent_comment_id,
+            body: comment.body,
+            is_edited: comment.is_edited,
+            created_at: comment.created_at.to_string(),
+        })
+        .collect();
+        let post_votes = sqlx::query!(
+            r#"
+            SELECT post_id, up FROM post_votes
+            WHERE user_id = $1
+            ORDER BY post_id
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|vote| ExportedPostVote {
+            post_id: vote.post_id,
+            up: vote.up,
+        })
+        .collect();
+        let comment_votes = sqlx::query!(
+            r#"
+            SELECT comment_id, up FROM comment_votes
+            WHERE user_id = $1
+            ORDER BY comment_id
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|vote| ExportedCommentVote {
+            comment_id: vote.comment_id,
+            up: vote.up,
+        })
+        .collect();
+        let memberships = sqlx::query!(
+            r#"
+            SELECT guilds.guild_tag, guild_memberships.is_admin, guild_memberships.is_moderator, guild_memberships.is_banned, guild_memberships.notification_preference
+            FROM guild_memberships
+            INNER JOIN guilds ON guilds.guild_id = guild_memberships.guild_id
+            WHERE guild_memberships.user_id = $1
+            ORDER BY guild_memberships.membership_id
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|membership| ExportedMembership {
+            guild_tag: membership.guild_tag,
+            is_admin: membership.is_admin,
+            is_moderator: membership.is_moderator,
+            is_banned: membership.is_banned,
+            notification_preference: membership.notification_preference,
+        })
+        .collect();
+        let blocked_usernames = sqlx::query!(
+            r#"
+            SELECT blocked_user_username FROM blocks
+            WHERE user_id = $1
+            ORDER BY blocked_user_username
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|block| block.blocked_user_username)
+        .collect();
+        let bookmarks = sqlx::query!(
+            r#"
+            SELECT post_id, created_at FROM bookmarks
+            WHERE user_id = $1
+            ORDER BY created_at, bookmark_id
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|bookmark| ExportedBookmark {
+            post_id: bookmark.post_id,
+            created_at: bookmark.created_at.to_string(),
+        })
+        .collect();
+        let notifications = sqlx::query!(
+            r#"
+            SELECT notification_type AS "notification_type!", post_id, comment_id, is_read AS "is_read!", created_at AS "created_at!" FROM (
+                SELECT notification_type, post_id, NULL::INTEGER AS comment_id, is_read, created_at FROM post_notifications
+                WHERE user_id = $1
+                UNION ALL
+                SELECT notification_type, NULL::INTEGER AS post_id, comment_id, is_read, created_at FROM comment_notifications
+                WHERE user_id = $1
+            ) notifications
+            ORDER BY created_at
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|notification| ExportedNotification {
+            notification_type: notification.notification_type,
+            post_id: notification.post_id,
+            comment_id: notification.comment_id,
+            is_read: notification.is_read,
+            created_at: notification.created_at.to_string(),
+        })
+        .collect();
+        let media = sqlx::query!(
+            r#"
+            SELECT media_id, content_type, byte_size, created_at FROM media
+            WHERE user_id = $1
+            ORDER BY created_at, media_id
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|media| ExportedMedia {
+            media_id: media.media_id,
+            content_type: media.content_type,
+            byte_size: media.byte_size,
+            created_at: media.created_at.to_string(),
+        })
+        .collect();
+        Ok(UserExport {
+            exported_at: profile.exported_at.to_string(),
+            profile: UserViewPersonal {
+                email: profile.email,
+                username: profile.username,
+                avatar_url: profile.avatar_url,
+                display_name: profile.display_name,
+                bio: profile.bio,
+                is_admin: profile.is_admin,
+                is_verified: profile.is_verified,
+                is_banned: profile.is_banned,
+                created_at: profile.created_at.to_string(),
+            },
+            previous_usernames,
+            posts,
+            comments,
+            post_votes,
+            comment_votes,
+            memberships,
+            blocked_usernames,
+            bookmarks,
+            notifications,
+            media,
+        })
+    }
+}
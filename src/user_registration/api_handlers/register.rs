@@ -1,16 +1,22 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpResponse};
 use sqlx::PgPool;
 
 use crate::user::User;
 use crate::user_registration::*;
+use crate::utils::api_error::ApiError;
+use crate::utils::mailer::{MailTemplate, Mailer};
 
 pub async fn handler(
     registration_form: web::Json<UserRegistrationForm>,
     db_pool: web::Data<PgPool>,
-) -> impl Responder {
+    mailer: web::Data<dyn Mailer>,
+) -> Result<HttpResponse, ApiError> {
     //make sure passwords match
     if registration_form.password != registration_form.confirm_password {
-        return HttpResponse::InternalServerError().body("passwords do not match");
+        return Err(ApiError::invalid_field(
+            "confirm_password",
+            "Passwords do not match.",
+        ));
     }
 
     //validate username, must be all alphanumeric, 15 chars max
@@ -24,8 +30,10 @@ pub async fn handler(
     }
 
     if !valid_username {
-        return HttpResponse::NotAcceptable()
-                .body("Usernames can only contain alphanumeric characters, and must be no longer than 15 characters");
+        return Err(ApiError::invalid_field(
+            "username",
+            "Usernames can only contain alphanumeric characters, and must be no longer than 15 characters",
+        ));
     }
 
     //TODO: Validate email (proabbly use regex...)
@@ -39,95 +47,66 @@ pub async fn handler(
         confirm_password: cloned_form.confirm_password,
     };
 
-    //check for existing usernames, emailscontains(char::is_alphanumeric);
-
-    let existing_username =
-        User::find_by_username(&formatted_form.username, db_pool.get_ref()).await;
-    match existing_username {
-        Ok(Some(user)) => {
-            return HttpResponse::InternalServerError()
-                .body("Error registering user: User with that username already exists");
-        }
-        Err(err) => {
-            error!("Error registering user: {}", err);
-            return HttpResponse::InternalServerError().body("Error registering user");
-        }
-        _ => (),
+    //check for existing usernames and emails, registered or still waiting on confirmation
+    if User::find_by_username(&formatted_form.username, db_pool.get_ref())
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::conflicting_field(
+            "username",
+            "User with that username already exists",
+        ));
     }
-
-    let existing_username_registration =
-        UserRegistration::find_by_username(&formatted_form.username, db_pool.get_ref()).await;
-    match existing_username_registration {
-        Ok(Some(user_registration)) => {
-            return HttpResponse::InternalServerError()
-                .body("Error registering user: That username has already been registered");
-        }
-        Err(err) => {
-            error!("Error registering user: {}", err);
-            return HttpResponse::InternalServerError().body("Error registering user");
-        }
-        _ => (),
+    if UserRegistration::find_by_username(&formatted_form.username, db_pool.get_ref())
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::conflicting_field(
+            "username",
+            "That username has already been registered",
+        ));
     }
-
-    let existing_email = User::find_by_email(&formatted_form.email, db_pool.get_ref()).await;
-    match existing_email {
-        Ok(Some(user)) => {
-            return HttpResponse::InternalServerError()
-                .body("Error registering user: User with that email already exists");
-        }
-        Err(err) => {
-            error!("Error registering user: {}", err);
-            return HttpResponse::InternalServerError().body("Error registering user");
-        }
-        _ => (),
+    if User::is_username_held(&formatted_form.username, None, db_pool.get_ref()).await? {
+        return Err(ApiError::conflicting_field(
+            "username",
+            "That username was recently used by someone else.",
+        ));
     }
-
-    let existing_email_registration =
-        UserRegistration::find_by_email(&formatted_form.email, db_pool.get_ref()).await;
-    match existing_email_registration {
-        Ok(Some(user_registration)) => {
-            return HttpResponse::InternalServerError()
-                .body("Error registering user: That email has already been registered");
-        }
-        Err(err) => {
-            error!("Error registering user: {}", err);
-            return HttpResponse::InternalServerError().body("Error registering user");
-        }
-        _ => (),
+    if User::find_by_email(&formatted_form.email, db_pool.get_ref())
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::conflicting_field(
+            "email",
+            "User with that email already exists",
+        ));
+    }
+    if UserRegistration::find_by_email(&formatted_form.email, db_pool.get_ref())
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::conflicting_field(
+            "email",
+            "That email has already been registered",
+        ));
     }
 
     //TODO: validate email address
 
     //email and username are open, begin tx
-
-    let mut tx = db_pool.begin().await.unwrap();
-
-    //username is free, register user
-    let registration = UserRegistration::create(&formatted_form, &mut tx).await;
-    match registration {
-        Ok(registration_details) => {
-            //TODO: IMPORT MAILER CRATE, EMAIL HASH TO THE EMAIL
-            let succesful_commit = tx.commit().await;
-            match succesful_commit {
-                Ok(()) => (),
-                Err(err) => {
-                    error!("Error committing transaction: {}", err);
-                    return HttpResponse::InternalServerError().body("Unknown Error.");
-                }
-            }
-            HttpResponse::Ok().json(registration_details)
-        }
-        Err(err) => {
-            let succesful_rollback = tx.rollback().await;
-            match succesful_rollback {
-                Ok(()) => (),
-                Err(err) => {
-                    error!("Error rolling back transaction: {}", err);
-                    return HttpResponse::InternalServerError().body("Unknown Error.");
-                }
-            }
-            error!("Error registering user: {}", err);
-            HttpResponse::InternalServerError().body("Error registering user")
-        }
-    }
+    let mut tx = db_pool.begin().await?;
+    let registration_details = UserRegistration::create(&formatted_form, &mut tx).await?;
+    //email the confirmation link, the hash is never sent back in the response
+    mailer
+        .send_template(
+            MailTemplate::RegistrationConfirmation,
+            &registration_details.email,
+            &[
+                ("username", &formatted_form.username),
+                ("registration_hash", &registration_details.registration_hash),
+            ],
+        )
+        .await?;
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().body("A link to confirm your registration has been sent to your email."))
 }
@@ -7,7 +7,11 @@ use uuid::Uuid;
 pub struct UserSession {
     pub session_id: String,
     pub user_id: i32,
-    pub created_at: String, //convert time to string
+    pub device_id: i32,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: String,   //convert time to string
+    pub last_seen_at: String, //convert time to string
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -16,20 +20,48 @@ pub struct UserSessionView {
     pub user_id: i32,
 }
 
+//a session as its owner sees it on the "manage my devices" page, never includes the session_id itself
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeviceSessionView {
+    pub device_id: i32,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: String,
+    pub last_seen_at: String,
+    pub is_current: bool,
+}
+
+impl DeviceSessionView {
+    pub fn from_session(session: UserSession, current_session_id: &Option<String>) -> Self {
+        DeviceSessionView {
+            is_current: current_session_id.as_ref() == Some(&session.session_id),
+            device_id: session.device_id,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+        }
+    }
+}
+
 impl UserSession {
     pub async fn create(
         user_id: i32,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<UserSessionView> {
         let session_id = Uuid::new_v4().to_string();
         //insert session into table
         sqlx::query!(
             r#"
-                INSERT INTO user_sessions (session_id, user_id)
-                VALUES ($1, $2)
+                INSERT INTO user_sessions (session_id, user_id, user_agent, ip_address)
+                VALUES ($1, $2, $3, $4)
             "#,
             &session_id,
             &user_id,
+            user_agent.map(|agent| agent.chars().take(512).collect::<String>()),
+            ip_address.map(|ip| ip.chars().take(64).collect::<String>()),
         )
         .execute(tx)
         .await?;
@@ -58,17 +90,72 @@ impl UserSession {
         Ok(session.map(|session| UserSession {
             session_id: session.session_id,
             user_id: session.user_id,
+            device_id: session.device_id,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
             created_at: session.created_at.to_string(),
+            last_seen_at: session.last_seen_at.to_string(),
         }))
     }
-    pub async fn find_sessions_by_user_id(user_id: i32, pool: &PgPool) -> Result<Vec<UserSession>> {
+    //only returns the session if it is inside both timeouts. last_seen_at is bumped at most once a minute
+    //so a burst of requests doesn't turn into a burst of writes
+    pub async fn find_active_by_session_id(
+        session_id: &String,
+        idle_timeout_minutes: &i32,
+        absolute_timeout_hours: &i32,
+        pool: &PgPool,
+    ) -> Result<Option<UserSession>> {
+        let session = sqlx::query!(
+            r#"
+            WITH active AS (
+                SELECT * FROM user_sessions
+                WHERE session_id = $1
+                AND last_seen_at > LOCALTIMESTAMP - make_interval(mins => $2)
+                AND created_at > LOCALTIMESTAMP - make_interval(hours => $3)
+            ), touched AS (
+                UPDATE user_sessions
+                SET last_seen_at = LOCALTIMESTAMP
+                WHERE session_id IN (SELECT session_id FROM active)
+                AND last_seen_at < LOCALTIMESTAMP - INTERVAL '1 minute'
+            )
+            SELECT session_id AS "session_id!", user_id AS "user_id!", device_id AS "device_id!", user_agent, ip_address, created_at AS "created_at!", last_seen_at AS "last_seen_at!"
+            FROM active
+            "#,
+            session_id,
+            idle_timeout_minutes,
+            absolute_timeout_hours
+        )
+        .fetch_optional(&*pool)
+        .await?;
+        Ok(session.map(|session| UserSession {
+            session_id: session.session_id,
+            user_id: session.user_id,
+            device_id: session.device_id,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at.to_string(),
+            last_seen_at: session.last_seen_at.to_string(),
+        }))
+    }
+    //active sessions only, most recently used first
+    pub async fn find_sessions_by_user_id(
+        user_id: &i32,
+        idle_timeout_minutes: &i32,
+        absolute_timeout_hours: &i32,
+        pool: &PgPool,
+    ) -> Result<Vec<UserSession>> {
         let sessions = sqlx::query!(
             r#"
             SELECT *
             FROM user_sessions
             WHERE user_id = $1
+            AND last_seen_at > LOCALTIMESTAMP - make_interval(mins => $2)
+            AND created_at > LOCALTIMESTAMP - make_interval(hours => $3)
+            ORDER BY last_seen_at DESC
             "#,
-            user_id
+            user_id,
+            idle_timeout_minutes,
+            absolute_timeout_hours
         )
         .fetch_all(pool)
         .await?
@@ -76,7 +163,11 @@ impl UserSession {
         .map(|session| UserSession {
             session_id: session.session_id,
             user_id: session.user_id,
+            device_id: session.device_id,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
             created_at: session.created_at.to_string(),
+            last_seen_at: session.last_seen_at.to_string(),
         })
         .collect();
 
@@ -94,4 +185,61 @@ impl UserSession {
         .await?;
         Ok(())
     }
+    //scoped to the user so one user can't revoke another's device by guessing ids
+    pub async fn delete_by_device_id(
+        device_id: &i32,
+        user_id: &i32,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<String>> {
+        let deleted = sqlx::query!(
+            r#"
+            DELETE FROM user_sessions
+            WHERE device_id = $1 AND user_id = $2
+            RETURNING session_id
+            "#,
+            device_id,
+            user_id
+        )
+        .fetch_optional(tx)
+        .await?;
+        Ok(deleted.map(|deleted| deleted.session_id))
+    }
+    //log out everywhere, optionally keeping the session making the request
+    pub async fn delete_all_by_user_id(
+        user_id: &i32,
+        keep_session_id: Option<&str>,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<u64> {
+        let deleted = sqlx::query!(
+            r#"
+            DELETE FROM user_sessions
+            WHERE user_id = $1
+            AND ($2::VARCHAR IS NULL OR session_id <> $2)
+            "#,
+            user_id,
+            keep_session_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(deleted.rows_affected())
+    }
+    //returns how many sessions were removed
+    pub async fn purge_expired(
+        idle_timeout_minutes: &i32,
+        absolute_timeout_hours: &i32,
+        pool: &PgPool,
+    ) -> Result<u64> {
+        let purged = sqlx::query!(
+            r#"
+            DELETE FROM user_sessions
+            WHERE last_seen_at <= LOCALTIMESTAMP - make_interval(mins => $1)
+            OR created_at <= LOCALTIMESTAMP - make_interval(hours => $2)
+            "#,
+            idle_timeout_minutes,
+            absolute_timeout_hours
+        )
+        .execute(pool)
+        .await?;
+        Ok(purged.rows_affected())
+    }
 }
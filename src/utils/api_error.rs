@@ -0,0 +1,210 @@
+use crate::user::BanDetails;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::anyhow;
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+//every error a handler returns goes out as {code, message, field?, ban?}, so clients can branch on code instead of matching text
+#[derive(Debug)]
+pub enum ApiError {
+    //bad input, field names the form field at fault when there is one
+    Validation {
+        message: String,
+        field: Option<String>,
+    },
+    //not logged in, or wrong credentials
+    Unauthorized(String),
+    Forbidden(String),
+    //forbidden because of a site or guild ban, the body says why and until when
+    Banned {
+        message: String,
+        ban: BanDetails,
+    },
+    NotFound(String),
+    //the request clashes with data that already exists, eg a taken username
+    Conflict {
+        message: String,
+        field: Option<String>,
+    },
+    //too many requests, retry_after is rounded up to whole seconds for the Retry-After header
+    RateLimited {
+        message: String,
+        retry_after: u64,
+    },
+    //anything unexpected. the cause is logged, the client only gets a generic message
+    Internal(anyhow::Error),
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ban: Option<&'a BanDetails>,
+}
+
+impl ApiError {
+    pub fn validation<M: Into<String>>(message: M) -> ApiError {
+        ApiError::Validation {
+            message: message.into(),
+            field: None,
+        }
+    }
+    pub fn invalid_field<M: Into<String>>(field: &str, message: M) -> ApiError {
+        ApiError::Validation {
+            message: message.into(),
+            field: Some(field.to_string()),
+        }
+    }
+    pub fn unauthorized<M: Into<String>>(message: M) -> ApiError {
+        ApiError::Unauthorized(message.into())
+    }
+    pub fn forbidden<M: Into<String>>(message: M) -> ApiError {
+        ApiError::Forbidden(message.into())
+    }
+    pub fn banned<M: Into<String>>(message: M, ban: BanDetails) -> ApiError {
+        ApiError::Banned {
+            message: message.into(),
+            ban,
+        }
+    }
+    pub fn not_found<M: Into<String>>(message: M) -> ApiError {
+        ApiError::NotFound(message.into())
+    }
+    pub fn conflict<M: Into<String>>(message: M) -> ApiError {
+        ApiError::Conflict {
+            message: message.into(),
+            field: None,
+        }
+    }
+    pub fn conflicting_field<M: Into<String>>(field: &str, message: M) -> ApiError {
+        ApiError::Conflict {
+            message: message.into(),
+            field: Some(field.to_string()),
+        }
+    }
+    pub fn rate_limited<M: Into<String>>(message: M, wait: Duration) -> ApiError {
+        ApiError::RateLimited {
+            message: message.into(),
+            retry_after: wait.as_secs_f64().ceil().max(1.0) as u64,
+        }
+    }
+    //for failures that don't come with an error value of their own
+    pub fn internal<M: fmt::Display>(message: M) -> ApiError {
+        ApiError::Internal(anyhow!(message.to_string()))
+    }
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Validation { .. } => "validation_error",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::Banned { .. } => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict { .. } => "conflict",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+    fn message(&self) -> &str {
+        match self {
+            ApiError::Validation { message, .. } => message,
+            ApiError::Unauthorized(message) => message,
+            ApiError::Forbidden(message) => message,
+            ApiError::Banned { message, .. } => message,
+            ApiError::NotFound(message) => message,
+            ApiError::Conflict { message, .. } => message,
+            ApiError::RateLimited { message, .. } => message,
+            ApiError::Internal(_) => "Internal server error.",
+        }
+    }
+    fn field(&self) -> Option<&str> {
+        match self {
+            ApiError::Validation { field, .. } => field.as_deref(),
+            ApiError::Conflict { field, .. } => field.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Internal(err) => write!(f, "{}: {}", self.code(), err),
+            _ => write!(f, "{}: {}", self.code(), self.message()),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::Validation { .. } => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Banned { .. } => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict { .. } => StatusCode::CONFLICT,
+            ApiError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+    fn error_response(&self) -> HttpResponse {
+        if let ApiError::Internal(err) = self {
+            error!("Internal error: {:#}", err);
+        }
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::RateLimited { retry_after, .. } = self {
+            response.header("Retry-After", retry_after.to_string());
+        }
+        response.json(ErrorBody {
+            code: self.code(),
+            message: self.message(),
+            field: self.field(),
+            ban: match self {
+                ApiError::Banned { ban, .. } => Some(ban),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+//lets handlers use ? on model calls, which all return anyhow::Result. a model can return an ApiError through
+//anyhow when the client is at fault (eg a bad cursor), everything else is internal
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> ApiError {
+        match err.downcast::<ApiError>() {
+            Ok(api_error) => api_error,
+            Err(err) => ApiError::Internal(err),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> ApiError {
+        ApiError::Internal(err.into())
+    }
+}
+
+//malformed json bodies, path segments and query strings get the same error shape as everything else
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _| {
+        ApiError::validation(format!("Invalid request body: {}", err)).into()
+    })
+}
+
+pub fn path_config() -> web::PathConfig {
+    web::PathConfig::default()
+        .error_handler(|err, _| ApiError::validation(format!("Invalid path: {}", err)).into())
+}
+
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|err, _| {
+        ApiError::validation(format!("Invalid query string: {}", err)).into()
+    })
+}
@@ -0,0 +1,105 @@
+use crate::api_token::ApiToken;
+use crate::utils::api_error::ApiError;
+use actix_session::Session;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method};
+use actix_web::{web, Error};
+use anyhow::anyhow;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use sqlx::PgPool;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+//where the grant for a bearer token is put in the session, session_validation reads it from there
+pub const API_TOKEN_GRANT_KEY: &str = "api_token_grant";
+
+//lets Authorization: Bearer <token> stand in for the session cookie. a valid token's grant is put in the request's
+//session without marking it changed, and Set-Cookie is dropped from the response so it can never end up in a cookie.
+//has to wrap the cookie session, ie be registered after it
+pub struct ApiTokenAuth;
+
+impl<S, B> Transform<S> for ApiTokenAuth
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ApiTokenAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ApiTokenAuthMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct ApiTokenAuthMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for ApiTokenAuthMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        Box::pin(async move {
+            let token = match bearer_token(&req) {
+                Some(token) => token,
+                None => {
+                    let response = service.borrow_mut().call(req);
+                    return response.await;
+                }
+            };
+            let pool = req
+                .app_data::<web::Data<PgPool>>()
+                .cloned()
+                .ok_or_else(|| {
+                    ApiError::from(anyhow!("the database pool is missing from app data"))
+                })?;
+            let writes = !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+            let grant = ApiToken::find_grant(&token, writes, pool.get_ref())
+                .await
+                .map_err(ApiError::from)?
+                .ok_or_else(|| ApiError::unauthorized("Invalid or expired API token."))?;
+            if let Err(err) = ApiToken::record_use(&grant.token_id, pool.get_ref()).await {
+                error!("Error recording API token use: {}", err);
+            }
+            let grant = serde_json::to_string(&grant)
+                .map_err(|err| ApiError::from(anyhow::Error::from(err)))?;
+            Session::set_session(vec![(API_TOKEN_GRANT_KEY.to_string(), grant)], &mut req);
+            let response = service.borrow_mut().call(req);
+            let mut response = response.await?;
+            response.headers_mut().remove(header::SET_COOKIE);
+            Ok(response)
+        })
+    }
+}
+
+//other schemes are left alone
+fn bearer_token(req: &ServiceRequest) -> Option<String> {
+    let authorization = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty() {
+        Some(token.trim().to_string())
+    } else {
+        None
+    }
+}
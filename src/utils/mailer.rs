@@ -0,0 +1,174 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncStd1Executor, AsyncTransport, Message};
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct OutgoingMail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub enum MailTemplate {
+    RegistrationConfirmation,
+    PasswordReset,
+    AccountDeletion,
+    EmailChange,
+}
+
+impl MailTemplate {
+    fn subject(&self) -> &'static str {
+        match self {
+            MailTemplate::RegistrationConfirmation => "Confirm your email address",
+            MailTemplate::PasswordReset => "Reset your password",
+            MailTemplate::AccountDeletion => "Your account is scheduled for deletion",
+            MailTemplate::EmailChange => "Confirm your new email address",
+        }
+    }
+    fn body(&self) -> &'static str {
+        match self {
+            MailTemplate::RegistrationConfirmation => {
+                include_str!("../../templates/registration_confirmation.txt")
+            }
+            MailTemplate::PasswordReset => include_str!("../../templates/password_reset.txt"),
+            MailTemplate::AccountDeletion => include_str!("../../templates/account_deletion.txt"),
+            MailTemplate::EmailChange => include_str!("../../templates/email_change.txt"),
+        }
+    }
+    //fills in {{key}} placeholders, site_url is always available to templates
+    pub fn render(&self, to: &str, site_url: &str, values: &[(&str, &str)]) -> OutgoingMail {
+        let mut body = self.body().replace("{{site_url}}", site_url);
+        for (key, value) in values {
+            body = body.replace(&format!("{{{{{}}}}}", key), value);
+        }
+        OutgoingMail {
+            to: to.to_string(),
+            subject: self.subject().to_string(),
+            body,
+        }
+    }
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    fn site_url(&self) -> &str;
+    async fn send(&self, mail: OutgoingMail) -> Result<()>;
+    async fn send_template(
+        &self,
+        template: MailTemplate,
+        to: &str,
+        values: &[(&str, &str)],
+    ) -> Result<()> {
+        let mail = template.render(to, self.site_url(), values);
+        self.send(mail).await
+    }
+}
+
+pub struct SmtpMailer {
+    from: String,
+    site_url: String,
+    transport: AsyncSmtpTransport<AsyncStd1Executor>,
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    fn site_url(&self) -> &str {
+        &self.site_url
+    }
+    async fn send(&self, mail: OutgoingMail) -> Result<()> {
+        let message = Message::builder()
+            .from(self.from.parse()?)
+            .to(mail.to.parse()?)
+            .subject(mail.subject)
+            .body(mail.body)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+//writes each mail to its own file in directory, or to stdout if there is no directory. for local dev
+pub struct FileMailer {
+    from: String,
+    site_url: String,
+    directory: Option<PathBuf>,
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    fn site_url(&self) -> &str {
+        &self.site_url
+    }
+    async fn send(&self, mail: OutgoingMail) -> Result<()> {
+        let contents = format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}",
+            self.from, mail.to, mail.subject, mail.body
+        );
+        match &self.directory {
+            Some(directory) => {
+                async_std::fs::create_dir_all(directory).await?;
+                let path = directory.join(format!("{}.eml", Uuid::new_v4()));
+                async_std::fs::write(&path, contents).await?;
+                info!("wrote mail for {} to {}", mail.to, path.display());
+            }
+            None => {
+                println!("{}", contents);
+            }
+        }
+        Ok(())
+    }
+}
+
+//MAILER picks the implementation: smtp, file or stdout (default)
+pub fn mailer_from_env() -> Result<Arc<dyn Mailer>> {
+    let from = dotenv::var("MAIL_FROM").unwrap_or_else(|_| "noreply@localhost".to_string());
+    let site_url = dotenv::var("SITE_URL").unwrap_or_else(|_| "http://127.0.0.1:4567".to_string());
+    let mailer_kind = dotenv::var("MAILER").unwrap_or_else(|_| "stdout".to_string());
+    match mailer_kind.as_str() {
+        "smtp" => {
+            let host = dotenv::var("SMTP_HOST").map_err(|_| anyhow!("SMTP_HOST is not set"))?;
+            let use_tls = dotenv::var("SMTP_TLS")
+                .map(|tls| tls != "false")
+                .unwrap_or(true);
+            let mut builder = if use_tls {
+                AsyncSmtpTransport::<AsyncStd1Executor>::relay(&host)?
+            } else {
+                AsyncSmtpTransport::<AsyncStd1Executor>::builder_dangerous(&host)
+            };
+            if let Ok(port) = dotenv::var("SMTP_PORT") {
+                builder = builder.port(port.parse()?);
+            }
+            if let (Ok(username), Ok(password)) =
+                (dotenv::var("SMTP_USERNAME"), dotenv::var("SMTP_PASSWORD"))
+            {
+                if !username.is_empty() {
+                    builder = builder.credentials(Credentials::new(username, password));
+                }
+            }
+            Ok(Arc::new(SmtpMailer {
+                from,
+                site_url,
+                transport: builder.build(),
+            }))
+        }
+        "file" => Ok(Arc::new(FileMailer {
+            from,
+            site_url,
+            directory: Some(PathBuf::from(
+                dotenv::var("MAIL_DIR").unwrap_or_else(|_| "mail".to_string()),
+            )),
+        })),
+        "stdout" => Ok(Arc::new(FileMailer {
+            from,
+            site_url,
+            directory: None,
+        })),
+        other => Err(anyhow!(
+            "unknown MAILER {}, expected smtp, file or stdout",
+            other
+        )),
+    }
+}
@@ -0,0 +1,263 @@
+use actix_web::client::Client;
+use actix_web::http::StatusCode;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
+use sqlx::types::time::OffsetDateTime;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+//largest object we'll read back out of a store, uploads are capped well below this
+const MAX_OBJECT_BYTES: usize = 64 * 1024 * 1024;
+
+//keys are generated by media::Media, but the local file route takes them from the path, so anything that could
+//walk out of the directory is refused
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= 100
+        && !key.starts_with('.')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+//where uploaded files go. the awc client futures aren't Send, which is fine inside handlers
+#[async_trait(?Send)]
+pub trait MediaStore: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<()>;
+    //None if there's nothing stored at key
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+    //deleting a key that isn't there is not an error
+    async fn delete(&self, key: &str) -> Result<()>;
+    //where clients fetch the file from
+    fn url(&self, key: &str) -> String;
+}
+
+//files in a directory on this server, served by GET /media/files/{key}. for local dev and single instance setups
+pub struct LocalMediaStore {
+    directory: PathBuf,
+    public_url: String,
+}
+
+#[async_trait(?Send)]
+impl MediaStore for LocalMediaStore {
+    async fn put(&self, key: &str, _content_type: &str, bytes: Vec<u8>) -> Result<()> {
+        if !is_valid_key(key) {
+            return Err(anyhow!("invalid media key {}", key));
+        }
+        async_std::fs::create_dir_all(&self.directory).await?;
+        async_std::fs::write(self.directory.join(key), bytes).await?;
+        Ok(())
+    }
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        if !is_valid_key(key) {
+            return Ok(None);
+        }
+        match async_std::fs::read(self.directory.join(key)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+    async fn delete(&self, key: &str) -> Result<()> {
+        if !is_valid_key(key) {
+            return Ok(());
+        }
+        match async_std::fs::remove_file(self.directory.join(key)).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_url, key)
+    }
+}
+
+//any s3 compatible bucket, addressed path style ({endpoint}/{bucket}/{key}) so minio works without dns setup.
+//requests are signed with aws signature v4. the bucket has to allow public reads, or MEDIA_PUBLIC_URL has to point
+//at something that can read it, eg a cdn
+pub struct S3MediaStore {
+    endpoint: String,
+    host: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    public_url: String,
+}
+
+impl S3MediaStore {
+    fn object_url(&self, key: &str) -> String {
+        format!("{}/{}/{}", self.endpoint, self.bucket, key)
+    }
+    //the headers that go with a request for key. only host, x-amz-content-sha256 and x-amz-date are signed
+    fn signed_headers(
+        &self,
+        method: &str,
+        key: &str,
+        payload: &[u8],
+        now: OffsetDateTime,
+    ) -> Vec<(&'static str, String)> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ");
+        let date = now.format("%Y%m%d");
+        let payload_hash = hex::encode(Sha256::digest(payload));
+        let canonical_request = format!(
+            "{}\n/{}/{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method, self.bucket, key, self.host, payload_hash, amz_date, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signing_key = [self.region.as_bytes(), b"s3", b"aws4_request"]
+            .iter()
+            .fold(
+                hmac_sha256(
+                    format!("AWS4{}", self.secret_key).as_bytes(),
+                    date.as_bytes(),
+                ),
+                |key, part| hmac_sha256(&key, part),
+            );
+        let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+        vec![
+            ("host", self.host.clone()),
+            ("x-amz-content-sha256", payload_hash),
+            ("x-amz-date", amz_date),
+            (
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+                    self.access_key, scope, signature
+                ),
+            ),
+        ]
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    //hmac takes keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[async_trait(?Send)]
+impl MediaStore for S3MediaStore {
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<()> {
+        let mut request = Client::default()
+            .put(self.object_url(key))
+            .header("content-type", content_type);
+        for (name, value) in self.signed_headers("PUT", key, &bytes, OffsetDateTime::now_utc()) {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send_body(bytes)
+            .await
+            .map_err(|err| anyhow!("error uploading {} to s3: {}", key, err))?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "s3 answered {} when uploading {}",
+                response.status(),
+                key
+            ));
+        }
+        Ok(())
+    }
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let mut request = Client::default().get(self.object_url(key));
+        for (name, value) in self.signed_headers("GET", key, b"", OffsetDateTime::now_utc()) {
+            request = request.header(name, value);
+        }
+        let mut response = request
+            .send()
+            .await
+            .map_err(|err| anyhow!("error fetching {} from s3: {}", key, err))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let bytes = response
+                    .body()
+                    .limit(MAX_OBJECT_BYTES)
+                    .await
+                    .map_err(|err| anyhow!("error reading {} from s3: {}", key, err))?;
+                Ok(Some(bytes.to_vec()))
+            }
+            status => Err(anyhow!("s3 answered {} when fetching {}", status, key)),
+        }
+    }
+    async fn delete(&self, key: &str) -> Result<()> {
+        let mut request = Client::default().delete(self.object_url(key));
+        for (name, value) in self.signed_headers("DELETE", key, b"", OffsetDateTime::now_utc()) {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|err| anyhow!("error deleting {} from s3: {}", key, err))?;
+        //s3 answers 204 whether or not the key existed
+        if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
+            return Err(anyhow!(
+                "s3 answered {} when deleting {}",
+                response.status(),
+                key
+            ));
+        }
+        Ok(())
+    }
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_url, key)
+    }
+}
+
+//MEDIA_STORE picks the implementation: local (default) or s3
+pub fn media_store_from_env() -> Result<Arc<dyn MediaStore>> {
+    let site_url = dotenv::var("SITE_URL").unwrap_or_else(|_| "http://127.0.0.1:4567".to_string());
+    let public_url = dotenv::var("MEDIA_PUBLIC_URL")
+        .ok()
+        .filter(|public_url| !public_url.is_empty())
+        .map(|public_url| public_url.trim_end_matches('/').to_string());
+    let media_store_kind = dotenv::var("MEDIA_STORE").unwrap_or_else(|_| "local".to_string());
+    match media_store_kind.as_str() {
+        "local" => Ok(Arc::new(LocalMediaStore {
+            directory: PathBuf::from(
+                dotenv::var("MEDIA_DIR").unwrap_or_else(|_| "media".to_string()),
+            ),
+            public_url: public_url.unwrap_or_else(|| format!("{}/media/files", site_url)),
+        })),
+        "s3" => {
+            let endpoint = dotenv::var("S3_ENDPOINT")
+                .map_err(|_| anyhow!("S3_ENDPOINT is not set"))?
+                .trim_end_matches('/')
+                .to_string();
+            let host = endpoint
+                .split("://")
+                .nth(1)
+                .filter(|host| !host.is_empty() && !host.contains('/'))
+                .ok_or_else(|| {
+                    anyhow!("S3_ENDPOINT must look like http://host:port, without a path")
+                })?
+                .to_string();
+            let bucket = dotenv::var("S3_BUCKET").map_err(|_| anyhow!("S3_BUCKET is not set"))?;
+            Ok(Arc::new(S3MediaStore {
+                public_url: public_url.unwrap_or_else(|| format!("{}/{}", endpoint, bucket)),
+                endpoint,
+                host,
+                bucket,
+                region: dotenv::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                access_key: dotenv::var("S3_ACCESS_KEY")
+                    .map_err(|_| anyhow!("S3_ACCESS_KEY is not set"))?,
+                secret_key: dotenv::var("S3_SECRET_KEY")
+                    .map_err(|_| anyhow!("S3_SECRET_KEY is not set"))?,
+            }))
+        }
+        other => Err(anyhow!(
+            "unknown MEDIA_STORE {}, expected local or s3",
+            other
+        )),
+    }
+}
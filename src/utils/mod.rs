@@ -1 +1,11 @@
+pub mod api_error;
+pub mod api_token_auth;
+pub mod mailer;
+pub mod media_store;
+pub mod pagination;
+pub mod rate_limit;
+pub mod request_info;
+pub mod session_config;
 pub mod session_validation;
+pub mod token;
+pub mod totp;
@@ -0,0 +1,136 @@
+use crate::utils::api_error::ApiError;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::types::time::PrimitiveDateTime;
+use std::fmt;
+use std::str::FromStr;
+
+//the position of the last item on a page: the sort that produced it, the value it was sorted by and a unique
+//tiebreaker (usually its id). clients only ever see it base64 encoded, so the format can change without breaking them
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    pub sort: String,
+    pub key: String,
+    pub id: String,
+}
+
+impl Cursor {
+    pub fn new<K: ToString, I: ToString>(sort: &str, key: K, id: I) -> Cursor {
+        Cursor {
+            sort: sort.to_string(),
+            key: key.to_string(),
+            id: id.to_string(),
+        }
+    }
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_string(&(&self.sort, &self.key, &self.id)).unwrap_or_default();
+        base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+    }
+    pub fn decode(token: &str) -> Result<Cursor> {
+        let json = base64::decode_config(token, base64::URL_SAFE_NO_PAD)?;
+        let (sort, key, id): (String, String, String) = serde_json::from_slice(&json)?;
+        Ok(Cursor { sort, key, id })
+    }
+}
+
+//timestamp sort keys. written with a fixed format so they parse back exactly, and so a tampered cursor is
+//rejected here instead of failing the cast in postgres
+#[derive(Debug, Clone, Copy)]
+pub struct CursorTimestamp(pub PrimitiveDateTime);
+
+const CURSOR_TIMESTAMP_FORMAT: &str = "%F %T.%N";
+
+impl fmt::Display for CursorTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format(CURSOR_TIMESTAMP_FORMAT))
+    }
+}
+
+impl FromStr for CursorTimestamp {
+    type Err = anyhow::Error;
+    fn from_str(key: &str) -> Result<CursorTimestamp> {
+        Ok(CursorTimestamp(PrimitiveDateTime::parse(
+            key,
+            CURSOR_TIMESTAMP_FORMAT,
+        )?))
+    }
+}
+
+//query string for list routes, eg ?cursor=WyIxIiwiMiJd
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PageForm {
+    pub cursor: Option<String>,
+}
+
+//where a list query starts. a cursor wins over the page number, which is only kept so old /{page_number} links work
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    pub cursor: Option<Cursor>,
+    pub page_number: i64,
+    pub results_per_page: i64,
+}
+
+impl PageRequest {
+    pub fn new(
+        page_form: &PageForm,
+        page_number: &i64,
+        results_per_page: &i64,
+    ) -> Result<PageRequest> {
+        let cursor = match &page_form.cursor {
+            Some(token) if !token.is_empty() => Some(Cursor::decode(token)?),
+            _ => None,
+        };
+        if *page_number < 1 {
+            return Err(anyhow!("Page number must be at least 1."));
+        }
+        Ok(PageRequest {
+            cursor,
+            page_number: *page_number,
+            results_per_page: *results_per_page,
+        })
+    }
+    //keyset queries skip nothing once they have a cursor, the WHERE clause does the work
+    pub fn offset(&self) -> i64 {
+        match self.cursor {
+            Some(_) => 0,
+            None => (self.page_number - 1) * self.results_per_page,
+        }
+    }
+    //the cursor's sort key, parsed into whatever type the query compares it against. a cursor from another sort
+    //(or another list) would be compared against the wrong column, so it's the client's mistake, not ours
+    pub fn key<T: FromStr>(&self, sort: &str) -> Result<Option<T>> {
+        match &self.cursor {
+            Some(cursor) if cursor.sort != sort => {
+                Err(ApiError::invalid_field("cursor", "Cursor is from a different sort.").into())
+            }
+            Some(cursor) => match cursor.key.parse::<T>() {
+                Ok(key) => Ok(Some(key)),
+                Err(_) => Err(ApiError::invalid_field("cursor", "Invalid cursor.").into()),
+            },
+            None => Ok(None),
+        }
+    }
+    pub fn id<T: FromStr>(&self) -> Result<Option<T>> {
+        match &self.cursor {
+            Some(cursor) => match cursor.id.parse::<T>() {
+                Ok(id) => Ok(Some(id)),
+                Err(_) => Err(ApiError::invalid_field("cursor", "Invalid cursor.").into()),
+            },
+            None => Ok(None),
+        }
+    }
+    //a full page means there may be more, so hand out a cursor pointing at its last row
+    pub fn next_cursor<R>(&self, rows: &[R], cursor_of: impl Fn(&R) -> Cursor) -> Option<String> {
+        if (rows.len() as i64) < self.results_per_page {
+            return None;
+        }
+        rows.last().map(|row| cursor_of(row).encode())
+    }
+}
+
+//every list route returns one of these instead of a bare array
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
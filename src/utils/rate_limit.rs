@@ -0,0 +1,349 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::{request_info, session_validation};
+use actix_session::UserSession;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use sqlx::PgPool;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+//routes that share a limit. each group has its own buckets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RouteGroup {
+    //login, registration, password resets and email changes
+    Auth,
+    Post,
+    Comment,
+    Vote,
+    Report,
+    Upload,
+}
+
+impl RouteGroup {
+    fn name(&self) -> &'static str {
+        match self {
+            RouteGroup::Auth => "auth",
+            RouteGroup::Post => "post",
+            RouteGroup::Comment => "comment",
+            RouteGroup::Vote => "vote",
+            RouteGroup::Report => "report",
+            RouteGroup::Upload => "upload",
+        }
+    }
+}
+
+//a bucket holds up to capacity requests and refills completely over period
+#[derive(Debug, Clone, Copy)]
+pub struct BucketLimit {
+    pub capacity: f64,
+    pub period: Duration,
+}
+
+impl BucketLimit {
+    fn refill_per_second(&self) -> f64 {
+        self.capacity / self.period.as_secs_f64()
+    }
+    //RATE_LIMIT_VOTE=120/60 is 120 requests per 60 seconds
+    fn from_env(name: &str, default: BucketLimit) -> Result<BucketLimit> {
+        let value = match dotenv::var(name) {
+            Ok(value) => value,
+            Err(_) => return Ok(default),
+        };
+        let parsed = value.split_once('/').and_then(|(capacity, seconds)| {
+            Some((
+                capacity.trim().parse::<u32>().ok()?,
+                seconds.trim().parse::<u64>().ok()?,
+            ))
+        });
+        match parsed {
+            Some((capacity, seconds)) if capacity > 0 && seconds > 0 => Ok(BucketLimit {
+                capacity: capacity as f64,
+                period: Duration::from_secs(seconds),
+            }),
+            _ => Err(anyhow!(
+                "{} must look like requests/seconds, eg 10/60",
+                name
+            )),
+        }
+    }
+}
+
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    //takes a token from the bucket at key. None if it got one, otherwise how long until one refills
+    async fn take(&self, key: &str, limit: &BucketLimit) -> Result<Option<Duration>>;
+    //forgets buckets that have refilled, they'd start out full anyway
+    async fn purge(&self) -> Result<u64>;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    full_at: Instant,
+}
+
+//the default, limits are per instance
+#[derive(Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take(&self, key: &str, limit: &BucketLimit) -> Result<Option<Duration>> {
+        let now = Instant::now();
+        let refill_per_second = limit.refill_per_second();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .map_err(|_| anyhow!("rate limit buckets lock is poisoned"))?;
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: limit.capacity,
+            updated_at: now,
+            full_at: now,
+        });
+        let available = (bucket.tokens
+            + now.duration_since(bucket.updated_at).as_secs_f64() * refill_per_second)
+            .min(limit.capacity);
+        let (remaining, wait) = if available >= 1.0 {
+            (available - 1.0, None)
+        } else {
+            (
+                available,
+                Some(Duration::from_secs_f64(
+                    (1.0 - available) / refill_per_second,
+                )),
+            )
+        };
+        bucket.tokens = remaining;
+        bucket.updated_at = now;
+        bucket.full_at =
+            now + Duration::from_secs_f64((limit.capacity - remaining) / refill_per_second);
+        Ok(wait)
+    }
+    async fn purge(&self) -> Result<u64> {
+        let now = Instant::now();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .map_err(|_| anyhow!("rate limit buckets lock is poisoned"))?;
+        let before = buckets.len();
+        buckets.retain(|_, bucket| bucket.full_at > now);
+        Ok((before - buckets.len()) as u64)
+    }
+}
+
+//shared by every instance using the database, see the rate_limit_buckets migration
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+#[async_trait]
+impl RateLimitStore for PostgresStore {
+    async fn take(&self, key: &str, limit: &BucketLimit) -> Result<Option<Duration>> {
+        let wait = sqlx::query!(
+            r#"
+            SELECT take_rate_limit_token($1, $2, $3) AS "wait!"
+            "#,
+            key,
+            limit.capacity,
+            limit.refill_per_second()
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .wait;
+        if wait > 0.0 {
+            Ok(Some(Duration::from_secs_f64(wait)))
+        } else {
+            Ok(None)
+        }
+    }
+    async fn purge(&self) -> Result<u64> {
+        let purged = sqlx::query!(
+            r#"
+            DELETE FROM rate_limit_buckets
+            WHERE full_at <= LOCALTIMESTAMP
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(purged.rows_affected())
+    }
+}
+
+pub struct RateLimiter {
+    store: Box<dyn RateLimitStore>,
+    auth: BucketLimit,
+    post: BucketLimit,
+    comment: BucketLimit,
+    vote: BucketLimit,
+    report: BucketLimit,
+    upload: BucketLimit,
+}
+
+impl RateLimiter {
+    fn limit(&self, group: RouteGroup) -> &BucketLimit {
+        match group {
+            RouteGroup::Auth => &self.auth,
+            RouteGroup::Post => &self.post,
+            RouteGroup::Comment => &self.comment,
+            RouteGroup::Vote => &self.vote,
+            RouteGroup::Report => &self.report,
+            RouteGroup::Upload => &self.upload,
+        }
+    }
+    //one bucket per client ip and, when logged in, one per user. both have to have a token left
+    pub async fn check(
+        &self,
+        group: RouteGroup,
+        user_id: Option<i32>,
+        ip: Option<String>,
+    ) -> Result<Option<Duration>> {
+        let limit = self.limit(group);
+        let mut keys = vec![];
+        if let Some(user_id) = user_id {
+            keys.push(format!("{}:user:{}", group.name(), user_id));
+        }
+        if let Some(ip) = ip {
+            keys.push(format!("{}:ip:{}", group.name(), ip));
+        }
+        for key in keys {
+            if let Some(wait) = self.store.take(&key, limit).await? {
+                return Ok(Some(wait));
+            }
+        }
+        Ok(None)
+    }
+}
+
+//RATE_LIMIT_STORE is memory (default) or postgres, and RATE_LIMIT_<GROUP>=requests/seconds overrides a group's limit
+pub fn rate_limiter_from_env(pool: &PgPool) -> Result<Arc<RateLimiter>> {
+    let store: Box<dyn RateLimitStore> = match dotenv::var("RATE_LIMIT_STORE")
+        .unwrap_or_else(|_| "memory".to_string())
+        .as_str()
+    {
+        "memory" => Box::new(MemoryStore::default()),
+        "postgres" => Box::new(PostgresStore { pool: pool.clone() }),
+        other => {
+            return Err(anyhow!(
+                "unknown RATE_LIMIT_STORE {}, expected memory or postgres",
+                other
+            ))
+        }
+    };
+    let per_minute = |capacity: f64, minutes: u64| BucketLimit {
+        capacity,
+        period: Duration::from_secs(minutes * 60),
+    };
+    Ok(Arc::new(RateLimiter {
+        store,
+        auth: BucketLimit::from_env("RATE_LIMIT_AUTH", per_minute(10.0, 1))?,
+        post: BucketLimit::from_env("RATE_LIMIT_POST", per_minute(10.0, 10))?,
+        comment: BucketLimit::from_env("RATE_LIMIT_COMMENT", per_minute(30.0, 10))?,
+        vote: BucketLimit::from_env("RATE_LIMIT_VOTE", per_minute(120.0, 1))?,
+        report: BucketLimit::from_env("RATE_LIMIT_REPORT", per_minute(10.0, 10))?,
+        upload: BucketLimit::from_env("RATE_LIMIT_UPLOAD", per_minute(20.0, 10))?,
+    }))
+}
+
+//runs for the life of the server, dropping buckets that have refilled so memory and the table don't grow forever
+pub async fn purge_rate_limits(limiter: Arc<RateLimiter>) {
+    loop {
+        match limiter.store.purge().await {
+            Ok(0) => (),
+            Ok(count) => debug!("Purged {} refilled rate limit buckets", count),
+            Err(err) => error!("Error purging rate limit buckets: {}", err),
+        }
+        async_std::task::sleep(Duration::from_secs(10 * 60)).await;
+    }
+}
+
+//wrap a resource or scope in this to rate limit it, eg web::resource("/login").wrap(RateLimit::new(RouteGroup::Auth)).
+//the limiter comes from app data
+pub struct RateLimit {
+    group: RouteGroup,
+}
+
+impl RateLimit {
+    pub fn new(group: RouteGroup) -> RateLimit {
+        RateLimit { group }
+    }
+}
+
+impl<S, B> Transform<S> for RateLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            group: self.group,
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    group: RouteGroup,
+}
+
+impl<S, B> Service for RateLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let group = self.group;
+        Box::pin(async move {
+            let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+            let pool = req.app_data::<web::Data<PgPool>>().cloned();
+            if let (Some(limiter), Some(pool)) = (limiter, pool) {
+                let session = req.get_session();
+                let user_id = session_validation::validate_session(&session, pool.get_ref())
+                    .await
+                    .map_err(ApiError::from)?
+                    .map(|user| user.user_id);
+                let ip = request_info::service_client_ip(&req);
+                //a broken store shouldn't take the routes down with it
+                match limiter.check(group, user_id, ip).await {
+                    Ok(Some(wait)) => {
+                        return Err(
+                            ApiError::rate_limited("Too many requests, slow down.", wait).into(),
+                        )
+                    }
+                    Ok(None) => (),
+                    Err(err) => error!("Error checking rate limit: {}", err),
+                }
+            }
+            let response = service.borrow_mut().call(req);
+            response.await
+        })
+    }
+}
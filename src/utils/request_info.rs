@@ -0,0 +1,64 @@
+use actix_web::dev::ServiceRequest;
+use actix_web::http::HeaderMap;
+use actix_web::HttpRequest;
+use once_cell::sync::Lazy;
+use std::net::{IpAddr, SocketAddr};
+
+//TRUSTED_PROXIES, comma separated addresses of the reverse proxies in front of the server. X-Forwarded-For is
+//ignored unless the connection comes from one of them, since anyone can send it
+static TRUSTED_PROXIES: Lazy<Vec<IpAddr>> = Lazy::new(|| {
+    dotenv::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|proxy| proxy.trim().parse::<IpAddr>().ok())
+        .collect()
+});
+
+//the client's address, without the port. rate limits and login lockouts are keyed on this
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    ip_address(req.peer_addr(), req.headers())
+}
+
+//same as client_ip, for middleware
+pub fn service_client_ip(req: &ServiceRequest) -> Option<String> {
+    ip_address(req.peer_addr(), req.headers())
+}
+
+//the connection's address, unless it's a trusted proxy. then it's the last address in X-Forwarded-For that isn't
+//a trusted proxy, since every proxy appends the address it got the request from and anything further left could
+//have been sent by the client
+fn ip_address(peer_address: Option<SocketAddr>, headers: &HeaderMap) -> Option<String> {
+    let peer_ip = peer_address?.ip();
+    if !TRUSTED_PROXIES.contains(&peer_ip) {
+        return Some(peer_ip.to_string());
+    }
+    let forwarded_ips: Vec<IpAddr> = headers
+        .get_all("X-Forwarded-For")
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(','))
+        .filter_map(|address| parse_forwarded_address(address.trim()))
+        .collect();
+    let client_ip = forwarded_ips
+        .iter()
+        .rev()
+        .find(|ip| !TRUSTED_PROXIES.contains(ip))
+        .or_else(|| forwarded_ips.first())
+        .copied()
+        .unwrap_or(peer_ip);
+    Some(client_ip.to_string())
+}
+
+//X-Forwarded-For entries are usually bare addresses, but some proxies add a port
+fn parse_forwarded_address(address: &str) -> Option<IpAddr> {
+    address
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| address.parse::<SocketAddr>().ok().map(|address| address.ip()))
+}
+
+pub fn user_agent(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("User-Agent")
+        .and_then(|agent| agent.to_str().ok())
+        .map(|agent| agent.to_string())
+}
@@ -0,0 +1,102 @@
+use actix_session::CookieSession;
+use actix_web::cookie::SameSite;
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+
+static SESSION_CONFIG: OnceCell<SessionConfig> = OnceCell::new();
+
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    key: Vec<u8>,
+    cookie_name: String,
+    secure: bool,
+    same_site: SameSite,
+    domain: Option<String>,
+    pub idle_timeout_minutes: i32,
+    pub absolute_timeout_hours: i32,
+    pub purge_interval_minutes: u64,
+}
+
+impl SessionConfig {
+    //SESSION_KEY is required: base64, at least 32 bytes once decoded. generate one with `openssl rand -base64 64`
+    pub fn from_env() -> Result<SessionConfig> {
+        let encoded_key =
+            dotenv::var("SESSION_KEY").map_err(|_| anyhow!("SESSION_KEY is not set"))?;
+        let key = base64::decode(encoded_key.trim())
+            .map_err(|_| anyhow!("SESSION_KEY must be base64 encoded"))?;
+        if key.len() < 32 {
+            return Err(anyhow!("SESSION_KEY must be at least 32 bytes"));
+        }
+        let secure = dotenv::var("SESSION_COOKIE_SECURE")
+            .map(|secure| secure != "false")
+            .unwrap_or(true);
+        let same_site = match dotenv::var("SESSION_COOKIE_SAME_SITE")
+            .unwrap_or_else(|_| "lax".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            "none" => SameSite::None,
+            other => {
+                return Err(anyhow!(
+                    "unknown SESSION_COOKIE_SAME_SITE {}, expected strict, lax or none",
+                    other
+                ))
+            }
+        };
+        //browsers reject SameSite=None without Secure
+        if same_site == SameSite::None && !secure {
+            return Err(anyhow!(
+                "SESSION_COOKIE_SAME_SITE=none requires SESSION_COOKIE_SECURE=true"
+            ));
+        }
+        Ok(SessionConfig {
+            key,
+            cookie_name: dotenv::var("SESSION_COOKIE_NAME")
+                .unwrap_or_else(|_| "linkagg-session".to_string()),
+            secure,
+            same_site,
+            domain: dotenv::var("SESSION_COOKIE_DOMAIN")
+                .ok()
+                .filter(|domain| !domain.is_empty()),
+            idle_timeout_minutes: parse_var("SESSION_IDLE_TIMEOUT_MINUTES", 60 * 24 * 7)?,
+            absolute_timeout_hours: parse_var("SESSION_ABSOLUTE_TIMEOUT_HOURS", 24 * 30)?,
+            purge_interval_minutes: parse_var("SESSION_PURGE_INTERVAL_MINUTES", 60)?,
+        })
+    }
+    //private cookies are encrypted and authenticated, so the session id can't be read or forged client side
+    pub fn cookie_session(&self) -> CookieSession {
+        let cookie_session = CookieSession::private(&self.key)
+            .name(self.cookie_name.as_str())
+            .path("/")
+            .secure(self.secure)
+            .http_only(true)
+            .same_site(self.same_site)
+            .max_age(self.absolute_timeout_hours as i64 * 60 * 60);
+        match &self.domain {
+            Some(domain) => cookie_session.domain(domain.as_str()),
+            None => cookie_session,
+        }
+    }
+}
+
+fn parse_var<T: std::str::FromStr>(name: &str, default: T) -> Result<T> {
+    match dotenv::var(name) {
+        Ok(value) => value
+            .parse::<T>()
+            .map_err(|_| anyhow!("{} must be a number", name)),
+        Err(_) => Ok(default),
+    }
+}
+
+//loaded once in main, session validation reads the timeouts from here
+pub fn init(config: SessionConfig) -> &'static SessionConfig {
+    SESSION_CONFIG.get_or_init(|| config)
+}
+
+pub fn get() -> &'static SessionConfig {
+    SESSION_CONFIG
+        .get()
+        .expect("session config is loaded in main before the server starts")
+}
@@ -1,14 +1,34 @@
+use crate::api_token::{ApiToken, ApiTokenGrant};
+use crate::email_change::EmailChange;
+use crate::guild::Guild;
 use crate::guild_membership::GuildMembership;
+use crate::login_failure::LoginFailure;
+use crate::site::Site;
+use crate::two_factor::PendingLogin;
 use crate::user::User;
 use crate::user_session::UserSession;
+use crate::utils::api_error::ApiError;
+use crate::utils::api_token_auth::API_TOKEN_GRANT_KEY;
+use crate::utils::session_config;
 use actix_session::Session;
-use actix_web::HttpResponse;
 use anyhow::Result;
 use sqlx::PgPool;
+use std::time::Duration;
 
+//the user from a bearer token, if the request had one, otherwise from the session cookie
 pub async fn validate_session(session: &Session, pool: &PgPool) -> Result<Option<User>> {
+    if let Some(grant) = api_token_grant(session) {
+        return User::find_by_id(&grant.user_id, pool).await;
+    }
     if let Ok(Some(session_id)) = session.get::<String>("session_id") {
-        let active_session = UserSession::find_by_session_id(session_id, pool).await;
+        let config = session_config::get();
+        let active_session = UserSession::find_active_by_session_id(
+            &session_id,
+            &config.idle_timeout_minutes,
+            &config.absolute_timeout_hours,
+            pool,
+        )
+        .await;
         match active_session {
             //make sure session is valid
             Ok(Some(my_session)) => {
@@ -30,7 +50,7 @@ pub async fn validate_session(session: &Session, pool: &PgPool) -> Result<Option
                 }
             }
             Ok(None) => {
-                //remove session from cookie
+                //session doesn't exist or has expired, remove session from cookie
                 session.remove("session_id");
                 return Ok(None);
             }
@@ -44,227 +64,241 @@ pub async fn validate_session(session: &Session, pool: &PgPool) -> Result<Option
     Ok(None)
 }
 
-pub async fn policy_user(
-    session: &Session,
-    pool: &PgPool,
-) -> Result<(Option<HttpResponse>, Option<User>)> {
-    let valid_session = validate_session(session, pool).await;
-    match valid_session {
-        Ok(Some(user)) => {
-            if user.is_banned {
-                Ok((
-                    Some(HttpResponse::Forbidden().body("You are banned.")),
-                    None,
-                ))
-            } else {
-                Ok((None, Some(user)))
-            }
+//runs for the life of the server, deleting sessions that validate_session would reject anyway
+pub async fn purge_expired_sessions(pool: PgPool) {
+    let config = session_config::get();
+    loop {
+        let purged = UserSession::purge_expired(
+            &config.idle_timeout_minutes,
+            &config.absolute_timeout_hours,
+            &pool,
+        )
+        .await;
+        match purged {
+            Ok(0) => (),
+            Ok(count) => info!("Purged {} expired sessions", count),
+            Err(err) => error!("Error purging expired sessions: {}", err),
+        }
+        //old failed login counts, abandoned two factor logins, long expired api tokens and unconfirmed email changes
+        //go on the same schedule
+        if let Err(err) = LoginFailure::purge_stale(&pool).await {
+            error!("Error purging failed logins: {}", err);
+        }
+        if let Err(err) = PendingLogin::purge_expired(&pool).await {
+            error!("Error purging pending logins: {}", err);
         }
-        Ok(None) => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-        Err(err) => {
-            error!(
-                "Error checking guild membership data on admin policy: {}",
-                err
-            );
-            Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
+        if let Err(err) = ApiToken::purge_expired(&pool).await {
+            error!("Error purging expired API tokens: {}", err);
         }
+        if let Err(err) = EmailChange::purge_expired(&pool).await {
+            error!("Error purging expired email changes: {}", err);
+        }
+        async_std::task::sleep(Duration::from_secs(config.purge_interval_minutes * 60)).await;
     }
 }
 
-pub async fn policy_self(
-    username: &String,
-    session: &Session,
-    pool: &PgPool,
-) -> Result<(Option<HttpResponse>, Option<User>)> {
-    let valid_session = validate_session(session, pool).await;
-    match valid_session {
-        Ok(Some(user)) => {
-            if user.is_banned {
-                Ok((
-                    Some(HttpResponse::Forbidden().body("You are banned.")),
-                    None,
-                ))
-            } else {
-                if &user.username == username {
-                    Ok((None, Some(user)))
-                } else {
-                    Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
-                }
-            }
+//runs for the life of the server, lifting temporary site and guild bans once they expire
+pub async fn lift_expired_bans(pool: PgPool) {
+    loop {
+        let lifted = match User::lift_expired_bans(&pool).await {
+            Ok(users) => GuildMembership::lift_expired_bans(&pool)
+                .await
+                .map(|memberships| users + memberships),
+            Err(err) => Err(err),
+        };
+        match lifted {
+            Ok(0) => (),
+            Ok(count) => info!("Lifted {} expired bans", count),
+            Err(err) => error!("Error lifting expired bans: {}", err),
         }
-        Ok(None) => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-        Err(err) => {
-            error!(
-                "Error checking guild membership data on admin policy: {}",
-                err
-            );
-            Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
+        async_std::task::sleep(Duration::from_secs(60)).await;
+    }
+}
+
+//turns away site banned users with the reason and expiry. used by policy_user and login
+pub async fn check_site_ban(user: &User, pool: &PgPool) -> Result<(), ApiError> {
+    if !user.is_banned {
+        return Ok(());
+    }
+    match User::find_active_ban(&user.user_id, pool).await? {
+        Some(ban) => Err(ApiError::banned("You are banned.", ban)),
+        None => Ok(()),
+    }
+}
+
+//what the request's bearer token is allowed to do. None for cookie sessions
+pub fn api_token_grant(session: &Session) -> Option<ApiTokenGrant> {
+    session
+        .get::<ApiTokenGrant>(API_TOKEN_GRANT_KEY)
+        .unwrap_or(None)
+}
+
+//logged in and not site banned, without checking token scopes
+async fn authenticated_user(session: &Session, pool: &PgPool) -> Result<User, ApiError> {
+    match validate_session(session, pool).await? {
+        Some(user) => {
+            check_site_ban(&user, pool).await?;
+            Ok(user)
         }
+        None => Err(ApiError::unauthorized("You must be logged in.")),
+    }
+}
+
+//logged in and not site banned. tokens without the post scope can only make GET requests
+pub async fn policy_user(session: &Session, pool: &PgPool) -> Result<User, ApiError> {
+    let user = authenticated_user(session, pool).await?;
+    match api_token_grant(session) {
+        Some(grant) if !grant.can_post() => Err(ApiError::forbidden(
+            "This API token can only read, it needs the post scope.",
+        )),
+        _ => Ok(user),
     }
 }
 
-pub async fn policy_admin(
+//for account management (api tokens, two factor, sessions), which a token can't do itself
+pub async fn policy_cookie_user(session: &Session, pool: &PgPool) -> Result<User, ApiError> {
+    if api_token_grant(session).is_some() {
+        return Err(ApiError::forbidden(
+            "You must be logged in with a session to do that, not an API token.",
+        ));
+    }
+    policy_user(session, pool).await
+}
+
+//tokens only get into a guild's mod and admin routes with a moderate:<guild_tag> scope for it.
+//scope tags are resolved like any other tag, so old tags of a renamed guild still work
+async fn check_moderate_scope(
     session: &Session,
+    guild_id: &i32,
     pool: &PgPool,
-) -> Result<(Option<HttpResponse>, Option<User>)> {
-    let valid_session = validate_session(session, pool).await;
-    match valid_session {
-        Ok(Some(user)) => match user.is_admin {
-            true => Ok((None, Some(user))),
-            _ => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-        },
-        Ok(none) => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-        Err(err) => {
-            error!(
-                "Error checking guild membership data on admin policy: {}",
-                err
-            );
-            Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
+) -> Result<(), ApiError> {
+    let grant = match api_token_grant(session) {
+        Some(grant) => grant,
+        None => return Ok(()),
+    };
+    for guild_tag in grant.moderated_guild_tags() {
+        if let Some(guild) = Guild::find_by_guild_tag(&guild_tag, pool).await? {
+            if &guild.guild_id == guild_id {
+                return Ok(());
+            }
         }
     }
+    Err(ApiError::forbidden(
+        "This API token needs the moderate scope for this guild.",
+    ))
 }
 
-pub async fn policy_guild_admin(
+//for routes anyone can read, where a logged in user gets extra detail. banned users are treated as guests
+pub async fn optional_user(session: &Session, pool: &PgPool) -> Result<Option<User>, ApiError> {
+    match policy_user(session, pool).await {
+        Ok(user) => Ok(Some(user)),
+        Err(ApiError::Internal(err)) => Err(ApiError::Internal(err)),
+        Err(_) => Ok(None),
+    }
+}
+
+pub async fn policy_self(
+    username: &String,
     session: &Session,
-    guild_tag: &String,
     pool: &PgPool,
-) -> Result<(Option<HttpResponse>, Option<User>)> {
-    let valid_session = validate_session(session, pool).await;
-    match valid_session {
-        Ok(Some(user)) => {
-            if user.is_banned {
-                return Ok((
-                    Some(HttpResponse::Forbidden().body("You are banned.")),
-                    None,
-                ));
-            }
+) -> Result<User, ApiError> {
+    let user = policy_user(session, pool).await?;
+    if &user.username == username {
+        Ok(user)
+    } else {
+        Err(ApiError::forbidden(
+            "You can only do that to your own account.",
+        ))
+    }
+}
 
-            //also make avaiable to site admins
-            if user.is_admin {
-                return Ok((None, Some(user)));
-            }
-            let is_member =
-                GuildMembership::find_by_user_and_guild_tag(&user.user_id, guild_tag, pool).await;
-            match is_member {
-                Ok(Some(membership)) => match membership.is_admin {
-                    true => Ok((None, Some(user))),
-                    _ => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-                },
-                Ok(None) => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-                Err(err) => {
-                    error!(
-                        "Error checking guild membership data on admin policy: {}",
-                        err
-                    );
-                    Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
-                }
+//site admins without two factor lose their admin powers while the site requires it, until they turn it on
+async fn acts_as_site_admin(user: &User, pool: &PgPool) -> Result<bool, ApiError> {
+    if !user.is_admin {
+        return Ok(false);
+    }
+    Ok(user.totp_enabled || !Site::requires_admin_two_factor(pool).await?)
+}
+
+//never through an api token
+pub async fn policy_admin(session: &Session, pool: &PgPool) -> Result<User, ApiError> {
+    if api_token_grant(session).is_some() {
+        return Err(ApiError::forbidden(
+            "API tokens can't be used for site admin routes.",
+        ));
+    }
+    match validate_session(session, pool).await? {
+        Some(user) => {
+            if acts_as_site_admin(&user, pool).await? {
+                Ok(user)
+            } else if user.is_admin {
+                Err(ApiError::forbidden(
+                    "Site admins must turn on two-factor authentication first.",
+                ))
+            } else {
+                Err(ApiError::forbidden("Only site admins can do that."))
             }
         }
-        Ok(None) => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-        Err(err) => {
-            error!(
-                "Error checking guild membership data on admin policy: {}",
-                err
-            );
-            Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
-        }
+        None => Err(ApiError::unauthorized("You must be logged in.")),
+    }
+}
+
+pub async fn policy_guild_admin(
+    session: &Session,
+    guild_id: &i32,
+    pool: &PgPool,
+) -> Result<User, ApiError> {
+    let user = authenticated_user(session, pool).await?;
+    check_moderate_scope(session, guild_id, pool).await?;
+    //also make avaiable to site admins
+    if acts_as_site_admin(&user, pool).await? {
+        return Ok(user);
+    }
+    match GuildMembership::find_by_user_and_guild_id(&user.user_id, guild_id, pool).await? {
+        Some(membership) if membership.is_admin => Ok(user),
+        _ => Err(ApiError::forbidden("Only guild admins can do that.")),
     }
 }
 
 pub async fn policy_guild_member(
     session: &Session,
-    guild_tag: &String,
+    guild_id: &i32,
     pool: &PgPool,
-) -> Result<(Option<HttpResponse>, Option<User>)> {
-    let valid_session = validate_session(session, pool).await;
-    match valid_session {
-        Ok(Some(user)) => {
-            if user.is_banned {
-                return Ok((
-                    Some(HttpResponse::Forbidden().body("You are banned.")),
-                    None,
-                ));
+) -> Result<User, ApiError> {
+    let user = policy_user(session, pool).await?;
+    //also make avaiable to site admins
+    if acts_as_site_admin(&user, pool).await? {
+        return Ok(user);
+    }
+    match GuildMembership::find_by_user_and_guild_id(&user.user_id, guild_id, pool).await? {
+        Some(membership) => {
+            if !membership.is_banned {
+                return Ok(user);
             }
-            //also make avaiable to site admins
-            if user.is_admin {
-                return Ok((None, Some(user)));
+            match GuildMembership::find_active_ban(&membership.membership_id, pool).await? {
+                Some(ban) => Err(ApiError::banned("You are banned from this guild.", ban)),
+                None => Ok(user),
             }
-            let is_member =
-                GuildMembership::find_by_user_and_guild_tag(&user.user_id, guild_tag, pool).await;
-            match is_member {
-                Ok(Some(membership)) => {
-                    if membership.is_banned {
-                        Ok((
-                            Some(HttpResponse::Forbidden().body("You are banned from this guild.")),
-                            None,
-                        ))
-                    } else {
-                        Ok((None, Some(user)))
-                    }
-                }
-                Ok(None) => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-                Err(err) => {
-                    error!("Error checking guild membership data on policy: {}", err);
-                    Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
-                }
-            }
-        }
-        Ok(None) => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-        Err(err) => {
-            error!(
-                "Error checking guild membership data on admin policy: {}",
-                err
-            );
-            Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
         }
+        None => Err(ApiError::forbidden("You are not a member of this guild.")),
     }
 }
 
 pub async fn policy_guild_moderator_or_admin(
     session: &Session,
-    guild_tag: &String,
+    guild_id: &i32,
     pool: &PgPool,
-) -> Result<(Option<HttpResponse>, Option<User>)> {
-    let valid_session = validate_session(session, pool).await;
-    match valid_session {
-        Ok(Some(user)) => {
-            if user.is_banned {
-                return Ok((
-                    Some(HttpResponse::Forbidden().body("You are banned.")),
-                    None,
-                ));
-            }
-            //also make avaiable to site admins
-            if user.is_admin {
-                return Ok((None, Some(user)));
-            }
-            let is_member =
-                GuildMembership::find_by_user_and_guild_tag(&user.user_id, guild_tag, pool).await;
-            match is_member {
-                Ok(Some(membership)) => {
-                    if membership.is_admin || membership.is_moderator {
-                        Ok((None, Some(user)))
-                    } else {
-                        Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
-                    }
-                }
-                Ok(None) => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-                Err(err) => {
-                    error!(
-                        "Error checking guild membership data on admin policy: {}",
-                        err
-                    );
-                    Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
-                }
-            }
-        }
-        Ok(None) => Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None)),
-        Err(err) => {
-            error!(
-                "Error checking guild membership data on admin policy: {}",
-                err
-            );
-            Ok((Some(HttpResponse::Forbidden().body("Forbidden.")), None))
-        }
+) -> Result<User, ApiError> {
+    let user = authenticated_user(session, pool).await?;
+    check_moderate_scope(session, guild_id, pool).await?;
+    //also make avaiable to site admins
+    if acts_as_site_admin(&user, pool).await? {
+        return Ok(user);
+    }
+    match GuildMembership::find_by_user_and_guild_id(&user.user_id, guild_id, pool).await? {
+        Some(membership) if membership.is_admin || membership.is_moderator => Ok(user),
+        _ => Err(ApiError::forbidden(
+            "Only guild moderators and admins can do that.",
+        )),
     }
 }
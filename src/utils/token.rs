@@ -0,0 +1,17 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+//lowercase and digits without the easily confused 0, o, 1 and l, so codes can be read out and typed in
+const CHARSET: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+
+pub fn random_token(length: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..length)
+        .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
+//tokens are random enough that a plain sha256 is fine for storing them, unlike passwords
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
@@ -1,7 +1,8 @@
+use crate::utils::api_error::ApiError;
 use crate::utils::session_validation;
 use crate::view::DetailedGuildView;
 use actix_session::Session;
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{get, web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
@@ -16,55 +17,24 @@ pub async fn handler(
     db_pool: web::Data<PgPool>,
     guild_tag: web::Path<String>,
     session: Session,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let formatted_tag = guild_tag.to_string().to_lowercase();
-    let is_guild_member =
-        session_validation::policy_guild_member(&session, &formatted_tag, db_pool.get_ref()).await;
-    match is_guild_member {
-        Ok((None, Some(user))) => {
-            //user is guild member
-            let detailed_guild_view =
-                DetailedGuildView::find_by_guild_tag(&formatted_tag, db_pool.get_ref()).await;
-            match detailed_guild_view {
-                Ok(Some(guild)) => {
-                    let resp = DetailedGuildViewResponse {
-                        is_member: true,
-                        guild_details: guild,
-                    };
-                    HttpResponse::Ok().json(resp)
-                }
-                Ok(None) => return HttpResponse::BadRequest().body("Guild does not exist."),
-                Err(err) => {
-                    error!("Error fetching guild: {}", err);
-                    return HttpResponse::InternalServerError().body("Error fetching guild.");
-                }
-            }
-        }
-        Ok((Some(response), None)) => {
-            //user is not guild member
-            let detailed_guild_view =
-                DetailedGuildView::find_by_guild_tag(&formatted_tag, db_pool.get_ref()).await;
-            match detailed_guild_view {
-                Ok(Some(guild)) => {
-                    let resp = DetailedGuildViewResponse {
-                        is_member: false,
-                        guild_details: guild,
-                    };
-                    HttpResponse::Ok().json(resp)
-                }
-                Ok(None) => return HttpResponse::BadRequest().body("Guild does not exist."),
-                Err(err) => {
-                    error!("Error fetching guild: {}", err);
-                    return HttpResponse::InternalServerError().body("Error fetching guild.");
-                }
-            }
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
-    }
+    let guild = DetailedGuildView::find_by_guild_tag(&formatted_tag, db_pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild does not exist."))?;
+    let is_member = match session_validation::policy_guild_member(
+        &session,
+        &guild.guild_id.unwrap_or(0),
+        db_pool.get_ref(),
+    )
+    .await
+    {
+        Ok(_) => true,
+        Err(ApiError::Internal(err)) => return Err(ApiError::Internal(err)),
+        Err(_) => false,
+    };
+    Ok(HttpResponse::Ok().json(DetailedGuildViewResponse {
+        is_member,
+        guild_details: guild,
+    }))
 }
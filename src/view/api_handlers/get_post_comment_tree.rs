@@ -0,0 +1,25 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::session_validation;
+use crate::view::{CommentTree, CommentTreeForm, DetailedCommentView};
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+#[get("/post/{post_id}/comments")]
+pub async fn handler(
+    db_pool: web::Data<PgPool>,
+    post_id: web::Path<i32>,
+    tree_form: web::Query<CommentTreeForm>,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    let mut tree = CommentTree::find_by_post_id(&post_id, &tree_form, db_pool.get_ref()).await?;
+    if let Some(user) = session_validation::optional_user(&session, db_pool.get_ref()).await? {
+        DetailedCommentView::annotate_for_user(
+            tree.comments_mut(),
+            &user.user_id,
+            db_pool.get_ref(),
+        )
+        .await?;
+    }
+    Ok(HttpResponse::Ok().json(tree))
+}
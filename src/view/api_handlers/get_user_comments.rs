@@ -1,8 +1,9 @@
-use crate::comment_vote::CommentVote;
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{PageForm, PageRequest};
 use crate::utils::session_validation;
 use crate::view::DetailedCommentView;
 use actix_session::Session;
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{get, web, HttpResponse};
 use serde::Deserialize;
 use sqlx::PgPool;
 
@@ -16,82 +17,24 @@ pub struct GetUserComments {
 pub async fn handler(
     db_pool: web::Data<PgPool>,
     request_form: web::Path<GetUserComments>,
+    page_form: web::Query<PageForm>,
     session: Session,
-) -> impl Responder {
-    let is_user = session_validation::policy_user(&session, db_pool.get_ref()).await;
-    match is_user {
-        Ok((None, Some(user))) => {
-            //user
-            let get_comments = DetailedCommentView::get_comments_by_username(
-                &request_form.username,
-                db_pool.get_ref(),
-                &20,
-                &request_form.page_number,
-            )
-            .await;
-            match get_comments {
-                Ok(comments) => {
-                    let mut the_comments: Vec<DetailedCommentView> = Vec::new();
-                    for mut comment in comments {
-                        match &comment.post_id {
-                            Some(comment_id) => {
-                                let is_voted = CommentVote::find_by_comment_and_user_id(
-                                    comment_id,
-                                    &user.user_id,
-                                    db_pool.get_ref(),
-                                )
-                                .await;
-                                match is_voted {
-                                    Ok(Some(vote)) => {
-                                        if vote.up {
-                                            comment.is_upvoted = true;
-                                        } else {
-                                            comment.is_downvoted = true;
-                                        }
-                                    }
-                                    Err(err) => {
-                                        error!("Error fetching votes: {}", err);
-                                        HttpResponse::InternalServerError()
-                                            .body("Error fetching votes.");
-                                    }
-                                    _ => (),
-                                }
-                            }
-                            _ => (),
-                        }
-                        the_comments.push(comment.clone());
-                    }
-                    return HttpResponse::Ok().json(the_comments);
-                }
-                Err(err) => {
-                    error!("Error fetching comments: {}", err);
-                    HttpResponse::InternalServerError().body("Error fetching comments.")
-                }
-            }
-        }
-        Ok((Some(response), None)) => {
-            //not user
-            let get_comments = DetailedCommentView::get_comments_by_username(
-                &request_form.username,
-                db_pool.get_ref(),
-                &20,
-                &request_form.page_number,
-            )
-            .await;
-            match get_comments {
-                Ok(comments) => HttpResponse::Ok().json(comments),
-                Err(err) => {
-                    error!("Error fetching comments: {}", err);
-                    HttpResponse::InternalServerError().body("Error fetching comments.")
-                }
-            }
-        }
-        Err(err) => {
-            error!("Error verifying user session: {}", err);
-            return HttpResponse::InternalServerError().body("Error verifying user session.");
-        }
-        _ => {
-            return HttpResponse::InternalServerError().body("Unknown Error.");
-        }
+) -> Result<HttpResponse, ApiError> {
+    let page = PageRequest::new(&page_form, &request_form.page_number, &20)
+        .map_err(|_| ApiError::validation("Invalid page number or cursor."))?;
+    let mut comments = DetailedCommentView::get_comments_by_username(
+        &request_form.username,
+        db_pool.get_ref(),
+        &page,
+    )
+    .await?;
+    if let Some(user) = session_validation::optional_user(&session, db_pool.get_ref()).await? {
+        DetailedCommentView::annotate_for_user(
+            comments.items.iter_mut().collect(),
+            &user.user_id,
+            db_pool.get_ref(),
+        )
+        .await?;
     }
+    Ok(HttpResponse::Ok().json(comments))
 }
@@ -1,5 +1,6 @@
 pub mod get_all_posts;
 pub mod get_guild_details;
+pub mod get_post_comment_tree;
 pub mod get_post_comments;
 pub mod get_posts_by_guild;
 pub mod get_short_guild_details;
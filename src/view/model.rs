@@ -1,15 +1,61 @@
+use crate::block::Block;
+use crate::comment_vote::CommentVote;
+use crate::post_vote::PostVote;
+use crate::utils::pagination::{Cursor, CursorTimestamp, Page, PageRequest};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use sqlx::types::time::PrimitiveDateTime;
 use sqlx::PgPool;
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum PostSort {
+    Hot,
+    Top,
+    New,
+    Controversial,
+    Rising,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TopWindow {
+    Day,
+    Week,
+    Month,
+    All,
+}
+
+//query string for sorted feeds, eg ?sort=top&time=week. defaults to new, which is how feeds were ordered before
+//there was a choice, and top defaults to all time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PostSortForm {
+    pub sort: Option<PostSort>,
+    pub time: Option<TopWindow>,
+}
+
+impl PostSortForm {
+    pub fn window_hours(&self) -> Option<i32> {
+        match self.time.unwrap_or(TopWindow::All) {
+            TopWindow::Day => Some(24),
+            TopWindow::Week => Some(24 * 7),
+            TopWindow::Month => Some(24 * 30),
+            TopWindow::All => None,
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DetailedPostView {
     pub post_id: Option<i32>,
+    pub guild_id: Option<i32>,
     pub guild_tag: Option<String>,
     pub image_url: Option<String>,
     pub link_url: Option<String>,
     pub title: Option<String>,
     pub body: Option<String>,
+    pub body_html: Option<String>, //sanitised html rendered from body
     pub is_locked: Option<bool>,
     pub is_edited: Option<bool>,
     pub created_at: Option<String>, //time to string
@@ -24,29 +70,42 @@ pub struct DetailedPostView {
     pub is_upvoted: bool,
     pub is_downvoted: bool,
 }
-impl DetailedPostView {
-    pub async fn get_post_by_id(
-        post_id: &i32,
-        pool: &PgPool,
-        results_per_page: &i64,
-        page_number: &i64,
-    ) -> Result<Option<DetailedPostView>> {
-        let post = sqlx::query!(
-            r#"
-            SELECT * FROM detailed_post_view
-            WHERE post_id = $1
-            "#,
-            post_id
-        )
-        .fetch_optional(&*pool)
-        .await?;
-        Ok(post.map(|post| DetailedPostView {
+//a row of detailed_post_view, so every post query can share the conversion below
+struct DetailedPostRow {
+    post_id: Option<i32>,
+    guild_id: Option<i32>,
+    guild_tag: Option<String>,
+    image_url: Option<String>,
+    link_url: Option<String>,
+    title: Option<String>,
+    body: Option<String>,
+    is_locked: Option<bool>,
+    is_edited: Option<bool>,
+    created_at: Option<PrimitiveDateTime>,
+    username: Option<String>,
+    avatar_url: Option<String>,
+    is_admin: Option<bool>,
+    is_verified: Option<bool>,
+    upvotes: Option<i32>,
+    downvotes: Option<i32>,
+    replies: Option<i32>,
+    score: Option<i32>,
+    hot_rank: Option<f64>,
+    controversy_rank: Option<f64>,
+    body_html: Option<String>,
+}
+
+impl From<DetailedPostRow> for DetailedPostView {
+    fn from(post: DetailedPostRow) -> DetailedPostView {
+        DetailedPostView {
             post_id: post.post_id,
+            guild_id: post.guild_id,
             guild_tag: post.guild_tag,
             image_url: post.image_url,
             link_url: post.link_url,
             title: post.title,
             body: post.body,
+            body_html: post.body_html,
             is_locked: post.is_locked,
             is_edited: post.is_edited,
             created_at: post.created_at.map(|c| c.to_string()),
@@ -60,139 +119,317 @@ impl DetailedPostView {
             is_blocked: false,
             is_upvoted: false,
             is_downvoted: false,
-        }))
+        }
     }
-    pub async fn get_posts_by_guild(
-        guild_tag: &String,
+}
+
+impl DetailedPostView {
+    pub async fn get_post_by_id(
+        post_id: &i32,
         pool: &PgPool,
         results_per_page: &i64,
         page_number: &i64,
-    ) -> Result<Vec<DetailedPostView>> {
-        let posts = sqlx::query!(
+    ) -> Result<Option<DetailedPostView>> {
+        let post = sqlx::query_as!(
+            DetailedPostRow,
             r#"
             SELECT * FROM detailed_post_view
-            WHERE guild_tag = $1
-            LIMIT $2
-            OFFSET $3
+            WHERE post_id = $1
             "#,
-            guild_tag,
-            results_per_page,
-            ((page_number - 1) * results_per_page)
+            post_id
         )
-        .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|post| DetailedPostView {
-            post_id: post.post_id,
-            guild_tag: post.guild_tag,
-            image_url: post.image_url,
-            link_url: post.link_url,
-            title: post.title,
-            body: post.body,
-            is_locked: post.is_locked,
-            is_edited: post.is_edited,
-            created_at: post.created_at.map(|c| c.to_string()),
-            username: post.username,
-            avatar_url: post.avatar_url,
-            is_admin: post.is_admin,
-            is_verified: post.is_verified,
-            upvotes: post.upvotes,
-            downvotes: post.downvotes,
-            replies: post.replies,
-            is_blocked: false,
-            is_upvoted: false,
-            is_downvoted: false,
-        })
-        .collect();
-
-        Ok(posts)
+        .fetch_optional(&*pool)
+        .await?;
+        Ok(post.map(DetailedPostView::from))
+    }
+    pub async fn get_posts_by_guild(
+        guild_id: &i32,
+        sort: &PostSortForm,
+        pool: &PgPool,
+        page: &PageRequest,
+    ) -> Result<Page<DetailedPostView>> {
+        DetailedPostView::get_sorted_posts(&Some(*guild_id), sort, pool, page).await
     }
     pub async fn get_all_posts(
+        sort: &PostSortForm,
         pool: &PgPool,
-        results_per_page: &i64,
-        page_number: &i64,
-    ) -> Result<Vec<DetailedPostView>> {
-        let posts = sqlx::query!(
-            r#"
-            SELECT * FROM detailed_post_view
-            LIMIT $1
-            OFFSET $2
-            "#,
-            results_per_page,
-            ((page_number - 1) * results_per_page)
-        )
-        .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|post| DetailedPostView {
-            post_id: post.post_id,
-            guild_tag: post.guild_tag,
-            image_url: post.image_url,
-            link_url: post.link_url,
-            title: post.title,
-            body: post.body,
-            is_locked: post.is_locked,
-            is_edited: post.is_edited,
-            created_at: post.created_at.map(|c| c.to_string()),
-            username: post.username,
-            avatar_url: post.avatar_url,
-            is_admin: post.is_admin,
-            is_verified: post.is_verified,
-            upvotes: post.upvotes,
-            downvotes: post.downvotes,
-            replies: post.replies,
-            is_blocked: false,
-            is_upvoted: false,
-            is_downvoted: false,
-        })
-        .collect();
+        page: &PageRequest,
+    ) -> Result<Page<DetailedPostView>> {
+        DetailedPostView::get_sorted_posts(&None, sort, pool, page).await
+    }
+    //each sort is its own query so it can use its index on post_aggregates or posts.
+    //the cursor is (sort key, post_id), except rising which is recomputed against the clock on every request
+    //and so can only carry an offset
+    async fn get_sorted_posts(
+        guild_id: &Option<i32>,
+        sort: &PostSortForm,
+        pool: &PgPool,
+        page: &PageRequest,
+    ) -> Result<Page<DetailedPostView>> {
+        let (posts, next_cursor) = match sort.sort.unwrap_or(PostSort::New) {
+            PostSort::Hot => {
+                let posts = sqlx::query_as!(
+                    DetailedPostRow,
+                    r#"
+                    SELECT * FROM detailed_post_view
+                    WHERE ($1::INTEGER IS NULL OR guild_id = $1)
+                    AND ($4::FLOAT8 IS NULL OR (hot_rank, post_id) < ($4, $5::INTEGER))
+                    ORDER BY hot_rank DESC, post_id DESC
+                    LIMIT $2
+                    OFFSET $3
+                    "#,
+                    *guild_id,
+                    page.results_per_page,
+                    page.offset(),
+                    page.key::<f64>("hot")?,
+                    page.id::<i32>()?
+                )
+                .fetch_all(pool)
+                .await?;
+                let next_cursor = page.next_cursor(&posts, |post| {
+                    Cursor::new(
+                        "hot",
+                        post.hot_rank.unwrap_or(0.0),
+                        post.post_id.unwrap_or(0),
+                    )
+                });
+                (posts, next_cursor)
+            }
+            PostSort::Top => {
+                let posts = sqlx::query_as!(
+                    DetailedPostRow,
+                    r#"
+                    SELECT * FROM detailed_post_view
+                    WHERE ($1::INTEGER IS NULL OR guild_id = $1)
+                    AND ($4::INTEGER IS NULL OR created_at > LOCALTIMESTAMP - make_interval(hours => $4))
+                    AND ($5::INTEGER IS NULL OR (score, post_id) < ($5, $6::INTEGER))
+                    ORDER BY score DESC, post_id DESC
+                    LIMIT $2
+                    OFFSET $3
+                    "#,
+                    *guild_id,
+                    page.results_per_page,
+                    page.offset(),
+                    sort.window_hours(),
+                    page.key::<i32>("top")?,
+                    page.id::<i32>()?
+                )
+                .fetch_all(pool)
+                .await?;
+                let next_cursor = page.next_cursor(&posts, |post| {
+                    Cursor::new("top", post.score.unwrap_or(0), post.post_id.unwrap_or(0))
+                });
+                (posts, next_cursor)
+            }
+            PostSort::New => {
+                let posts = sqlx::query_as!(
+                    DetailedPostRow,
+                    r#"
+                    SELECT * FROM detailed_post_view
+                    WHERE ($1::INTEGER IS NULL OR guild_id = $1)
+                    AND ($4::TIMESTAMP IS NULL OR (created_at, post_id) < ($4, $5::INTEGER))
+                    ORDER BY created_at DESC, post_id DESC
+                    LIMIT $2
+                    OFFSET $3
+                    "#,
+                    *guild_id,
+                    page.results_per_page,
+                    page.offset(),
+                    page.key::<CursorTimestamp>("new")?.map(|key| key.0),
+                    page.id::<i32>()?
+                )
+                .fetch_all(pool)
+                .await?;
+                let next_cursor = page.next_cursor(&posts, |post| {
+                    Cursor::new(
+                        "new",
+                        post.created_at
+                            .map(|c| CursorTimestamp(c).to_string())
+                            .unwrap_or_default(),
+                        post.post_id.unwrap_or(0),
+                    )
+                });
+                (posts, next_cursor)
+            }
+            PostSort::Controversial => {
+                let posts = sqlx::query_as!(
+                    DetailedPostRow,
+                    r#"
+                    SELECT * FROM detailed_post_view
+                    WHERE ($1::INTEGER IS NULL OR guild_id = $1)
+                    AND ($4::FLOAT8 IS NULL OR (controversy_rank, post_id) < ($4, $5::INTEGER))
+                    ORDER BY controversy_rank DESC, post_id DESC
+                    LIMIT $2
+                    OFFSET $3
+                    "#,
+                    *guild_id,
+                    page.results_per_page,
+                    page.offset(),
+                    page.key::<f64>("controversial")?,
+                    page.id::<i32>()?
+                )
+                .fetch_all(pool)
+                .await?;
+                let next_cursor = page.next_cursor(&posts, |post| {
+                    Cursor::new(
+                        "controversial",
+                        post.controversy_rank.unwrap_or(0.0),
+                        post.post_id.unwrap_or(0),
+                    )
+                });
+                (posts, next_cursor)
+            }
+            PostSort::Rising => {
+                let rising_offset = page.key::<i64>("rising")?.unwrap_or_else(|| page.offset());
+                let posts = sqlx::query_as!(
+                    DetailedPostRow,
+                    r#"
+                    SELECT * FROM detailed_post_view
+                    WHERE ($1::INTEGER IS NULL OR guild_id = $1)
+                    AND created_at > LOCALTIMESTAMP - INTERVAL '1 day'
+                    ORDER BY (score + replies) / power(EXTRACT(EPOCH FROM (LOCALTIMESTAMP - created_at)) / 3600 + 2, 1.5) DESC, post_id DESC
+                    LIMIT $2
+                    OFFSET $3
+                    "#,
+                    *guild_id,
+                    page.results_per_page,
+                    rising_offset
+                )
+                .fetch_all(pool)
+                .await?;
+                let next_cursor = page.next_cursor(&posts, |post| {
+                    Cursor::new(
+                        "rising",
+                        rising_offset + page.results_per_page,
+                        post.post_id.unwrap_or(0),
+                    )
+                });
+                (posts, next_cursor)
+            }
+        };
 
-        Ok(posts)
+        Ok(Page {
+            next_cursor,
+            items: posts.into_iter().map(DetailedPostView::from).collect(),
+        })
     }
     pub async fn get_posts_by_user(
         username: &String,
         pool: &PgPool,
-        results_per_page: &i64,
-        page_number: &i64,
-    ) -> Result<Vec<DetailedPostView>> {
-        let posts = sqlx::query!(
+        page: &PageRequest,
+    ) -> Result<Page<DetailedPostView>> {
+        let posts = sqlx::query_as!(
+            DetailedPostRow,
             r#"
             SELECT * FROM detailed_post_view
             WHERE username = $1
+            AND ($4::TIMESTAMP IS NULL OR (created_at, post_id) < ($4, $5::INTEGER))
+            ORDER BY created_at DESC, post_id DESC
             LIMIT $2
             OFFSET $3
             "#,
             username,
-            results_per_page,
-            ((page_number - 1) * results_per_page)
+            page.results_per_page,
+            page.offset(),
+            page.key::<CursorTimestamp>("new")?.map(|key| key.0),
+            page.id::<i32>()?
         )
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|post| DetailedPostView {
-            post_id: post.post_id,
-            guild_tag: post.guild_tag,
-            image_url: post.image_url,
-            link_url: post.link_url,
-            title: post.title,
-            body: post.body,
-            is_locked: post.is_locked,
-            is_edited: post.is_edited,
-            created_at: post.created_at.map(|c| c.to_string()),
-            username: post.username,
-            avatar_url: post.avatar_url,
-            is_admin: post.is_admin,
-            is_verified: post.is_verified,
-            upvotes: post.upvotes,
-            downvotes: post.downvotes,
-            replies: post.replies,
-            is_blocked: false,
-            is_upvoted: false,
-            is_downvoted: false,
+        .await?;
+
+        Ok(Page {
+            next_cursor: page.next_cursor(&posts, |post| {
+                Cursor::new(
+                    "new",
+                    post.created_at
+                        .map(|c| CursorTimestamp(c).to_string())
+                        .unwrap_or_default(),
+                    post.post_id.unwrap_or(0),
+                )
+            }),
+            items: posts.into_iter().map(DetailedPostView::from).collect(),
         })
-        .collect();
+    }
+    //saved posts are paged by when they were saved, not when they were posted
+    pub async fn get_bookmarked_posts_by_user_id(
+        user_id: &i32,
+        pool: &PgPool,
+        page: &PageRequest,
+    ) -> Result<Page<DetailedPostView>> {
+        let posts = sqlx::query!(
+            r#"
+            SELECT detailed_post_view.*, bookmarks.bookmark_id, bookmarks.created_at AS saved_at FROM detailed_post_view
+            INNER JOIN bookmarks ON bookmarks.post_id = detailed_post_view.post_id
+            WHERE bookmarks.user_id = $1
+            AND ($4::TIMESTAMP IS NULL OR (bookmarks.created_at, bookmarks.bookmark_id) < ($4, $5::INTEGER))
+            ORDER BY bookmarks.created_at DESC, bookmarks.bookmark_id DESC
+            LIMIT $2
+            OFFSET $3
+            "#,
+            user_id,
+            page.results_per_page,
+            page.offset(),
+            page.key::<CursorTimestamp>("saved")?.map(|key| key.0),
+            page.id::<i32>()?
+        )
+        .fetch_all(pool)
+        .await?;
 
-        Ok(posts)
+        Ok(Page {
+            next_cursor: page.next_cursor(&posts, |post| {
+                Cursor::new("saved", CursorTimestamp(post.saved_at), post.bookmark_id)
+            }),
+            items: posts
+                .into_iter()
+                .map(|post| DetailedPostView {
+                    post_id: post.post_id,
+                    guild_id: post.guild_id,
+                    guild_tag: post.guild_tag,
+                    image_url: post.image_url,
+                    link_url: post.link_url,
+                    title: post.title,
+                    body: post.body,
+                    body_html: post.body_html,
+                    is_locked: post.is_locked,
+                    is_edited: post.is_edited,
+                    created_at: post.created_at.map(|c| c.to_string()),
+                    username: post.username,
+                    avatar_url: post.avatar_url,
+                    is_admin: post.is_admin,
+                    is_verified: post.is_verified,
+                    upvotes: post.upvotes,
+                    downvotes: post.downvotes,
+                    replies: post.replies,
+                    is_blocked: false,
+                    is_upvoted: false,
+                    is_downvoted: false,
+                })
+                .collect(),
+        })
+    }
+    //flags posts by authors the user has blocked, and the ones they have voted on
+    pub async fn annotate_for_user(
+        posts: &mut [DetailedPostView],
+        user_id: &i32,
+        pool: &PgPool,
+    ) -> Result<()> {
+        let blocks = Block::find_all_by_user(user_id, pool).await?;
+        for post in posts.iter_mut() {
+            if let Some(username) = &post.username {
+                post.is_blocked = blocks
+                    .iter()
+                    .any(|block| &block.blocked_user_username == username);
+            }
+            if let Some(post_id) = &post.post_id {
+                if let Some(vote) =
+                    PostVote::find_by_post_and_user_id(post_id, user_id, pool).await?
+                {
+                    post.is_upvoted = vote.up;
+                    post.is_downvoted = !vote.up;
+                }
+            }
+        }
+        Ok(())
     }
 }
 
@@ -202,6 +439,7 @@ pub struct DetailedCommentView {
     pub post_id: Option<i32>,
     pub parent_comment_id: Option<i32>,
     pub body: Option<String>,
+    pub body_html: Option<String>, //sanitised html rendered from body
     pub is_edited: Option<bool>,
     pub created_at: Option<String>, //time to string
     pub username: Option<String>,
@@ -233,6 +471,7 @@ impl DetailedCommentView {
             post_id: comment.post_id,
             parent_comment_id: comment.parent_comment_id,
             body: comment.body,
+            body_html: comment.body_html,
             is_edited: comment.is_edited,
             created_at: comment.created_at.map(|c| c.to_string()),
             username: comment.username,
@@ -246,93 +485,346 @@ impl DetailedCommentView {
             is_downvoted: false,
         }))
     }
+    //oldest first, so a thread reads top to bottom
     pub async fn get_comments_by_post_id(
         post_id: &i32,
         pool: &PgPool,
-        results_per_page: &i64,
-        page_number: &i64,
-    ) -> Result<Vec<DetailedCommentView>> {
+        page: &PageRequest,
+    ) -> Result<Page<DetailedCommentView>> {
         let comments = sqlx::query!(
             r#"
             SELECT * FROM detailed_comment_view
             WHERE post_id = $1
+            AND ($4::TIMESTAMP IS NULL OR (created_at, comment_id) > ($4, $5::INTEGER))
+            ORDER BY created_at ASC, comment_id ASC
             LIMIT $2
             OFFSET $3
             "#,
             post_id,
-            results_per_page,
-            ((page_number - 1) * results_per_page)
+            page.results_per_page,
+            page.offset(),
+            page.key::<CursorTimestamp>("old")?.map(|key| key.0),
+            page.id::<i32>()?
         )
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|comment| DetailedCommentView {
-            comment_id: comment.comment_id,
-            post_id: comment.post_id,
-            parent_comment_id: comment.parent_comment_id,
-            body: comment.body,
-            is_edited: comment.is_edited,
-            created_at: comment.created_at.map(|c| c.to_string()),
-            username: comment.username,
-            avatar_url: comment.avatar_url,
-            is_admin: comment.is_admin,
-            is_verified: comment.is_verified,
-            upvotes: comment.upvotes,
-            downvotes: comment.downvotes,
-            is_blocked: false,
-            is_upvoted: false,
-            is_downvoted: false,
-        })
-        .collect();
+        .await?;
 
-        Ok(comments)
+        Ok(Page {
+            next_cursor: page.next_cursor(&comments, |comment| {
+                Cursor::new(
+                    "old",
+                    comment
+                        .created_at
+                        .map(|c| CursorTimestamp(c).to_string())
+                        .unwrap_or_default(),
+                    comment.comment_id.unwrap_or(0),
+                )
+            }),
+            items: comments
+                .into_iter()
+                .map(|comment| DetailedCommentView {
+                    comment_id: comment.comment_id,
+                    post_id: comment.post_id,
+                    parent_comment_id: comment.parent_comment_id,
+                    body: comment.body,
+                    body_html: comment.body_html,
+                    is_edited: comment.is_edited,
+                    created_at: comment.created_at.map(|c| c.to_string()),
+                    username: comment.username,
+                    avatar_url: comment.avatar_url,
+                    is_admin: comment.is_admin,
+                    is_verified: comment.is_verified,
+                    upvotes: comment.upvotes,
+                    downvotes: comment.downvotes,
+                    is_blocked: false,
+                    is_upvoted: false,
+                    is_downvoted: false,
+                })
+                .collect(),
+        })
     }
 
     pub async fn get_comments_by_username(
         username: &String,
         pool: &PgPool,
-        results_per_page: &i64,
-        page_number: &i64,
-    ) -> Result<Vec<DetailedCommentView>> {
+        page: &PageRequest,
+    ) -> Result<Page<DetailedCommentView>> {
         let comments = sqlx::query!(
             r#"
             SELECT * FROM detailed_comment_view
             WHERE username = $1
+            AND ($4::TIMESTAMP IS NULL OR (created_at, comment_id) < ($4, $5::INTEGER))
+            ORDER BY created_at DESC, comment_id DESC
             LIMIT $2
             OFFSET $3
             "#,
             username,
-            results_per_page,
-            ((page_number - 1) * results_per_page)
+            page.results_per_page,
+            page.offset(),
+            page.key::<CursorTimestamp>("new")?.map(|key| key.0),
+            page.id::<i32>()?
         )
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|comment| DetailedCommentView {
-            comment_id: comment.comment_id,
-            post_id: comment.post_id,
-            parent_comment_id: comment.parent_comment_id,
-            body: comment.body,
-            is_edited: comment.is_edited,
-            created_at: comment.created_at.map(|c| c.to_string()),
-            username: comment.username,
-            avatar_url: comment.avatar_url,
-            is_admin: comment.is_admin,
-            is_verified: comment.is_verified,
-            upvotes: comment.upvotes,
-            downvotes: comment.downvotes,
-            is_blocked: false,
-            is_upvoted: false,
-            is_downvoted: false,
+        .await?;
+
+        Ok(Page {
+            next_cursor: page.next_cursor(&comments, |comment| {
+                Cursor::new(
+                    "new",
+                    comment
+                        .created_at
+                        .map(|c| CursorTimestamp(c).to_string())
+                        .unwrap_or_default(),
+                    comment.comment_id.unwrap_or(0),
+                )
+            }),
+            items: comments
+                .into_iter()
+                .map(|comment| DetailedCommentView {
+                    comment_id: comment.comment_id,
+                    post_id: comment.post_id,
+                    parent_comment_id: comment.parent_comment_id,
+                    body: comment.body,
+                    body_html: comment.body_html,
+                    is_edited: comment.is_edited,
+                    created_at: comment.created_at.map(|c| c.to_string()),
+                    username: comment.username,
+                    avatar_url: comment.avatar_url,
+                    is_admin: comment.is_admin,
+                    is_verified: comment.is_verified,
+                    upvotes: comment.upvotes,
+                    downvotes: comment.downvotes,
+                    is_blocked: false,
+                    is_upvoted: false,
+                    is_downvoted: false,
+                })
+                .collect(),
         })
-        .collect();
+    }
+    //same as DetailedPostView::annotate_for_user, takes references so it works on pages and trees alike
+    pub async fn annotate_for_user(
+        comments: Vec<&mut DetailedCommentView>,
+        user_id: &i32,
+        pool: &PgPool,
+    ) -> Result<()> {
+        let blocks = Block::find_all_by_user(user_id, pool).await?;
+        for comment in comments {
+            if let Some(username) = &comment.username {
+                comment.is_blocked = blocks
+                    .iter()
+                    .any(|block| &block.blocked_user_username == username);
+            }
+            if let Some(comment_id) = &comment.comment_id {
+                if let Some(vote) =
+                    CommentVote::find_by_comment_and_user_id(comment_id, user_id, pool).await?
+                {
+                    comment.is_upvoted = vote.up;
+                    comment.is_downvoted = !vote.up;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentSort {
+    Best,
+    Top,
+    New,
+    Old,
+}
+
+impl CommentSort {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CommentSort::Best => "best",
+            CommentSort::Top => "top",
+            CommentSort::New => "new",
+            CommentSort::Old => "old",
+        }
+    }
+}
+
+//query string for comment trees, eg ?sort=best&max_depth=4&limit=20&child_limit=5.
+//parent_comment_id and offset continue a level that was cut off, leave parent_comment_id out for more top level comments
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentTreeForm {
+    pub sort: Option<CommentSort>,
+    pub max_depth: Option<i32>,
+    pub limit: Option<i64>,
+    pub child_limit: Option<i64>,
+    pub parent_comment_id: Option<i32>,
+    pub offset: Option<i64>,
+}
+
+impl CommentTreeForm {
+    pub fn max_depth(&self) -> i32 {
+        self.max_depth.unwrap_or(6).clamp(0, 10)
+    }
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(20).clamp(1, 100)
+    }
+    pub fn child_limit(&self) -> i64 {
+        self.child_limit.unwrap_or(5).clamp(0, 50)
+    }
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentTreeNode {
+    #[serde(flatten)]
+    pub comment: DetailedCommentView,
+    pub depth: i32,
+    pub child_count: i64, //all direct replies, more than children.len() means the client can load more
+    pub children: Vec<CommentTreeNode>,
+}
 
-        Ok(comments)
+impl CommentTreeNode {
+    fn collect_comments<'a>(&'a mut self, comments: &mut Vec<&'a mut DetailedCommentView>) {
+        comments.push(&mut self.comment);
+        for child in self.children.iter_mut() {
+            child.collect_comments(comments);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentTree {
+    pub comments: Vec<CommentTreeNode>,
+    pub total: i64, //comments at the starting level, ie top level comments or replies to parent_comment_id
+    pub next_offset: Option<i64>,
+}
+
+impl CommentTree {
+    //ranks every comment of the post among its siblings once, then walks down from the starting level
+    //keeping the first `limit` (then `child_limit`) siblings of each parent. rows come back depth first by path
+    pub async fn find_by_post_id(
+        post_id: &i32,
+        form: &CommentTreeForm,
+        pool: &PgPool,
+    ) -> Result<CommentTree> {
+        let rows = sqlx::query!(
+            r#"
+            WITH RECURSIVE ranked AS (
+                SELECT detailed_comment_view.*,
+                (SELECT COUNT(*) FROM comments replies WHERE replies.parent_comment_id = detailed_comment_view.comment_id) AS child_count,
+                row_number() OVER (
+                    PARTITION BY parent_comment_id
+                    ORDER BY
+                    CASE WHEN $6 = 'best' THEN comment_best_rank(upvotes, downvotes) END DESC NULLS LAST,
+                    CASE WHEN $6 IN ('best', 'top') THEN upvotes - downvotes END DESC NULLS LAST,
+                    CASE WHEN $6 = 'old' THEN created_at END ASC,
+                    CASE WHEN $6 = 'old' THEN comment_id END ASC,
+                    created_at DESC, comment_id DESC
+                ) AS sibling_rank
+                FROM detailed_comment_view
+                WHERE post_id = $1
+            ), tree AS (
+                SELECT ranked.*, 0 AS depth, ARRAY[ranked.sibling_rank] AS path FROM ranked
+                WHERE parent_comment_id IS NOT DISTINCT FROM $2
+                AND sibling_rank > $4 AND sibling_rank <= $4 + $3
+                UNION ALL
+                SELECT ranked.*, tree.depth + 1, tree.path || ranked.sibling_rank FROM ranked
+                INNER JOIN tree ON ranked.parent_comment_id = tree.comment_id
+                WHERE tree.depth < $5 AND ranked.sibling_rank <= $7
+            )
+            SELECT * FROM tree
+            ORDER BY path
+            "#,
+            post_id,
+            form.parent_comment_id,
+            form.limit(),
+            form.offset(),
+            form.max_depth(),
+            form.sort.unwrap_or(CommentSort::Best).as_str(),
+            form.child_limit()
+        )
+        .fetch_all(pool)
+        .await?;
+
+        //counted on its own, the tree has no rows to read it from once offset is past the end
+        let total = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "total!" FROM detailed_comment_view
+            WHERE post_id = $1 AND parent_comment_id IS NOT DISTINCT FROM $2
+            "#,
+            post_id,
+            form.parent_comment_id
+        )
+        .fetch_one(pool)
+        .await?
+        .total;
+        let next_offset = form.offset() + form.limit();
+
+        //depth first order means every reply comes after its parent, so walking backwards
+        //each node's replies are already collected by the time we reach it
+        let mut replies: HashMap<i32, Vec<CommentTreeNode>> = HashMap::new();
+        let mut comments: Vec<CommentTreeNode> = Vec::new();
+        for comment in rows.into_iter().rev() {
+            let comment_id = comment.comment_id.unwrap_or(0);
+            let depth = comment.depth.unwrap_or(0);
+            let parent_comment_id = comment.parent_comment_id;
+            let mut children = replies.remove(&comment_id).unwrap_or_default();
+            children.reverse();
+            let node = CommentTreeNode {
+                comment: DetailedCommentView {
+                    comment_id: comment.comment_id,
+                    post_id: comment.post_id,
+                    parent_comment_id: comment.parent_comment_id,
+                    body: comment.body,
+                    body_html: comment.body_html,
+                    is_edited: comment.is_edited,
+                    created_at: comment.created_at.map(|c| c.to_string()),
+                    username: comment.username,
+                    avatar_url: comment.avatar_url,
+                    is_admin: comment.is_admin,
+                    is_verified: comment.is_verified,
+                    upvotes: comment.upvotes,
+                    downvotes: comment.downvotes,
+                    is_blocked: false,
+                    is_upvoted: false,
+                    is_downvoted: false,
+                },
+                depth,
+                child_count: comment.child_count.unwrap_or(0),
+                children,
+            };
+            match (depth, parent_comment_id) {
+                (0, _) | (_, None) => comments.push(node),
+                (_, Some(parent_comment_id)) => {
+                    replies.entry(parent_comment_id).or_default().push(node)
+                }
+            }
+        }
+        comments.reverse();
+
+        Ok(CommentTree {
+            comments,
+            total,
+            next_offset: if next_offset < total {
+                Some(next_offset)
+            } else {
+                None
+            },
+        })
+    }
+    //every comment in the tree, for annotating blocks and votes without recursing in the handler
+    pub fn comments_mut(&mut self) -> Vec<&mut DetailedCommentView> {
+        let mut comments = Vec::new();
+        for node in self.comments.iter_mut() {
+            node.collect_comments(&mut comments);
+        }
+        comments
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DetailedGuildView {
+    pub guild_id: Option<i32>,
     pub guild_tag: Option<String>,
     pub guild_name: Option<String>,
     pub guild_description: Option<String>,
@@ -351,13 +843,14 @@ impl DetailedGuildView {
         let guild = sqlx::query!(
             r#"
             SELECT * FROM detailed_guild_view
-            WHERE guild_tag = $1
+            WHERE guild_id = resolve_guild_tag($1)
             "#,
             guild_tag
         )
         .fetch_optional(&*pool)
         .await?;
         Ok(guild.map(|guild| DetailedGuildView {
+            guild_id: guild.guild_id,
             guild_tag: guild.guild_tag,
             guild_name: guild.guild_name,
             guild_description: guild.guild_description,
@@ -373,6 +866,7 @@ impl DetailedGuildView {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ShortGuildView {
+    pub guild_id: Option<i32>,
     pub guild_tag: Option<String>,
     pub guild_name: Option<String>,
     pub avatar_url: Option<String>,
@@ -381,34 +875,45 @@ pub struct ShortGuildView {
     pub is_member: bool,
 }
 impl ShortGuildView {
-    pub async fn find_all(
-        results_per_page: &i64,
-        page_number: &i64,
-        pool: &PgPool,
-    ) -> Result<Vec<ShortGuildView>> {
+    //biggest guilds first, ties broken by the oldest guild
+    pub async fn find_all(page: &PageRequest, pool: &PgPool) -> Result<Page<ShortGuildView>> {
         let guilds = sqlx::query!(
             r#"
             SELECT * FROM short_guild_view
+            WHERE ($3::INTEGER IS NULL OR members < $3 OR (members = $3 AND guild_id > $4))
+            ORDER BY members DESC, guild_id ASC
             LIMIT $1
             OFFSET $2
             "#,
-            results_per_page,
-            ((page_number - 1) * results_per_page)
+            page.results_per_page,
+            page.offset(),
+            page.key::<i32>("members")?,
+            page.id::<i32>()?
         )
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|guild| ShortGuildView {
-            guild_tag: guild.guild_tag,
-            guild_name: guild.guild_name,
-            avatar_url: guild.avatar_url,
-            members: guild.members,
-            number_of_posts: guild.number_of_posts,
-            is_member: false,
-        })
-        .collect();
+        .await?;
 
-        Ok(guilds)
+        Ok(Page {
+            next_cursor: page.next_cursor(&guilds, |guild| {
+                Cursor::new(
+                    "members",
+                    guild.members.unwrap_or(0),
+                    guild.guild_id.unwrap_or(0),
+                )
+            }),
+            items: guilds
+                .into_iter()
+                .map(|guild| ShortGuildView {
+                    guild_id: guild.guild_id,
+                    guild_tag: guild.guild_tag,
+                    guild_name: guild.guild_name,
+                    avatar_url: guild.avatar_url,
+                    members: guild.members,
+                    number_of_posts: guild.number_of_posts,
+                    is_member: false,
+                })
+                .collect(),
+        })
     }
 }
 
@@ -425,6 +930,8 @@ pub struct DetailedUserView {
     pub number_of_posts: Option<i32>,
     pub number_of_comments: Option<i32>,
     pub number_of_memberships: Option<i32>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
 }
 impl DetailedUserView {
     pub async fn find_by_username(
@@ -452,6 +959,8 @@ impl DetailedUserView {
             number_of_posts: user.number_of_posts,
             number_of_comments: user.number_of_comments,
             number_of_memberships: user.number_of_memberships,
+            display_name: user.display_name,
+            bio: user.bio,
         }))
     }
 }
@@ -461,6 +970,8 @@ pub struct UserPersonalView {
     pub username: Option<String>,
     pub email: Option<String>,
     pub avatar_url: Option<String>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
 }
 impl UserPersonalView {
     pub async fn find_by_username(
@@ -480,6 +991,100 @@ impl UserPersonalView {
             username: user.username,
             email: user.email,
             avatar_url: user.avatar_url,
+            display_name: user.display_name,
+            bio: user.bio,
         }))
     }
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DetailedNotificationView {
+    pub notification_id: Option<i32>,
+    pub notification_source: Option<String>, //"comment" or "post", which table the notification lives in
+    pub notification_type: Option<String>,
+    pub is_read: Option<bool>,
+    pub created_at: Option<String>, //time to string
+    pub post_id: Option<i32>,
+    pub post_title: Option<String>,
+    pub guild_tag: Option<String>,
+    pub comment_id: Option<i32>,
+    pub comment_body: Option<String>,
+    pub actor_username: Option<String>, //the user who made the comment or post
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnreadNotificationCount {
+    pub unread: i64,
+}
+
+impl DetailedNotificationView {
+    pub async fn get_notifications_by_user_id(
+        user_id: &i32,
+        pool: &PgPool,
+        page: &PageRequest,
+    ) -> Result<Page<DetailedNotificationView>> {
+        let notis = sqlx::query!(
+            r#"
+            SELECT * FROM detailed_notification_view
+            WHERE user_id = $1
+            AND ($4::TIMESTAMP IS NULL OR (created_at, notification_id) < ($4, $5::INTEGER))
+            ORDER BY created_at DESC, notification_id DESC
+            LIMIT $2
+            OFFSET $3
+            "#,
+            user_id,
+            page.results_per_page,
+            page.offset(),
+            page.key::<CursorTimestamp>("new")?.map(|key| key.0),
+            page.id::<i32>()?
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(Page {
+            next_cursor: page.next_cursor(&notis, |noti| {
+                Cursor::new(
+                    "new",
+                    noti.created_at
+                        .map(|c| CursorTimestamp(c).to_string())
+                        .unwrap_or_default(),
+                    noti.notification_id.unwrap_or(0),
+                )
+            }),
+            items: notis
+                .into_iter()
+                .map(|noti| DetailedNotificationView {
+                    notification_id: noti.notification_id,
+                    notification_source: noti.notification_source,
+                    notification_type: noti.notification_type,
+                    is_read: noti.is_read,
+                    created_at: noti.created_at.map(|c| c.to_string()),
+                    post_id: noti.post_id,
+                    post_title: noti.post_title,
+                    guild_tag: noti.guild_tag,
+                    comment_id: noti.comment_id,
+                    comment_body: noti.comment_body,
+                    actor_username: noti.actor_username,
+                })
+                .collect(),
+        })
+    }
+    pub async fn count_unread_by_user_id(
+        user_id: &i32,
+        pool: &PgPool,
+    ) -> Result<UnreadNotificationCount> {
+        let count = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "unread!" FROM detailed_notification_view
+            WHERE user_id = $1 AND is_read = FALSE
+            "#,
+            user_id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(UnreadNotificationCount {
+            unread: count.unread,
+        })
+    }
+}